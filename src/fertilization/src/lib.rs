@@ -1,4 +1,4 @@
-use genetic::{GenotypeInitializer, Phenotype, Population, crossover};
+use genetic::{GenotypeInitializer, Phenotype, Population, crossover, mutation};
 use log::{debug, info, warn};
 use rand::prelude::*;
 
@@ -92,7 +92,6 @@ const POPULATION_SIZE: usize = 1000;
 const MUTATION_RATE: f64 = 0.3;
 const ROCKET_WIDTH: f32 = 2.0;
 const ROCKET_HEIGHT: f32 = 20.0;
-const TITLE: &str = "Fertilization";
 const SPEED: f32 = 4.0;
 // const FONT_SIZE: f32 = 42.0; // Text is now printed to console
 
@@ -229,7 +228,7 @@ pub struct DrawInfo {
     color: Color,
 }
 
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
 pub struct Angle(f32);
 
 impl Phenotype for SpermEvolver {
@@ -270,22 +269,17 @@ impl Phenotype for SpermEvolver {
         }
     }
 
-    fn mutate(genotype: &mut [Angle], rng: &mut ThreadRng) {
-        for gene in genotype.iter_mut() {
-            if rng.random_bool(MUTATION_RATE) {
-                let delta_angle: f32 = rng.random_range(-10.0..=10.0);
-                gene.0 = (gene.0 + delta_angle).rem_euclid(360.0);
-            }
-        }
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [Angle], rng: &mut R) {
+        mutation::creep(genotype, MUTATION_RATE, 10.0, rng);
     }
 
-    fn crossover(
+    fn crossover<R: Rng + ?Sized>(
         parent1: &[Angle],
         parent2: &[Angle],
         child1: &mut [Angle],
         child2: &mut [Angle],
         size: usize,
-        rng: &mut ThreadRng,
+        rng: &mut R,
     ) {
         crossover::double_split(parent1, parent2, child1, child2, size, rng);
     }
@@ -306,8 +300,18 @@ impl Phenotype for SpermEvolver {
     }
 }
 
+/// Angles wrap around to `0..360`.
+impl crossover::RealGene for Angle {
+    fn to_f64(self) -> f64 {
+        self.0 as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        Angle((value as f32).rem_euclid(360.0))
+    }
+}
+
 impl GenotypeInitializer for Angle {
-    fn initial_genotypes(genotype: &mut [Self], rng: &mut ThreadRng) {
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [Self], rng: &mut R) {
         let mut angle: i32 = rng.random_range(0..=360);
         for gene in genotype.iter_mut() {
             let delta_angle: i32 = rng.random_range(-10..=10);
@@ -529,7 +533,7 @@ impl State {
 
         let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Instance Buffer"),
-            size: (std::mem::size_of::<InstanceRaw>() * 1) as u64, // TODO: was max instances
+            size: std::mem::size_of::<InstanceRaw>() as u64, // TODO: was max instances
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -886,41 +890,33 @@ impl State {
 
         // Population
         if self.show_winner_path {
-            if let Some(population) = &self.population {
-                if let Some(winner) = population
-                    .get_phenotypes()
-                    .iter()
-                    .max_by(|a, b| a.get_fitness().partial_cmp(&b.get_fitness()).unwrap())
-                {
-                    let genotype = population.get_genotype(winner);
-                    draw_infos.extend(SpermEvolver::get_winner_path_draw_info(
-                        self.screen_height,
-                        genotype,
-                    ));
-                }
+            if let Some(population) = &self.population
+                && let Some(winner) = population.fittest_phenotype()
+            {
+                let genotype = population.get_genotype(winner);
+                draw_infos.extend(SpermEvolver::get_winner_path_draw_info(
+                    self.screen_height,
+                    genotype,
+                ));
             }
         } else {
             if let Some(population) = &mut self.population {
                 population.for_each_phenotype_mut(|p, genotype| {
-                    if let Some(info) = p.get_draw_info(&genotype[self.round as usize]) {
-                        if p.index() % 10 == 0 || self.fast {
-                            // Don't draw all 1000 unless in fast mode
-                            draw_infos.push(info);
-                        }
-                    }
-                });
-                if self.fast {
-                    if let Some(winner) = population
-                        .get_phenotypes()
-                        .iter()
-                        .max_by(|a, b| a.get_fitness().partial_cmp(&b.get_fitness()).unwrap())
+                    // Don't draw all 1000 unless in fast mode
+                    if let Some(info) = p.get_draw_info(&genotype[self.round as usize])
+                        && (p.index() % 10 == 0 || self.fast)
                     {
-                        let genotype = population.get_genotype(winner);
-                        draw_infos.extend(SpermEvolver::get_winner_path_draw_info(
-                            self.screen_height,
-                            genotype,
-                        ));
+                        draw_infos.push(info);
                     }
+                });
+                if self.fast
+                    && let Some(winner) = population.fittest_phenotype()
+                {
+                    let genotype = population.get_genotype(winner);
+                    draw_infos.extend(SpermEvolver::get_winner_path_draw_info(
+                        self.screen_height,
+                        genotype,
+                    ));
                 }
             }
         }
@@ -948,6 +944,13 @@ impl App {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ApplicationHandler<UserEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         #[cfg(target_arch = "wasm32")]
@@ -0,0 +1,489 @@
+//! # Benchmark Problems
+//!
+//! Standard test problems to compare operators and settings by numbers. Every
+//! problem is an [`Evaluator`] that maximises its fitness, so the cost
+//! functions return their negated cost. The optimum of each problem is given
+//! in its documentation.
+//!
+//! | Problem        | Genes    | Parameter          | Optimum               |
+//! |----------------|----------|--------------------|-----------------------|
+//! | [`OneMax`]     | `bool`   | `()`               | number of genes       |
+//! | [`Trap`]       | `bool`   | block size         | number of genes       |
+//! | [`RoyalRoad`]  | `bool`   | block size         | number of genes       |
+//! | [`Rastrigin`]  | [`Unit`] | `()`               | 0                     |
+//! | [`Rosenbrock`] | [`Unit`] | `()`               | 0                     |
+//! | [`Ackley`]     | [`Unit`] | `()`               | 0                     |
+//! | [`Knapsack`]   | `bool`   | [`Items`]          | depends on the items  |
+//! | [`Tsp`]        | [`City`] | [`Cities`]         | `-Cities::optimum`    |
+//!
+//! [`convergence`] evolves a population over many seeds and averages the
+//! best fitness of every generation. The `benchmark` binary runs all problems
+//! with several configurations and writes the result as CSV:
+//!
+//! ```text
+//! cargo run --release -p genetic --bin benchmark -- 30 200 > benchmark.csv
+//! ```
+
+use std::f64::consts::{E as EULER, PI};
+
+use rand::prelude::*;
+
+use crate::{
+    Evaluator, Gene, Genome, GenotypeInitializer, Individual, Population, crossover, mutation,
+};
+
+/// Bit strings with bit flip mutation and uniform crossover.
+///
+/// Without a mutation schedule every bit flips with probability `1 / n`.
+#[derive(Debug)]
+pub struct Bits;
+
+impl Genome for Bits {
+    type Gene = bool;
+
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [bool], rng: &mut R) {
+        let rate = 1.0 / genotype.len().max(1) as f64;
+        mutation::bit_flip(genotype, rate, rng);
+    }
+
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [bool], rate: f64, rng: &mut R) {
+        mutation::bit_flip(genotype, rate, rng);
+    }
+
+    fn crossover<R: Rng + ?Sized>(
+        parent1: &[bool],
+        parent2: &[bool],
+        child1: &mut [bool],
+        child2: &mut [bool],
+        size: usize,
+        rng: &mut R,
+    ) {
+        crossover::uniform(parent1, parent2, child1, child2, size, rng);
+    }
+}
+
+crate::bounded_gene! {
+    /// A coordinate in `0..=1`, mapped onto the domain of a real-valued problem.
+    pub struct Unit(f64): uniform(0.0, 1.0);
+}
+
+/// Real vectors with Gaussian mutation and simulated binary crossover.
+///
+/// Without a mutation schedule every gene mutates with probability `1 / n`
+/// and a standard deviation of 0.05.
+#[derive(Debug)]
+pub struct Real;
+
+impl Genome for Real {
+    type Gene = Unit;
+
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [Unit], rng: &mut R) {
+        let rate = 1.0 / genotype.len().max(1) as f64;
+        mutation::gaussian(genotype, rate, 0.05, rng);
+    }
+
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [Unit], rate: f64, rng: &mut R) {
+        mutation::gaussian(genotype, rate, 0.05, rng);
+    }
+
+    fn crossover<R: Rng + ?Sized>(
+        parent1: &[Unit],
+        parent2: &[Unit],
+        child1: &mut [Unit],
+        child2: &mut [Unit],
+        size: usize,
+        rng: &mut R,
+    ) {
+        crossover::simulated_binary(parent1, parent2, child1, child2, size, 15.0, rng);
+    }
+
+    fn distance(a: &[Unit], b: &[Unit]) -> f64 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x.0 - y.0).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// The index of a city in a tour.
+///
+/// Initialising a genotype of cities creates a random permutation of
+/// `0..n`, so every genotype visits each of its `n` cities once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct City(pub u16);
+
+impl GenotypeInitializer for City {
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [City], rng: &mut R) {
+        for (i, gene) in genotype.iter_mut().enumerate() {
+            *gene = City(i as u16);
+        }
+        genotype.shuffle(rng);
+    }
+}
+
+/// Permutations with inversion mutation and order crossover.
+///
+/// Without a mutation schedule a random segment is reversed with
+/// probability 0.5.
+#[derive(Debug)]
+pub struct Permutation;
+
+impl Genome for Permutation {
+    type Gene = City;
+
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [City], rng: &mut R) {
+        mutation::inversion(genotype, 0.5, rng);
+    }
+
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [City], rate: f64, rng: &mut R) {
+        mutation::inversion(genotype, rate, rng);
+    }
+
+    fn crossover<R: Rng + ?Sized>(
+        parent1: &[City],
+        parent2: &[City],
+        child1: &mut [City],
+        child2: &mut [City],
+        size: usize,
+        rng: &mut R,
+    ) {
+        crossover::ordered(parent1, parent2, child1, child2, size, rng);
+    }
+}
+
+/// Defines an evaluator that stores the fitness computed from the genotype
+/// and the parameter.
+macro_rules! benchmark {
+    (
+        $(#[$meta:meta])*
+        $name:ident: $genome:ty, $param:ty, |$genotype:ident, $p:pat_param| $fitness:expr
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        pub struct $name {
+            fitness: f64,
+        }
+
+        impl Evaluator for $name {
+            type Genome = $genome;
+            type FitnessParam = $param;
+
+            fn new(_individual: Individual) -> Self {
+                Self { fitness: 0.0 }
+            }
+
+            fn evaluate(&mut self, $genotype: &[Gene<Self>], $p: &$param) {
+                self.fitness = $fitness;
+            }
+
+            fn fitness(&self) -> f64 {
+                self.fitness
+            }
+        }
+    };
+}
+
+benchmark! {
+    /// The number of set bits. The optimum sets all of them.
+    OneMax: Bits, (), |genotype, _| genotype.iter().filter(|&&bit| bit).count() as f64
+}
+
+benchmark! {
+    /// Concatenated deceptive traps (Deb & Goldberg).
+    ///
+    /// The genotype is split into blocks of `k` bits. A block with all bits
+    /// set scores `k`, any other block `k - 1 - ones`, so within a block the
+    /// fitness leads away from the optimum towards all bits cleared.
+    Trap: Bits, usize, |genotype, &k| {
+        let k = k.max(1);
+        genotype
+            .chunks(k)
+            .map(|block| {
+                let ones = block.iter().filter(|&&bit| bit).count();
+                if ones == block.len() {
+                    ones as f64
+                } else {
+                    (block.len() - 1 - ones) as f64
+                }
+            })
+            .sum()
+    }
+}
+
+benchmark! {
+    /// Royal road R1 (Mitchell, Forrest & Holland).
+    ///
+    /// The genotype is split into blocks of `k` bits. Every block with all
+    /// bits set scores `k`, partially set blocks score nothing, so there is
+    /// no gradient to follow within a block.
+    RoyalRoad: Bits, usize, |genotype, &k| {
+        genotype
+            .chunks(k.max(1))
+            .filter(|block| block.iter().all(|&bit| bit))
+            .map(|block| block.len() as f64)
+            .sum()
+    }
+}
+
+/// Maps a coordinate from `0..=1` onto `low..=high`.
+fn scale(gene: Unit, low: f64, high: f64) -> f64 {
+    low + gene.0 * (high - low)
+}
+
+benchmark! {
+    /// The negated Rastrigin function on `-5.12..=5.12`, highly multimodal
+    /// with a regular grid of local optima. The optimum 0 is at the origin,
+    /// the centre of the unit cube.
+    Rastrigin: Real, (), |genotype, _| {
+        -genotype
+            .iter()
+            .map(|&gene| {
+                let x = scale(gene, -5.12, 5.12);
+                x * x - 10.0 * (2.0 * PI * x).cos() + 10.0
+            })
+            .sum::<f64>()
+    }
+}
+
+benchmark! {
+    /// The negated Rosenbrock function on `-2.048..=2.048`, a narrow curved
+    /// valley. The optimum 0 is at `(1, …, 1)`.
+    Rosenbrock: Real, (), |genotype, _| {
+        -genotype
+            .windows(2)
+            .map(|pair| {
+                let x = scale(pair[0], -2.048, 2.048);
+                let y = scale(pair[1], -2.048, 2.048);
+                100.0 * (y - x * x).powi(2) + (1.0 - x).powi(2)
+            })
+            .sum::<f64>()
+    }
+}
+
+benchmark! {
+    /// The negated Ackley function on `-32.768..=32.768`, a nearly flat outer
+    /// region around a deep funnel. The optimum 0 is at the origin.
+    Ackley: Real, (), |genotype, _| {
+        let n = genotype.len().max(1) as f64;
+        let (squares, cosines) = genotype.iter().fold((0.0, 0.0), |(s, c), &gene| {
+            let x = scale(gene, -32.768, 32.768);
+            (s + x * x, c + (2.0 * PI * x).cos())
+        });
+        20.0 * (-0.2 * (squares / n).sqrt()).exp() + (cosines / n).exp() - 20.0 - EULER
+    }
+}
+
+/// The items of a 0/1 knapsack problem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Items {
+    pub weights: Vec<f64>,
+    pub values: Vec<f64>,
+    pub capacity: f64,
+}
+
+impl Items {
+    /// `count` items with weights and values drawn uniformly from `1..=20`
+    /// and a capacity of half their total weight.
+    pub fn random<R: Rng + ?Sized>(count: usize, rng: &mut R) -> Self {
+        let weights: Vec<f64> = (0..count)
+            .map(|_| rng.random_range(1..=20) as f64)
+            .collect();
+        let values = (0..count)
+            .map(|_| rng.random_range(1..=20) as f64)
+            .collect();
+        let capacity = weights.iter().sum::<f64>() / 2.0;
+        Self {
+            weights,
+            values,
+            capacity,
+        }
+    }
+}
+
+benchmark! {
+    /// The 0/1 knapsack problem: gene `i` packs item `i`. A packing within
+    /// the capacity scores its total value, an overweight packing scores
+    /// minus its excess weight.
+    Knapsack: Bits, Items, |genotype, items| {
+        let (weight, value) = genotype
+            .iter()
+            .zip(items.weights.iter().zip(&items.values))
+            .filter(|(packed, _)| **packed)
+            .fold((0.0, 0.0), |(w, v), (_, (weight, value))| (w + weight, v + value));
+        if weight <= items.capacity {
+            value
+        } else {
+            items.capacity - weight
+        }
+    }
+}
+
+/// The cities of a travelling salesman problem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cities(pub Vec<(f64, f64)>);
+
+impl Cities {
+    /// `count` cities evenly spaced on the unit circle. The shortest tour
+    /// follows the circle, so its length is known, see [`Cities::optimum`].
+    pub fn circle(count: usize) -> Self {
+        Self(
+            (0..count)
+                .map(|i| {
+                    let angle = 2.0 * PI * i as f64 / count as f64;
+                    (angle.cos(), angle.sin())
+                })
+                .collect(),
+        )
+    }
+
+    /// `count` cities drawn uniformly from the unit square.
+    pub fn random<R: Rng + ?Sized>(count: usize, rng: &mut R) -> Self {
+        Self((0..count).map(|_| (rng.random(), rng.random())).collect())
+    }
+
+    /// The length of the closed tour visiting the cities in this order.
+    pub fn tour_length(&self, tour: &[City]) -> f64 {
+        let distance = |a: City, b: City| {
+            let (a, b) = (self.0[a.0 as usize], self.0[b.0 as usize]);
+            ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+        };
+        tour.iter()
+            .zip(tour.iter().cycle().skip(1))
+            .map(|(&a, &b)| distance(a, b))
+            .sum()
+    }
+
+    /// The length of the shortest tour for [`Cities::circle`].
+    pub fn optimum(&self) -> f64 {
+        let n = self.0.len() as f64;
+        2.0 * n * (PI / n).sin()
+    }
+}
+
+benchmark! {
+    /// The travelling salesman problem: the genotype is the order in which
+    /// the cities are visited, the fitness the negated length of the closed
+    /// tour. The genotype size must equal the number of cities.
+    Tsp: Permutation, Cities, |genotype, cities| -cities.tour_length(genotype)
+}
+
+/// The progress of a configuration averaged over several runs, one entry per
+/// generation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Convergence {
+    /// The number of runs.
+    pub runs: usize,
+    /// The best fitness found so far, averaged over the runs.
+    pub mean_best: Vec<f64>,
+    /// The best fitness found so far by any run.
+    pub max_best: Vec<f64>,
+    /// The mean fitness of the population, averaged over the runs.
+    pub mean_fitness: Vec<f64>,
+    /// The fitness evaluations performed so far, averaged over the runs.
+    pub evaluations: Vec<f64>,
+}
+
+/// Evolves the population built by `population` for every seed over
+/// `generations` generations and averages the progress.
+pub fn convergence<E: Evaluator, R: Rng>(
+    seeds: impl IntoIterator<Item = u64>,
+    generations: usize,
+    param: &E::FitnessParam,
+    mut population: impl FnMut(u64) -> Population<E, R>,
+) -> Convergence {
+    let mut result = Convergence {
+        runs: 0,
+        mean_best: vec![0.0; generations],
+        max_best: vec![f64::NEG_INFINITY; generations],
+        mean_fitness: vec![0.0; generations],
+        evaluations: vec![0.0; generations],
+    };
+
+    for seed in seeds {
+        let mut population = population(seed);
+        let mut best = f64::NEG_INFINITY;
+        for generation in 0..generations {
+            population.evolve(param);
+            let statistics = population
+                .statistics()
+                .expect("a generation has been evaluated");
+            best = best.max(statistics.max_fitness);
+            result.mean_best[generation] += best;
+            result.max_best[generation] = result.max_best[generation].max(best);
+            result.mean_fitness[generation] += statistics.mean_fitness;
+            result.evaluations[generation] += statistics.evaluations as f64;
+        }
+        result.runs += 1;
+    }
+
+    let runs = result.runs.max(1) as f64;
+    for sum in result
+        .mean_best
+        .iter_mut()
+        .chain(&mut result.mean_fitness)
+        .chain(&mut result.evaluations)
+    {
+        *sum /= runs;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fitness<E: Evaluator>(genotype: &[Gene<E>], param: &E::FitnessParam) -> f64 {
+        let mut evaluator = E::new(Individual(0));
+        evaluator.evaluate(genotype, param);
+        evaluator.fitness()
+    }
+
+    /// The gene that `scale` maps onto `x`.
+    fn unit(x: f64, low: f64, high: f64) -> Unit {
+        Unit((x - low) / (high - low))
+    }
+
+    #[test]
+    fn bit_benchmarks_peak_at_all_ones() {
+        let ones = [true; 12];
+        assert_eq!(fitness::<OneMax>(&ones, &()), 12.0);
+        assert_eq!(fitness::<Trap>(&ones, &4), 12.0);
+        assert_eq!(fitness::<RoyalRoad>(&ones, &4), 12.0);
+
+        // the deceptive attractor of a trap is one point short per block
+        assert_eq!(fitness::<Trap>(&[false; 12], &4), 9.0);
+        let mut one_off = ones;
+        one_off[0] = false;
+        assert_eq!(fitness::<Trap>(&one_off, &4), 8.0);
+        assert_eq!(fitness::<RoyalRoad>(&one_off, &4), 8.0);
+        assert_eq!(fitness::<OneMax>(&one_off, &()), 11.0);
+    }
+
+    #[test]
+    fn real_benchmarks_reach_zero_at_their_optimum() {
+        assert!(fitness::<Rastrigin>(&[Unit(0.5); 5], &()).abs() < 1e-9);
+        assert!(fitness::<Ackley>(&[Unit(0.5); 5], &()).abs() < 1e-9);
+        assert!(fitness::<Rosenbrock>(&[unit(1.0, -2.048, 2.048); 5], &()).abs() < 1e-9);
+
+        // and are worse anywhere else
+        let off = [Unit(0.6); 5];
+        assert!(fitness::<Rastrigin>(&off, &()) < -1.0);
+        assert!(fitness::<Ackley>(&off, &()) < -1.0);
+        assert!(fitness::<Rosenbrock>(&off, &()) < -1.0);
+    }
+
+    #[test]
+    fn circle_tour_in_order_is_optimal() {
+        for n in [4, 8, 50] {
+            let cities = Cities::circle(n);
+            let identity: Vec<City> = (0..n as u16).map(City).collect();
+            assert!((cities.tour_length(&identity) - cities.optimum()).abs() < 1e-9);
+            assert!((fitness::<Tsp>(&identity, &cities) + cities.optimum()).abs() < 1e-9);
+
+            let mut crossing = identity.clone();
+            crossing.swap(1, 2);
+            assert!(cities.tour_length(&crossing) > cities.optimum());
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Runs every benchmark problem with several configurations over many seeds
+//! and writes the averaged convergence as CSV to stdout.
+//!
+//! ```text
+//! benchmark [seeds] [generations]
+//! ```
+//!
+//! The seeds default to 30, the generations to 200. Every row holds one
+//! generation of one problem and configuration.
+
+use std::io::{self, Write};
+
+use genetic::{
+    Evaluator, Population,
+    benchmarks::{self, Cities, Convergence, Items},
+    selection::{Rank, RouletteWheel, Tournament},
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const POPULATION: usize = 100;
+const ELITISM: usize = 2;
+
+/// The settings compared on every problem.
+#[derive(Debug, Clone, Copy)]
+enum Config {
+    Tournament2,
+    Tournament5,
+    Rank,
+    Roulette,
+}
+
+impl Config {
+    const ALL: [Config; 4] = [
+        Config::Tournament2,
+        Config::Tournament5,
+        Config::Rank,
+        Config::Roulette,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Config::Tournament2 => "tournament2",
+            Config::Tournament5 => "tournament5",
+            Config::Rank => "rank",
+            Config::Roulette => "roulette",
+        }
+    }
+
+    fn population<E: Evaluator>(self, genotype_size: usize, seed: u64) -> Population<E> {
+        let population =
+            Population::with_seed(POPULATION, genotype_size, seed).with_elitism(ELITISM);
+        match self {
+            Config::Tournament2 => population.with_selection(Tournament::new(2)),
+            Config::Tournament5 => population.with_selection(Tournament::new(5)),
+            Config::Rank => population.with_selection(Rank::default()),
+            Config::Roulette => population.with_selection(RouletteWheel),
+        }
+    }
+}
+
+struct Runner<W> {
+    out: W,
+    seeds: u64,
+    generations: usize,
+}
+
+impl<W: Write> Runner<W> {
+    fn problem<E: Evaluator>(
+        &mut self,
+        name: &str,
+        genotype_size: usize,
+        param: &E::FitnessParam,
+    ) -> io::Result<()> {
+        for config in Config::ALL {
+            let convergence =
+                benchmarks::convergence(0..self.seeds, self.generations, param, |seed| {
+                    config.population::<E>(genotype_size, seed)
+                });
+            self.write(name, config, &convergence)?;
+        }
+        eprintln!("{name} done");
+        Ok(())
+    }
+
+    fn write(
+        &mut self,
+        problem: &str,
+        config: Config,
+        convergence: &Convergence,
+    ) -> io::Result<()> {
+        for generation in 0..convergence.mean_best.len() {
+            writeln!(
+                self.out,
+                "{problem},{},{generation},{},{},{},{}",
+                config.name(),
+                convergence.mean_best[generation],
+                convergence.max_best[generation],
+                convergence.mean_fitness[generation],
+                convergence.evaluations[generation],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let seeds = args.next().and_then(|s| s.parse().ok()).unwrap_or(30);
+    let generations = args.next().and_then(|s| s.parse().ok()).unwrap_or(200);
+
+    let mut runner = Runner {
+        out: io::BufWriter::new(io::stdout().lock()),
+        seeds,
+        generations,
+    };
+    writeln!(
+        runner.out,
+        "problem,config,generation,mean_best,max_best,mean_fitness,evaluations"
+    )?;
+
+    let items = Items::random(50, &mut ChaCha8Rng::seed_from_u64(0));
+    runner.problem::<benchmarks::OneMax>("onemax", 100, &())?;
+    runner.problem::<benchmarks::Trap>("trap", 40, &4)?;
+    runner.problem::<benchmarks::RoyalRoad>("royal_road", 64, &8)?;
+    runner.problem::<benchmarks::Rastrigin>("rastrigin", 10, &())?;
+    runner.problem::<benchmarks::Rosenbrock>("rosenbrock", 10, &())?;
+    runner.problem::<benchmarks::Ackley>("ackley", 10, &())?;
+    runner.problem::<benchmarks::Knapsack>("knapsack", 50, &items)?;
+    runner.problem::<benchmarks::Tsp>("tsp", 20, &Cities::circle(20))?;
+    runner.out.flush()
+}
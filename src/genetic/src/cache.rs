@@ -0,0 +1,339 @@
+//! Memoisation of fitness values, see `Population::with_fitness_cache`.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use rand::prelude::*;
+
+use crate::{Evaluator, Gene, Population, constraint};
+
+/// Fitness values of recently evaluated genotypes, together with their
+/// constraint violation (see `Population::with_constraints`), 0 without
+/// constraint handling.
+///
+/// Entries live in two generations of maps: once `current` holds `capacity`
+/// genotypes, it becomes `previous` and the old `previous` is dropped. Hits
+/// in `previous` move back into `current`, so genotypes that keep showing
+/// up, like the elites, are never evicted.
+#[derive(Debug)]
+pub(crate) struct FitnessCache<G> {
+    capacity: usize,
+    hash: fn(&[G]) -> u64,
+    current: HashMap<u64, (Vec<G>, Cached)>,
+    previous: HashMap<u64, (Vec<G>, Cached)>,
+    /// What is known about every slot of the generation being evaluated.
+    pub(crate) known: Vec<Lookup>,
+    hits: usize,
+}
+
+/// What is remembered about an evaluated genotype.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Cached {
+    fitness: f64,
+    violation: f64,
+}
+
+/// The result of looking up the genotype of a slot.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Lookup {
+    /// The genotype has to be evaluated.
+    Miss,
+    /// The genotype has been evaluated before.
+    Hit(Cached),
+    /// The genotype is a clone of the one in an earlier slot of the same
+    /// generation, which is evaluated instead.
+    Duplicate(usize),
+}
+
+impl Lookup {
+    pub(crate) fn needs_evaluation(known: &[Lookup], slot: usize) -> bool {
+        !matches!(known.get(slot), Some(Lookup::Hit(_) | Lookup::Duplicate(_)))
+    }
+}
+
+impl<G: Copy + PartialEq> FitnessCache<G> {
+    fn new(capacity: usize, hash: fn(&[G]) -> u64) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            hash,
+            current: HashMap::new(),
+            previous: HashMap::new(),
+            known: Vec::new(),
+            hits: 0,
+        }
+    }
+
+    fn get(&mut self, key: u64, genotype: &[G]) -> Option<Cached> {
+        if let Some((cached, values)) = self.current.get(&key) {
+            return (cached == genotype).then_some(*values);
+        }
+        let (cached, values) = self.previous.remove(&key)?;
+        if cached != genotype {
+            return None;
+        }
+        self.insert_entry(key, cached, values);
+        Some(values)
+    }
+
+    fn insert_entry(&mut self, key: u64, genotype: Vec<G>, values: Cached) {
+        if self.current.len() >= self.capacity {
+            self.previous = std::mem::take(&mut self.current);
+        }
+        // a colliding genotype simply replaces the older entry
+        self.current.insert(key, (genotype, values));
+    }
+}
+
+/// What the cache knows about every slot, empty without a cache.
+pub(crate) fn known<G>(cache: &Option<FitnessCache<G>>) -> &[Lookup] {
+    cache.as_ref().map_or(&[], |cache| cache.known.as_slice())
+}
+
+fn hash_genotype<G: Hash>(genotype: &[G]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    genotype.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R>
+where
+    Gene<E>: Hash,
+{
+    /// Remembers the fitness of the last evaluated genotypes, up to about
+    /// `capacity` of them, and reuses it for identical genotypes instead of
+    /// calling [`Evaluator::evaluate`] again. Elites and unmutated children
+    /// are then evaluated only once.
+    ///
+    /// Only suited for phenotypes whose fitness depends on nothing but the
+    /// genotype. On a hit the phenotype is left as it is, so its own
+    /// `get_fitness` may describe an older genotype, while the population's
+    /// fitness values and statistics use the cached value.
+    ///
+    /// The cache is keyed on the genotype alone, not on the fitness
+    /// parameter. Call [`Population::clear_fitness_cache`] whenever the
+    /// parameter passed to `evolve` changes, e.g. a new target or maze.
+    pub fn with_fitness_cache(mut self, capacity: usize) -> Self {
+        self.fitness_cache = Some(FitnessCache::new(capacity, hash_genotype::<Gene<E>>));
+        self
+    }
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// The number of calls to [`Evaluator::evaluate`] so far. Cache hits are
+    /// not counted.
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
+    }
+
+    /// The number of fitness values taken from the cache so far.
+    pub fn cache_hits(&self) -> usize {
+        self.fitness_cache.as_ref().map_or(0, |cache| cache.hits)
+    }
+
+    /// Forgets every cached fitness value, so that every genotype is
+    /// evaluated again. Needed once the fitness parameter changes, as the
+    /// cached values were calculated with the old one.
+    pub fn clear_fitness_cache(&mut self) {
+        if let Some(cache) = self.fitness_cache.as_mut() {
+            cache.current.clear();
+            cache.previous.clear();
+        }
+    }
+
+    /// Looks up the fitness of every genotype of the current generation,
+    /// before the phenotypes are evaluated.
+    pub(crate) fn lookup_cached_fitness(&mut self) {
+        let Some(cache) = self.fitness_cache.as_mut() else {
+            return;
+        };
+        cache.known.clear();
+        let mut misses: HashMap<u64, usize> = HashMap::new();
+        for (slot, genotype) in self.genotype_arena.chunks(self.genotype_size).enumerate() {
+            let key = (cache.hash)(genotype);
+            let lookup = match cache.get(key, genotype) {
+                Some(values) => Lookup::Hit(values),
+                None => match misses.get(&key) {
+                    Some(&first)
+                        if self.genotype_arena[first * self.genotype_size..]
+                            [..self.genotype_size]
+                            == *genotype =>
+                    {
+                        Lookup::Duplicate(first)
+                    }
+                    _ => {
+                        misses.insert(key, slot);
+                        Lookup::Miss
+                    }
+                },
+            };
+            cache.known.push(lookup);
+        }
+    }
+
+    /// Overwrites the fitness and violation of every cached slot with the
+    /// cached values, stores the others and counts the evaluations. Called
+    /// once the fitness and violations of the evaluated phenotypes have been
+    /// collected.
+    pub(crate) fn merge_cached_fitness(&mut self) {
+        let Some(cache) = self.fitness_cache.as_mut() else {
+            self.evaluations += self.phenotypes.len();
+            return;
+        };
+        let violations = constraint::violations_mut(&mut self.constraints);
+        for (slot, genotype) in self.genotype_arena.chunks(self.genotype_size).enumerate() {
+            match cache.known[slot] {
+                Lookup::Hit(cached) => {
+                    self.fitness[slot] = cached.fitness;
+                    if let Some(violation) = violations.get_mut(slot) {
+                        *violation = cached.violation;
+                    }
+                    cache.hits += 1;
+                }
+                Lookup::Duplicate(first) => {
+                    self.fitness[slot] = self.fitness[first];
+                    if slot < violations.len() {
+                        violations[slot] = violations[first];
+                    }
+                    cache.hits += 1;
+                }
+                Lookup::Miss => {
+                    self.evaluations += 1;
+                    let key = (cache.hash)(genotype);
+                    let values = Cached {
+                        fitness: self.fitness[slot],
+                        violation: violations.get(slot).copied().unwrap_or(0.0),
+                    };
+                    cache.insert_entry(key, genotype.to_vec(), values);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Individual, benchmarks::Bits};
+
+    /// Counts the set bits and its own evaluations.
+    struct Counted {
+        fitness: f64,
+        calls: usize,
+    }
+
+    impl Evaluator for Counted {
+        type Genome = Bits;
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            Counted {
+                fitness: 0.0,
+                calls: 0,
+            }
+        }
+
+        fn evaluate(&mut self, genotype: &[bool], _: &()) {
+            self.fitness = genotype.iter().filter(|&&bit| bit).count() as f64;
+            self.calls += 1;
+        }
+
+        fn fitness(&self) -> f64 {
+            self.fitness
+        }
+    }
+
+    fn calls(population: &Population<Counted>) -> usize {
+        population.get_phenotypes().iter().map(|p| p.calls).sum()
+    }
+
+    fn lookups(population: &Population<Counted>) -> &[Lookup] {
+        known(&population.fitness_cache)
+    }
+
+    #[test]
+    fn elites_are_not_evaluated_again() {
+        let mut population = Population::<Counted>::with_seed(20, 16, 1)
+            .with_elitism(4)
+            .with_fitness_cache(100);
+        for generation in 0..10 {
+            population.evolve(&());
+            let misses = lookups(&population)
+                .iter()
+                .filter(|l| matches!(l, Lookup::Miss))
+                .count();
+            assert_eq!(calls(&population), population.evaluations());
+            assert_eq!(
+                population.evaluations() + population.cache_hits(),
+                20 * (generation + 1)
+            );
+            if generation > 0 {
+                assert!(
+                    lookups(&population)[..4]
+                        .iter()
+                        .all(|l| !matches!(l, Lookup::Miss))
+                );
+                assert!(misses <= 16);
+            }
+            // every slot has the fitness of its genotype, cached or not
+            for slot in 0..20 {
+                let ones = population
+                    .evaluated_genotype(slot)
+                    .iter()
+                    .filter(|&&bit| bit)
+                    .count();
+                assert_eq!(population.fitness[slot], ones as f64);
+            }
+        }
+    }
+
+    #[test]
+    fn a_cleared_cache_evaluates_every_genotype_again() {
+        let mut population = Population::<Counted>::with_seed(20, 16, 2)
+            .with_elitism(4)
+            .with_fitness_cache(100);
+        population.evolve(&());
+        population.evolve(&());
+        assert!(
+            lookups(&population)
+                .iter()
+                .any(|l| matches!(l, Lookup::Hit(_)))
+        );
+
+        population.clear_fitness_cache();
+        let (evaluations, hits) = (population.evaluations(), population.cache_hits());
+        population.evolve(&());
+        // only clones within the generation are still shared
+        let duplicates = lookups(&population)
+            .iter()
+            .filter(|l| matches!(l, Lookup::Duplicate(_)))
+            .count();
+        assert!(
+            lookups(&population)
+                .iter()
+                .all(|l| !matches!(l, Lookup::Hit(_)))
+        );
+        assert_eq!(population.evaluations() - evaluations, 20 - duplicates);
+        assert_eq!(population.cache_hits() - hits, duplicates);
+    }
+
+    #[test]
+    fn entries_survive_one_generation_of_the_map() {
+        let mut cache = FitnessCache::new(2, hash_genotype::<u8>);
+        let values = |fitness| Cached {
+            fitness,
+            violation: 0.0,
+        };
+        for gene in 0..3u8 {
+            cache.insert_entry(gene.into(), vec![gene], values(gene.into()));
+        }
+        // 0 and 1 moved to `previous` when 2 arrived; a hit brings 0 back
+        assert_eq!(cache.get(0, &[0]).map(|c| c.fitness), Some(0.0));
+        cache.insert_entry(3, vec![3], values(3.0));
+        // `current` held 2 and 0, so 1 is dropped with the old `previous`
+        assert!(cache.get(1, &[1]).is_none());
+        assert_eq!(cache.get(0, &[0]).map(|c| c.fitness), Some(0.0));
+        assert_eq!(cache.get(2, &[2]).map(|c| c.fitness), Some(2.0));
+        // a colliding key with another genotype is a miss
+        assert!(cache.get(2, &[9]).is_none());
+    }
+}
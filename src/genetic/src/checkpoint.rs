@@ -0,0 +1,130 @@
+//! Saving and restoring a `Population` with serde, enabled with the `serde`
+//! cargo feature.
+//!
+//! A checkpoint holds everything needed to continue a run where it stopped:
+//! the genotypes of the generation that is evaluated next, the generation
+//! counter, the state of the random number generator and the statistics
+//! history, which also holds the number of evaluations used. The
+//! configuration (selection, elitism, mutation schedule, fitness cache) and
+//! the observers are not part of it and have to be applied again after
+//! restoring, e.g. `population.with_selection(Rank::default())`.
+//!
+//! The state that the configuration builds up during a run is not saved
+//! either: an adaptive mutation schedule such as `OneFifthRule` starts over
+//! from its initial rate, the fitness cache starts out empty, and niches,
+//! species, the novelty archive and the hall of fame are rebuilt from the
+//! next generation on. Only a population without such state, e.g. with a
+//! constant or no mutation schedule, continues exactly like one that was
+//! never interrupted under the same seed.
+//!
+//! The fitness of the last evaluated generation is not saved either, so
+//! `fittest` and `max_fitness` only have meaningful values again after the
+//! next `evolve`. The individuals of a restored population are numbered anew.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+use crate::{Evaluator, Gene, Population, statistics::Statistics};
+
+#[derive(Serialize)]
+struct CheckpointRef<'a, G, R> {
+    phenotype_size: usize,
+    genotype_size: usize,
+    genotype_arena: &'a [G],
+    generation: usize,
+    rng: &'a R,
+    history: &'a [Statistics],
+}
+
+#[derive(Deserialize)]
+struct Checkpoint<G, R> {
+    phenotype_size: usize,
+    genotype_size: usize,
+    genotype_arena: Vec<G>,
+    generation: usize,
+    rng: R,
+    history: Vec<Statistics>,
+}
+
+impl<E, R> Serialize for Population<E, R>
+where
+    E: Evaluator,
+    Gene<E>: Serialize,
+    R: rand::Rng + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CheckpointRef {
+            phenotype_size: self.phenotypes.len(),
+            genotype_size: self.genotype_size,
+            genotype_arena: &self.genotype_arena,
+            generation: self.generation,
+            rng: &self.rng,
+            history: &self.history,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, E, R> Deserialize<'de> for Population<E, R>
+where
+    E: Evaluator,
+    Gene<E>: Deserialize<'de>,
+    R: rand::Rng + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let checkpoint = Checkpoint::<Gene<E>, R>::deserialize(deserializer)?;
+        let expected = checkpoint
+            .phenotype_size
+            .checked_mul(checkpoint.genotype_size)
+            .ok_or_else(|| D::Error::custom("population size overflows"))?;
+        if checkpoint.genotype_arena.len() != expected {
+            return Err(D::Error::custom(format!(
+                "expected {expected} genes for {} genotypes of size {}, found {}",
+                checkpoint.phenotype_size,
+                checkpoint.genotype_size,
+                checkpoint.genotype_arena.len()
+            )));
+        }
+
+        let mut population = Population::from_arena(
+            checkpoint.genotype_arena,
+            checkpoint.phenotype_size,
+            checkpoint.genotype_size,
+            checkpoint.rng,
+        );
+        population.generation = checkpoint.generation;
+        population.evaluations = checkpoint.history.last().map_or(0, |s| s.evaluations);
+        population.history = checkpoint.history;
+        Ok(population)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::OneMax;
+
+    #[test]
+    fn resumed_run_matches_an_uninterrupted_one() {
+        let create = || Population::<OneMax>::with_seed(20, 16, 7).with_elitism(2);
+
+        let mut uninterrupted = create();
+        for _ in 0..20 {
+            uninterrupted.evolve(&());
+        }
+
+        let mut interrupted = create();
+        for _ in 0..10 {
+            interrupted.evolve(&());
+        }
+        let json = serde_json::to_string(&interrupted).unwrap();
+        let mut resumed: Population<OneMax> = serde_json::from_str::<Population<OneMax>>(&json)
+            .unwrap()
+            .with_elitism(2);
+        for _ in 0..10 {
+            resumed.evolve(&());
+        }
+
+        assert_eq!(resumed.genotype_arena, uninterrupted.genotype_arena);
+        assert_eq!(resumed.history, uninterrupted.history);
+    }
+}
@@ -0,0 +1,577 @@
+//! # CMA-ES
+//!
+//! [`CmaEs`], the covariance matrix adaptation evolution strategy (Hansen &
+//! Ostermeier, 2001), optimises real-valued genotypes, see [`RealGene`].
+//! Instead of a population of individuals it evolves a multivariate normal
+//! distribution:
+//!
+//! 1. Every generation samples `population_size` genotypes from the
+//!    distribution around the current mean.
+//! 2. The mean moves towards the weighted average of the fitter half.
+//! 3. The covariance matrix learns the directions in which the fitter
+//!    samples lie, and the step size `sigma` grows or shrinks depending on
+//!    how far the mean keeps moving.
+//!
+//! This makes CMA-ES a strong choice for smooth, badly scaled or rotated
+//! problems with up to a few hundred genes. Like differential evolution it
+//! only uses `Evaluator::evaluate`, so a phenotype written for a
+//! [`Population`](crate::Population) of real-valued genes works unchanged.
+//!
+//! Genes that clamp or round in `RealGene::from_f64` are supported, the
+//! update uses the genes that were actually evaluated.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    Evaluator, Gene, GenotypeInitializer, Individual,
+    crossover::RealGene,
+    fitness,
+    mutation::standard_normal,
+    statistics::{self, Observer, Observers, Statistics},
+    termination::{self, RunReport, Termination},
+};
+
+/// A CMA-ES optimiser.
+#[derive(Debug)]
+pub struct CmaEs<E: Evaluator, R: Rng = ChaCha8Rng> {
+    genotype_arena: Vec<Gene<E>>,
+    phenotypes: Vec<E>,
+    genotype_size: usize,
+    rng: R,
+    mean: Vec<f64>,
+    sigma: f64,
+    /// Row-major `genotype_size²` matrix.
+    covariance: Vec<f64>,
+    /// The eigenvectors of `covariance` as columns of a row-major matrix.
+    basis: Vec<f64>,
+    /// The square roots of the eigenvalues of `covariance`.
+    scales: Vec<f64>,
+    /// The generation `basis` and `scales` were computed in.
+    decomposed: usize,
+    covariance_path: Vec<f64>,
+    sigma_path: Vec<f64>,
+    fitness: Vec<f64>,
+    evaluations: usize,
+    generation: usize,
+    history: Vec<Statistics>,
+    observers: Observers,
+}
+
+/// The strategy parameters derived from the dimension and population size,
+/// with the defaults from Hansen's tutorial.
+struct Parameters {
+    weights: Vec<f64>,
+    mu_eff: f64,
+    c_c: f64,
+    c_sigma: f64,
+    c_1: f64,
+    c_mu: f64,
+    damping: f64,
+    /// Expected length of a standard normally distributed vector.
+    chi_n: f64,
+}
+
+impl Parameters {
+    fn new(n: usize, population_size: usize) -> Self {
+        let n = n as f64;
+        let mu = population_size / 2;
+        let mut weights: Vec<f64> = (1..=mu)
+            .map(|i| (mu as f64 + 0.5).ln() - (i as f64).ln())
+            .collect();
+        let sum: f64 = weights.iter().sum();
+        weights.iter_mut().for_each(|w| *w /= sum);
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff);
+        Self {
+            c_c: (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n),
+            c_sigma,
+            c_1,
+            c_mu: (1.0 - c_1)
+                .min(2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff)),
+            damping: 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma,
+            chi_n: n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n)),
+            weights,
+            mu_eff,
+        }
+    }
+}
+
+impl<E: Evaluator, R: Rng + SeedableRng> CmaEs<E, R>
+where
+    Gene<E>: RealGene,
+{
+    /// Creates an optimiser seeded from the thread-local entropy source.
+    pub fn new(genotype_size: usize) -> Self {
+        Self::with_rng(genotype_size, R::from_rng(&mut rand::rng()))
+    }
+
+    /// Creates an optimiser whose whole run is determined by `seed`.
+    pub fn with_seed(genotype_size: usize, seed: u64) -> Self {
+        Self::with_rng(genotype_size, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: Evaluator, R: Rng> CmaEs<E, R>
+where
+    Gene<E>: RealGene,
+{
+    /// Creates an optimiser driven by the given random number generator.
+    ///
+    /// The initial mean is a genotype drawn from the gene's
+    /// [`GenotypeInitializer`], the population size defaults to
+    /// `4 + 3 ln(genotype_size)`.
+    ///
+    /// # Panics
+    /// Panics if `genotype_size` is 0.
+    pub fn with_rng(genotype_size: usize, mut rng: R) -> Self {
+        assert!(genotype_size > 0, "CMA-ES needs at least one gene");
+        let mut start = vec![Gene::<E>::default(); genotype_size];
+        Gene::<E>::initial_genotypes(&mut start, &mut rng);
+
+        let n = genotype_size;
+        let mut identity = vec![0.0; n * n];
+        for i in 0..n {
+            identity[i * n + i] = 1.0;
+        }
+        let population_size = 4 + (3.0 * (n as f64).ln()) as usize;
+
+        Self {
+            genotype_arena: Vec::new(),
+            phenotypes: Vec::new(),
+            genotype_size,
+            rng,
+            mean: start.iter().map(|gene| gene.to_f64()).collect(),
+            sigma: 0.3,
+            covariance: identity.clone(),
+            basis: identity,
+            scales: vec![1.0; n],
+            decomposed: 0,
+            covariance_path: vec![0.0; n],
+            sigma_path: vec![0.0; n],
+            fitness: Vec::new(),
+            evaluations: 0,
+            generation: 0,
+            history: Vec::new(),
+            observers: Observers::default(),
+        }
+        .with_population_size(population_size)
+    }
+
+    /// Sets the number of genotypes sampled per generation. Larger
+    /// populations search more globally at the cost of more evaluations.
+    ///
+    /// # Panics
+    /// Panics if `size` is less than 2.
+    pub fn with_population_size(mut self, size: usize) -> Self {
+        assert!(size >= 2, "CMA-ES needs a population of at least 2");
+        self.genotype_arena = vec![Gene::<E>::default(); size * self.genotype_size];
+        self.phenotypes = (0..size).map(|i| E::new(Individual(i))).collect();
+        self.fitness = vec![0.0; size];
+        self
+    }
+
+    /// Sets the initial step size, the standard deviation of the samples
+    /// around the mean. Defaults to 0.3, it should be about a third of the
+    /// range the optimum is expected in.
+    ///
+    /// # Panics
+    /// Panics if `sigma` is not positive.
+    pub fn with_sigma(mut self, sigma: f64) -> Self {
+        assert!(sigma > 0.0, "the step size must be positive, got {sigma}");
+        self.sigma = sigma;
+        self
+    }
+
+    /// Sets the initial mean.
+    ///
+    /// # Panics
+    /// Panics if `mean` does not have `genotype_size` genes.
+    pub fn with_mean(mut self, mean: &[Gene<E>]) -> Self {
+        assert_eq!(
+            mean.len(),
+            self.genotype_size,
+            "the mean must have genotype_size genes"
+        );
+        self.mean = mean.iter().map(|gene| gene.to_f64()).collect();
+        self
+    }
+
+    /// Registers an observer that is notified after every evaluated generation.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    /// Samples a generation, evaluates it and adapts the distribution.
+    pub fn evolve(&mut self, param: &E::FitnessParam) {
+        let parameters = Parameters::new(self.genotype_size, self.phenotypes.len());
+        self.sample(&parameters);
+        self.calculate_fitness(param);
+        self.record_statistics();
+        self.update(&parameters);
+    }
+
+    /// Evolves until `termination` is satisfied.
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<Gene<E>> {
+        termination::drive(
+            self,
+            |cma_es| cma_es.evolve(param),
+            |cma_es| &cma_es.history,
+            |cma_es, index| cma_es.genotype(index),
+            termination,
+        )
+    }
+
+    /// Fills the arena with samples `mean + sigma * B * D * z`.
+    fn sample(&mut self, parameters: &Parameters) {
+        // The decomposition costs O(n³), so it is only renewed once the
+        // covariance has changed noticeably.
+        let n = self.genotype_size;
+        let interval =
+            self.phenotypes.len() as f64 / (parameters.c_1 + parameters.c_mu) / n as f64 / 10.0;
+        if (self.generation - self.decomposed) as f64 >= interval {
+            self.decompose();
+        }
+
+        let mut scaled = vec![0.0; n];
+        for genotype in self.genotype_arena.chunks_mut(n) {
+            for (s, scale) in scaled.iter_mut().zip(&self.scales) {
+                *s = scale * standard_normal(&mut self.rng);
+            }
+            for (i, gene) in genotype.iter_mut().enumerate() {
+                let row = &self.basis[i * n..(i + 1) * n];
+                let y: f64 = row.iter().zip(&scaled).map(|(b, s)| b * s).sum();
+                *gene = Gene::<E>::from_f64(self.mean[i] + self.sigma * y);
+            }
+        }
+    }
+
+    fn decompose(&mut self) {
+        let n = self.genotype_size;
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&self.covariance, n);
+        self.scales = eigenvalues.iter().map(|v| v.max(1e-20).sqrt()).collect();
+        self.basis = eigenvectors;
+        self.decomposed = self.generation;
+    }
+
+    fn calculate_fitness(&mut self, param: &E::FitnessParam) {
+        for (p, genotype) in self
+            .phenotypes
+            .iter_mut()
+            .zip(self.genotype_arena.chunks(self.genotype_size))
+        {
+            p.evaluate(genotype, param);
+        }
+        self.evaluations += self.phenotypes.len();
+        self.fitness.clear();
+        self.fitness
+            .extend(self.phenotypes.iter().map(|p| p.fitness()));
+        fitness::sanitize(&mut self.fitness);
+    }
+
+    /// Summarises the evaluated samples, appends them to the history and
+    /// notifies the observers.
+    fn record_statistics(&mut self) {
+        let mut statistics = Statistics::from_fitness(self.generation, &self.fitness);
+        statistics.diversity = statistics::diversity(
+            &self.genotype_arena,
+            self.genotype_size,
+            statistics.elite_index,
+        );
+        statistics.evaluations = self.evaluations;
+        self.observers.notify(&statistics);
+        self.history.push(statistics);
+        self.generation += 1;
+    }
+
+    /// Moves the mean and adapts the evolution paths, the covariance and
+    /// the step size.
+    fn update(&mut self, parameters: &Parameters) {
+        let n = self.genotype_size;
+        let Parameters {
+            weights,
+            mu_eff,
+            c_c,
+            c_sigma,
+            c_1,
+            c_mu,
+            damping,
+            chi_n,
+        } = parameters;
+
+        let mut order: Vec<usize> = (0..self.fitness.len()).collect();
+        order.sort_by(|&a, &b| fitness::compare(self.fitness[b], self.fitness[a]));
+
+        // the steps of the selected samples, as drawn from N(0, C)
+        let steps: Vec<Vec<f64>> = order[..weights.len()]
+            .iter()
+            .map(|&k| {
+                self.genotype(k)
+                    .iter()
+                    .zip(&self.mean)
+                    .map(|(gene, mean)| (gene.to_f64() - mean) / self.sigma)
+                    .collect()
+            })
+            .collect();
+        let mut step = vec![0.0; n];
+        for (w, y) in weights.iter().zip(&steps) {
+            for (s, y) in step.iter_mut().zip(y) {
+                *s += w * y;
+            }
+        }
+        for (m, s) in self.mean.iter_mut().zip(&step) {
+            *m += self.sigma * s;
+        }
+
+        // sigma path, with C^-1/2 * step = B * D^-1 * B^T * step
+        let rotated: Vec<f64> = (0..n)
+            .map(|j| (0..n).map(|i| self.basis[i * n + j] * step[i]).sum::<f64>() / self.scales[j])
+            .collect();
+        let sigma_factor = (c_sigma * (2.0 - c_sigma) * mu_eff).sqrt();
+        for i in 0..n {
+            let whitened: f64 = (0..n).map(|j| self.basis[i * n + j] * rotated[j]).sum();
+            self.sigma_path[i] = (1.0 - c_sigma) * self.sigma_path[i] + sigma_factor * whitened;
+        }
+        let sigma_path_norm = self.sigma_path.iter().map(|p| p * p).sum::<f64>().sqrt();
+
+        // stall the covariance path while the step size grows quickly
+        let generations = self.generation as i32;
+        let stalled =
+            sigma_path_norm / (1.0 - (1.0 - c_sigma).powi(2 * generations)).sqrt() / chi_n
+                >= 1.4 + 2.0 / (n as f64 + 1.0);
+        let h_sigma = if stalled { 0.0 } else { 1.0 };
+        let c_factor = (c_c * (2.0 - c_c) * mu_eff).sqrt();
+        for (p, s) in self.covariance_path.iter_mut().zip(&step) {
+            *p = (1.0 - c_c) * *p + h_sigma * c_factor * s;
+        }
+
+        // rank-one and rank-mu update
+        let decay = 1.0 - c_1 - c_mu + (1.0 - h_sigma) * c_1 * c_c * (2.0 - c_c);
+        for i in 0..n {
+            for j in i..n {
+                let rank_one = self.covariance_path[i] * self.covariance_path[j];
+                let rank_mu: f64 = weights
+                    .iter()
+                    .zip(&steps)
+                    .map(|(w, y)| w * y[i] * y[j])
+                    .sum();
+                let value = decay * self.covariance[i * n + j] + c_1 * rank_one + c_mu * rank_mu;
+                self.covariance[i * n + j] = value;
+                self.covariance[j * n + i] = value;
+            }
+        }
+
+        self.sigma *= ((c_sigma / damping) * (sigma_path_norm / chi_n - 1.0)).exp();
+    }
+
+    fn genotype(&self, index: usize) -> &[Gene<E>] {
+        let start = index * self.genotype_size;
+        &self.genotype_arena[start..start + self.genotype_size]
+    }
+
+    /// The mean of the distribution, the best estimate of the optimum.
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+
+    /// The current step size.
+    pub fn sigma(&self) -> f64 {
+        self.sigma
+    }
+
+    /// The fittest sample of the last evaluated generation.
+    pub fn fittest_phenotype(&self) -> Option<&E> {
+        fitness::argmax(self.fitness.iter().copied()).map(|index| &self.phenotypes[index])
+    }
+
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The statistics of every evaluated generation, oldest first.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
+    pub fn get_phenotypes(&self) -> &[E] {
+        &self.phenotypes
+    }
+
+    /// Retrieves the genotype of a phenotype.
+    ///
+    /// # Panics
+    /// Panics if the phenotype's index is out of bounds.
+    pub fn get_genotype(&self, phenotype: &E) -> &[Gene<E>] {
+        self.genotype(crate::slot_of(&self.phenotypes, phenotype))
+    }
+
+    /// Iterates over each phenotype mutably, providing the genotype it was
+    /// last sampled with.
+    pub fn for_each_phenotype_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut E, &[Gene<E>]),
+    {
+        for (p, genotype) in self
+            .phenotypes
+            .iter_mut()
+            .zip(self.genotype_arena.chunks(self.genotype_size))
+        {
+            func(p, genotype);
+        }
+    }
+}
+
+/// Eigendecomposition of a symmetric row-major `n * n` matrix with the
+/// cyclic Jacobi method. Returns the eigenvalues and the eigenvectors as
+/// the columns of a row-major matrix.
+fn symmetric_eigen(matrix: &[f64], n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut a = matrix.to_vec();
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+
+    let scale: f64 = a.iter().map(|x| x * x).sum::<f64>().max(f64::MIN_POSITIVE);
+    for _ in 0..100 {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|p| (p + 1..n).map(move |q| (p, q)))
+            .map(|(p, q)| a[p * n + q] * a[p * n + q])
+            .sum();
+        if off_diagonal <= 1e-30 * scale {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                // rotate by the angle that zeroes a[p][q]
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = if theta == 0.0 {
+                    1.0
+                } else {
+                    theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt())
+                };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Genome, termination::MaxGenerations};
+
+    struct Reals;
+
+    impl Genome for Reals {
+        type Gene = f64;
+
+        fn mutate<R: Rng + ?Sized>(_: &mut [f64], _: &mut R) {}
+
+        fn crossover<R: Rng + ?Sized>(
+            parent1: &[f64],
+            parent2: &[f64],
+            child1: &mut [f64],
+            child2: &mut [f64],
+            _: usize,
+            _: &mut R,
+        ) {
+            child1.copy_from_slice(parent1);
+            child2.copy_from_slice(parent2);
+        }
+    }
+
+    /// The negated squared distance to 0.3 in every gene.
+    struct Sphere {
+        fitness: f64,
+    }
+
+    impl Evaluator for Sphere {
+        type Genome = Reals;
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            Self { fitness: 0.0 }
+        }
+
+        fn evaluate(&mut self, genotype: &[f64], _: &()) {
+            self.fitness = -genotype.iter().map(|x| (x - 0.3) * (x - 0.3)).sum::<f64>();
+        }
+
+        fn fitness(&self) -> f64 {
+            self.fitness
+        }
+    }
+
+    #[test]
+    fn eigen_decomposes_a_known_matrix() {
+        // eigenvalues 1 and 3 with the eigenvectors (1, -1) and (1, 1)
+        let (values, vectors) = symmetric_eigen(&[2.0, 1.0, 1.0, 2.0], 2);
+        let mut pairs: Vec<(f64, [f64; 2])> = (0..2)
+            .map(|i| (values[i], [vectors[i], vectors[2 + i]]))
+            .collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        for ((value, vector), (expected_value, expected_vector)) in pairs
+            .iter()
+            .zip([(1.0, [half, -half]), (3.0, [half, half])])
+        {
+            assert!((value - expected_value).abs() < 1e-12);
+            // an eigenvector is only determined up to its sign
+            let dot = vector[0] * expected_vector[0] + vector[1] * expected_vector[1];
+            assert!((dot.abs() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn converges_on_the_sphere() {
+        let mut cma_es = CmaEs::<Sphere>::with_seed(5, 1);
+        let report = cma_es.run(&(), MaxGenerations::new(300));
+        assert!(report.best_fitness > -1e-10, "{}", report.best_fitness);
+        for x in cma_es.mean() {
+            assert!((x - 0.3).abs() < 1e-4, "{x}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "step size must be positive")]
+    fn rejects_a_nan_sigma() {
+        let _ = CmaEs::<Sphere>::with_seed(2, 0).with_sigma(f64::NAN);
+    }
+}
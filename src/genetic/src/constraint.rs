@@ -0,0 +1,309 @@
+//! # Constraint Handling
+//!
+//! Many problems have solutions that are not allowed: a packing over the
+//! weight limit, a path through a wall, an agent that died. Folding such
+//! violations into the fitness by hand mixes two goals and needs a new
+//! weighting for every problem. A [`Constrained`] evaluator reports how much
+//! its genotype violates the constraints instead, and the population turns
+//! fitness and violation into the fitness used for selection with one of the
+//! [`ConstraintHandling`] strategies.
+//!
+//! Elites, statistics, termination criteria and the hall of fame see the
+//! handled fitness. With [`ConstraintHandling::Repair`] and
+//! [`ConstraintHandling::FeasibilityFirst`] an infeasible individual
+//! therefore never counts as the best one of a generation with a feasible
+//! one, with [`ConstraintHandling::Penalty`] that depends on the
+//! coefficient. The violations of the last evaluated generation are
+//! available from `Population::violations`.
+
+use rand::prelude::*;
+
+use crate::{Evaluator, Gene, Population};
+
+/// An evaluator that reports the violation of constraints separately from
+/// the fitness.
+pub trait Constrained: Evaluator {
+    /// The total violation of all constraints in the last evaluation, 0 for a
+    /// feasible genotype. Violations below 0 are treated as 0, a NaN
+    /// violation as infinite.
+    fn violation(&self) -> f64;
+
+    /// Turns `genotype` into a feasible one, used by
+    /// [`ConstraintHandling::Repair`]. The repaired genotype replaces the
+    /// original one in the population. Defaults to leaving it unchanged.
+    fn repair(genotype: &mut [Gene<Self>], param: &Self::FitnessParam) {
+        let _ = (genotype, param);
+    }
+}
+
+/// How violations enter the fitness, see [`Population::with_constraints`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstraintHandling {
+    /// Static penalty: the fitness minus `coefficient` times the violation.
+    ///
+    /// Simple, but a coefficient that is too low lets infeasible individuals
+    /// win, one that is too high hides the fitness gradient near the border
+    /// of the feasible region.
+    Penalty { coefficient: f64 },
+    /// Repairs every genotype with [`Constrained::repair`] before it is
+    /// evaluated. Violations left after the repair are handled by the
+    /// feasibility rules.
+    Repair,
+    /// Deb's feasibility rules, without any parameter to tune:
+    ///
+    /// - a feasible individual beats an infeasible one,
+    /// - of two feasible individuals the fitter one wins,
+    /// - of two infeasible individuals the one with the lower violation wins.
+    ///
+    /// The rules are expressed as a fitness: an infeasible individual gets
+    /// the fitness of the least fit feasible individual of its generation
+    /// minus its violation.
+    FeasibilityFirst,
+}
+
+/// A strategy together with the way to query an evaluator.
+pub(crate) struct ConstraintState<E: Evaluator> {
+    handling: ConstraintHandling,
+    violation: fn(&E) -> f64,
+    repair: fn(&mut [Gene<E>], &E::FitnessParam),
+    /// The violation of every slot of the last evaluated generation.
+    violations: Vec<f64>,
+}
+
+// Not derived, as that would require `Debug` of the fitness parameter.
+impl<E: Evaluator> std::fmt::Debug for ConstraintState<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConstraintState")
+            .field("handling", &self.handling)
+            .field("violations", &self.violations)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E: Constrained, R: Rng> Population<E, R> {
+    /// Handles the constraint violations reported by the evaluators. Defaults
+    /// to none, which ignores [`Constrained::violation`].
+    ///
+    /// The fitness cache remembers the violation of a genotype together with
+    /// its fitness, so skipped phenotypes are handled like evaluated ones.
+    pub fn with_constraints(mut self, handling: ConstraintHandling) -> Self {
+        self.constraints = Some(ConstraintState {
+            handling,
+            violation: E::violation,
+            repair: E::repair,
+            violations: Vec::new(),
+        });
+        self
+    }
+}
+
+/// The violations of the generation being evaluated, empty without
+/// constraint handling.
+pub(crate) fn violations_mut<E: Evaluator>(state: &mut Option<ConstraintState<E>>) -> &mut [f64] {
+    state
+        .as_mut()
+        .map_or(&mut [], |state| state.violations.as_mut_slice())
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// The constraint violation of every individual of the last evaluated
+    /// generation, in the order of [`Population::get_phenotypes`]. Empty
+    /// without constraint handling.
+    pub fn violations(&self) -> &[f64] {
+        self.constraints
+            .as_ref()
+            .map_or(&[], |state| state.violations.as_slice())
+    }
+
+    /// Repairs the genotypes about to be evaluated, for
+    /// [`ConstraintHandling::Repair`].
+    pub(crate) fn repair_genotypes(&mut self, param: &E::FitnessParam) {
+        let Some(state) = self.constraints.as_ref() else {
+            return;
+        };
+        if state.handling != ConstraintHandling::Repair {
+            return;
+        }
+        for genotype in self.genotype_arena.chunks_mut(self.genotype_size) {
+            (state.repair)(genotype, param);
+        }
+    }
+
+    /// Reads the violations of the evaluated phenotypes. Called before the
+    /// cached values are merged in.
+    pub(crate) fn collect_violations(&mut self) {
+        let Some(state) = self.constraints.as_mut() else {
+            return;
+        };
+        state.violations.clear();
+        state.violations.extend(self.phenotypes.iter().map(|p| {
+            let violation = (state.violation)(p);
+            if violation.is_nan() {
+                f64::INFINITY
+            } else {
+                violation.max(0.0)
+            }
+        }));
+    }
+
+    /// Turns the collected fitness into the handled fitness. Called before
+    /// the fitness is sanitised.
+    pub(crate) fn apply_constraints(&mut self) {
+        let Some(state) = self.constraints.as_mut() else {
+            return;
+        };
+
+        match state.handling {
+            ConstraintHandling::Penalty { coefficient } => {
+                for (fitness, violation) in self.fitness.iter_mut().zip(&state.violations) {
+                    *fitness -= coefficient * violation;
+                }
+            }
+            ConstraintHandling::Repair | ConstraintHandling::FeasibilityFirst => {
+                let worst_feasible = self
+                    .fitness
+                    .iter()
+                    .zip(&state.violations)
+                    .filter(|&(f, &v)| v == 0.0 && !f.is_nan())
+                    .map(|(&f, _)| f)
+                    .fold(f64::INFINITY, f64::min);
+                let base = if worst_feasible.is_finite() {
+                    worst_feasible
+                } else {
+                    0.0
+                };
+                // an infinite violation ranks below every finite one, but
+                // stays finite, so that it is not sanitised up to the worst
+                // feasible fitness
+                let worst_finite = state
+                    .violations
+                    .iter()
+                    .copied()
+                    .filter(|v| v.is_finite())
+                    .fold(0.0, f64::max);
+                for (fitness, &violation) in self.fitness.iter_mut().zip(&state.violations) {
+                    if violation > 0.0 {
+                        *fitness = base - violation.min(worst_finite + 1.0);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Individual, benchmarks::Bits};
+
+    /// Counts the set bits, more than four of them violate the constraint.
+    struct Limited {
+        fitness: f64,
+        violation: f64,
+    }
+
+    fn excess(genotype: &[bool]) -> f64 {
+        (genotype.iter().filter(|&&bit| bit).count() as f64 - 4.0).max(0.0)
+    }
+
+    impl Evaluator for Limited {
+        type Genome = Bits;
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            Limited {
+                fitness: 0.0,
+                violation: 0.0,
+            }
+        }
+
+        fn evaluate(&mut self, genotype: &[bool], _: &()) {
+            self.fitness = genotype.iter().filter(|&&bit| bit).count() as f64;
+            self.violation = excess(genotype);
+        }
+
+        fn fitness(&self) -> f64 {
+            self.fitness
+        }
+    }
+
+    impl Constrained for Limited {
+        fn violation(&self) -> f64 {
+            self.violation
+        }
+    }
+
+    /// The handled fitness of phenotypes reporting `fitness` and `violations`.
+    fn handle(handling: ConstraintHandling, fitness: &[f64], violations: &[f64]) -> Vec<f64> {
+        let mut population =
+            Population::<Limited>::with_seed(fitness.len(), 8, 0).with_constraints(handling);
+        for (p, (&fitness, &violation)) in population
+            .phenotypes
+            .iter_mut()
+            .zip(fitness.iter().zip(violations))
+        {
+            *p = Limited { fitness, violation };
+        }
+        population.fitness = fitness.to_vec();
+        population.collect_violations();
+        population.apply_constraints();
+        crate::fitness::sanitize(&mut population.fitness);
+        population.fitness
+    }
+
+    #[test]
+    fn penalty_subtracts_the_weighted_violation() {
+        let handling = ConstraintHandling::Penalty { coefficient: 2.0 };
+        assert_eq!(
+            handle(handling, &[5.0, 3.0, 8.0], &[0.0, 1.0, 2.0]),
+            [5.0, 1.0, 4.0]
+        );
+        // negative violations count as none
+        assert_eq!(handle(handling, &[5.0], &[-1.0]), [5.0]);
+    }
+
+    #[test]
+    fn feasibility_first_ranks_infeasible_below_the_worst_feasible() {
+        let fitness = handle(
+            ConstraintHandling::FeasibilityFirst,
+            &[5.0, 3.0, 8.0, 9.0],
+            &[0.0, 0.0, 0.5, 2.0],
+        );
+        assert_eq!(fitness, [5.0, 3.0, 2.5, 1.0]);
+        // without a feasible individual only the violation counts
+        let fitness = handle(ConstraintHandling::Repair, &[5.0, 9.0], &[3.0, 1.0]);
+        assert_eq!(fitness, [-3.0, -1.0]);
+    }
+
+    #[test]
+    fn nan_violations_rank_last_with_a_finite_fitness() {
+        let mut population = Population::<Limited>::with_seed(3, 8, 0)
+            .with_constraints(ConstraintHandling::FeasibilityFirst);
+        for (p, violation) in population.phenotypes.iter_mut().zip([0.0, 1.0, f64::NAN]) {
+            p.violation = violation;
+        }
+        population.fitness = vec![5.0, 8.0, 9.0];
+        population.collect_violations();
+        assert_eq!(population.violations(), [0.0, 1.0, f64::INFINITY]);
+        population.apply_constraints();
+        assert_eq!(population.fitness, [5.0, 4.0, 3.0]);
+    }
+
+    #[test]
+    fn cached_violations_match_an_evaluation() {
+        let mut population = Population::<Limited>::with_seed(20, 8, 3)
+            .with_elitism(4)
+            .with_fitness_cache(100)
+            .with_constraints(ConstraintHandling::FeasibilityFirst);
+        for _ in 0..10 {
+            population.evolve(&());
+            for slot in 0..20 {
+                let genotype = population.evaluated_genotype(slot);
+                assert_eq!(population.violations()[slot], excess(genotype));
+            }
+        }
+        assert!(population.cache_hits() > 0);
+        // the elites, the fittest feasible genotypes, never exceed the limit
+        assert_eq!(population.fittest().0, 4.0);
+    }
+}
@@ -0,0 +1,618 @@
+//! # Crossover Operators
+//!
+//! Ready-made implementations for `Genome::crossover`. All operators take
+//! two parents and write two children of the same `size`, except for
+//! [`cut_and_splice`], which is meant for variable-length genomes.
+//!
+//! - General purpose: [`single_split`], [`double_split`], [`k_point`], [`uniform`].
+//! - Real-valued genes (see [`RealGene`]): [`arithmetic`], [`blend`], [`simulated_binary`].
+//! - Permutations, where every gene occurs exactly once: [`partially_mapped`],
+//!   [`ordered`], [`cycle`].
+//! - Variable-length genomes (see `VariablePopulation`): [`cut_and_splice`].
+
+use rand::prelude::*;
+
+/// A crossover operator chosen at run time, see `Population::with_crossover`.
+///
+/// The operators of this module are generic over the RNG, so they are
+/// wrapped in a closure that captures nothing, e.g.
+/// `|a, b, c1, c2, size, rng| crossover::blend(a, b, c1, c2, size, 0.5, rng)`.
+pub type Operator<G> = fn(&[G], &[G], &mut [G], &mut [G], usize, &mut dyn RngCore);
+
+pub fn single_split<T: Copy, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    rng: &mut R,
+) {
+    let crossover_point = rng.random_range(1..size);
+    let (p1_head, p1_tail) = parent1.split_at(crossover_point);
+    let (p2_head, p2_tail) = parent2.split_at(crossover_point);
+
+    // Create child 1 by combining head of parent 1 and tail of parent 2
+    child1[..crossover_point].copy_from_slice(p1_head);
+    child1[crossover_point..].copy_from_slice(p2_tail);
+
+    // Create child 2 by combining head of parent 2 and tail of parent 1
+    child2[..crossover_point].copy_from_slice(p2_head);
+    child2[crossover_point..].copy_from_slice(p1_tail);
+}
+
+pub fn double_split<T: Copy, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    rng: &mut R,
+) {
+    // Edge Case: If the genotype has fewer than 3 elements,
+    // it's impossible to pick two distinct internal points.
+    // Falling back to no crossover prevents panics or infinite loops.
+    if size < 3 {
+        child1.copy_from_slice(parent1);
+        child2.copy_from_slice(parent2);
+        return;
+    }
+
+    // 1. Generate two DISTINCT crossover points.
+    let mut point1 = rng.random_range(1..size);
+    let mut point2 = rng.random_range(1..size);
+    while point1 == point2 {
+        point2 = rng.random_range(1..size);
+    }
+
+    // 2. Ensure point1 < point2 to make slicing logic simple.
+    if point1 > point2 {
+        std::mem::swap(&mut point1, &mut point2);
+    }
+
+    // 3. Assemble the children by swapping the middle segment.
+    // Child 1 = P1_head + P2_middle + P1_tail
+    child1[..point1].copy_from_slice(&parent1[..point1]);
+    child1[point1..point2].copy_from_slice(&parent2[point1..point2]);
+    child1[point2..].copy_from_slice(&parent1[point2..]);
+
+    // Child 2 = P2_head + P1_middle + P2_tail
+    child2[..point1].copy_from_slice(&parent2[..point1]);
+    child2[point1..point2].copy_from_slice(&parent1[point1..point2]);
+    child2[point2..].copy_from_slice(&parent2[point2..]);
+}
+
+/// k-point crossover: the parents are cut at `k` distinct points and the
+/// children take the segments alternately from either parent.
+///
+/// `k` is capped at `size - 1`, `k = 1` equals [`single_split`].
+pub fn k_point<T: Copy, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    k: usize,
+    rng: &mut R,
+) {
+    child1.copy_from_slice(parent1);
+    child2.copy_from_slice(parent2);
+    if size < 2 || k == 0 {
+        return;
+    }
+
+    let mut points = rand::seq::index::sample(rng, size - 1, k.min(size - 1)).into_vec();
+    points.iter_mut().for_each(|p| *p += 1);
+    points.sort_unstable();
+    points.push(size);
+
+    // swap every second segment
+    for segment in points.windows(2).step_by(2) {
+        let (start, end) = (segment[0], segment[1]);
+        child1[start..end].copy_from_slice(&parent2[start..end]);
+        child2[start..end].copy_from_slice(&parent1[start..end]);
+    }
+}
+
+/// Uniform crossover: every gene is taken from either parent with equal probability.
+pub fn uniform<T: Copy, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    rng: &mut R,
+) {
+    for i in 0..size {
+        if rng.random_bool(0.5) {
+            child1[i] = parent1[i];
+            child2[i] = parent2[i];
+        } else {
+            child1[i] = parent2[i];
+            child2[i] = parent1[i];
+        }
+    }
+}
+
+/// A gene that represents a real number, required by the real-valued operators.
+pub trait RealGene: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+impl RealGene for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+impl RealGene for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+/// Integers round to the nearest value and saturate at their bounds.
+macro_rules! impl_real_gene_for_integers {
+    ($($ty:ty),*) => {
+        $(
+            impl RealGene for $ty {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+                fn from_f64(value: f64) -> Self {
+                    value.round() as $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_real_gene_for_integers!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+/// Whole arithmetic crossover: the children are a random weighted average of
+/// the parents, `child1 = w * parent1 + (1 - w) * parent2` and vice versa.
+pub fn arithmetic<T: RealGene, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    rng: &mut R,
+) {
+    let w: f64 = rng.random();
+    for i in 0..size {
+        let (x1, x2) = (parent1[i].to_f64(), parent2[i].to_f64());
+        child1[i] = T::from_f64(w * x1 + (1.0 - w) * x2);
+        child2[i] = T::from_f64((1.0 - w) * x1 + w * x2);
+    }
+}
+
+/// Blend crossover (BLX-α).
+///
+/// Every child gene is drawn uniformly from the interval spanned by the parent
+/// genes, extended by `alpha` times its width on both sides. `alpha = 0.5` is
+/// the common choice.
+pub fn blend<T: RealGene, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    alpha: f64,
+    rng: &mut R,
+) {
+    for i in 0..size {
+        let (x1, x2) = (parent1[i].to_f64(), parent2[i].to_f64());
+        let (low, high) = (x1.min(x2), x1.max(x2));
+        let extent = alpha * (high - low);
+        let (low, high) = (low - extent, high + extent);
+
+        if high > low {
+            child1[i] = T::from_f64(rng.random_range(low..high));
+            child2[i] = T::from_f64(rng.random_range(low..high));
+        } else {
+            child1[i] = parent1[i];
+            child2[i] = parent2[i];
+        }
+    }
+}
+
+/// Simulated binary crossover (SBX).
+///
+/// Spreads the children around the parents like single-point crossover does
+/// for binary strings. The distribution index `eta` controls the spread: large
+/// values create children close to their parents, typical values are 2 to 20.
+pub fn simulated_binary<T: RealGene, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    eta: f64,
+    rng: &mut R,
+) {
+    let exponent = 1.0 / (eta + 1.0);
+    for i in 0..size {
+        let (x1, x2) = (parent1[i].to_f64(), parent2[i].to_f64());
+        let u: f64 = rng.random();
+        let beta = if u <= 0.5 {
+            (2.0 * u).powf(exponent)
+        } else {
+            (1.0 / (2.0 * (1.0 - u))).powf(exponent)
+        };
+        child1[i] = T::from_f64(0.5 * ((1.0 + beta) * x1 + (1.0 - beta) * x2));
+        child2[i] = T::from_f64(0.5 * ((1.0 - beta) * x1 + (1.0 + beta) * x2));
+    }
+}
+
+/// Partially mapped crossover (PMX) for permutations.
+///
+/// The segment between two random cut points is copied from one parent, the
+/// remaining genes are placed by following the mapping between the segments,
+/// so that every child is a permutation again.
+pub fn partially_mapped<T: Copy + PartialEq, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    rng: &mut R,
+) {
+    if size < 2 {
+        child1.copy_from_slice(parent1);
+        child2.copy_from_slice(parent2);
+        return;
+    }
+    let (start, end) = segment(size, rng);
+    pmx_child(parent1, parent2, child1, start, end);
+    pmx_child(parent2, parent1, child2, start, end);
+}
+
+fn pmx_child<T: Copy + PartialEq>(
+    donor: &[T],
+    other: &[T],
+    child: &mut [T],
+    start: usize,
+    end: usize,
+) {
+    let mut filled = vec![false; child.len()];
+    child[start..end].copy_from_slice(&donor[start..end]);
+    filled[start..end].fill(true);
+
+    for i in start..end {
+        let gene = other[i];
+        if donor[start..end].contains(&gene) {
+            continue;
+        }
+        // follow the mapping until we leave the copied segment
+        let mut pos = i;
+        while (start..end).contains(&pos) {
+            pos = position(other, donor[pos]);
+        }
+        child[pos] = gene;
+        filled[pos] = true;
+    }
+
+    for (i, gene) in child.iter_mut().enumerate() {
+        if !filled[i] {
+            *gene = other[i];
+        }
+    }
+}
+
+/// Order crossover (OX1) for permutations.
+///
+/// The segment between two random cut points is copied from one parent, the
+/// remaining positions are filled with the missing genes in the order in which
+/// they appear in the other parent, starting after the segment.
+pub fn ordered<T: Copy + PartialEq, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    rng: &mut R,
+) {
+    if size < 2 {
+        child1.copy_from_slice(parent1);
+        child2.copy_from_slice(parent2);
+        return;
+    }
+    let (start, end) = segment(size, rng);
+    ox_child(parent1, parent2, child1, start, end);
+    ox_child(parent2, parent1, child2, start, end);
+}
+
+fn ox_child<T: Copy + PartialEq>(
+    donor: &[T],
+    other: &[T],
+    child: &mut [T],
+    start: usize,
+    end: usize,
+) {
+    let size = child.len();
+    child[start..end].copy_from_slice(&donor[start..end]);
+
+    let mut pos = end % size;
+    for offset in 0..size {
+        let gene = other[(end + offset) % size];
+        if donor[start..end].contains(&gene) {
+            continue;
+        }
+        child[pos] = gene;
+        pos = (pos + 1) % size;
+    }
+}
+
+/// Cycle crossover (CX) for permutations.
+///
+/// The positions are split into cycles between the parents. The children take
+/// the genes of alternating cycles from either parent, so every gene keeps the
+/// position it had in one of the parents.
+pub fn cycle<T: Copy + PartialEq, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut [T],
+    child2: &mut [T],
+    size: usize,
+    _rng: &mut R,
+) {
+    let mut visited = vec![false; size];
+    let mut swap = false;
+
+    for first in 0..size {
+        if visited[first] {
+            continue;
+        }
+        let mut pos = first;
+        while !visited[pos] {
+            visited[pos] = true;
+            if swap {
+                child1[pos] = parent2[pos];
+                child2[pos] = parent1[pos];
+            } else {
+                child1[pos] = parent1[pos];
+                child2[pos] = parent2[pos];
+            }
+            pos = position(parent1, parent2[pos]);
+        }
+        swap = !swap;
+    }
+}
+
+/// Cut-and-splice crossover for genomes of different lengths.
+///
+/// Each parent is cut at its own random point. `child1` is the head of
+/// `parent1` followed by the tail of `parent2`, `child2` the head of
+/// `parent2` followed by the tail of `parent1`. The children are cleared
+/// first, and their lengths usually differ from the parents', while the sum
+/// of their lengths equals that of the parents.
+pub fn cut_and_splice<T: Copy, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    child1: &mut Vec<T>,
+    child2: &mut Vec<T>,
+    rng: &mut R,
+) {
+    let cut1 = rng.random_range(0..=parent1.len());
+    let cut2 = rng.random_range(0..=parent2.len());
+
+    child1.clear();
+    child1.extend_from_slice(&parent1[..cut1]);
+    child1.extend_from_slice(&parent2[cut2..]);
+
+    child2.clear();
+    child2.extend_from_slice(&parent2[..cut2]);
+    child2.extend_from_slice(&parent1[cut1..]);
+}
+
+/// Two random cut points `start < end` in `0..=size`.
+fn segment<R: Rng + ?Sized>(size: usize, rng: &mut R) -> (usize, usize) {
+    let a = rng.random_range(0..=size);
+    let mut b = rng.random_range(0..size);
+    if b >= a {
+        b += 1;
+    }
+    (a.min(b), a.max(b))
+}
+
+/// Position of `gene` in the permutation `genotype`.
+///
+/// # Panics
+/// Panics if `genotype` does not contain `gene`, i.e. the parents are not
+/// permutations of the same genes.
+fn position<T: PartialEq>(genotype: &[T], gene: T) -> usize {
+    genotype
+        .iter()
+        .position(|g| *g == gene)
+        .expect("parents must be permutations of the same genes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    type Permutation = fn(&[u8], &[u8], &mut [u8], &mut [u8], usize, &mut ChaCha8Rng);
+
+    fn is_permutation_of(child: &[u8], parent: &[u8]) -> bool {
+        let (mut child, mut parent) = (child.to_vec(), parent.to_vec());
+        child.sort_unstable();
+        parent.sort_unstable();
+        child == parent
+    }
+
+    #[test]
+    fn permutation_operators_create_permutations() {
+        let operators: [(&str, Permutation); 3] =
+            [("pmx", partially_mapped), ("ox", ordered), ("cx", cycle)];
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for (name, operator) in operators {
+            for size in 0..12 {
+                for _ in 0..50 {
+                    let mut parent1: Vec<u8> = (0..size).collect();
+                    let mut parent2 = parent1.clone();
+                    parent1.shuffle(&mut rng);
+                    parent2.shuffle(&mut rng);
+                    let (mut child1, mut child2) = (vec![0; size as usize], vec![0; size as usize]);
+                    operator(
+                        &parent1,
+                        &parent2,
+                        &mut child1,
+                        &mut child2,
+                        size as usize,
+                        &mut rng,
+                    );
+                    assert!(
+                        is_permutation_of(&child1, &parent1)
+                            && is_permutation_of(&child2, &parent1),
+                        "{name}: {parent1:?} x {parent2:?} gave {child1:?} and {child2:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cycle_takes_alternating_cycles_from_either_parent() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let parent1 = [1, 2, 3, 4, 5, 6, 7, 8];
+        let (mut child1, mut child2) = ([0; 8], [0; 8]);
+        // cycles at the positions {0, 7, 6, 3}, {1, 4, 2} and {5}
+        let parent2 = [8, 5, 2, 1, 3, 6, 4, 7];
+        cycle(&parent1, &parent2, &mut child1, &mut child2, 8, &mut rng);
+        assert_eq!(child1, [1, 5, 2, 4, 3, 6, 7, 8]);
+        assert_eq!(child2, [8, 2, 3, 1, 5, 6, 4, 7]);
+        // cycles at the positions {0, 1}, {2, 3}, {4, 5} and {6, 7}
+        let parent2 = [2, 1, 4, 3, 6, 5, 8, 7];
+        cycle(&parent1, &parent2, &mut child1, &mut child2, 8, &mut rng);
+        assert_eq!(child1, [1, 2, 4, 3, 5, 6, 8, 7]);
+        assert_eq!(child2, [2, 1, 3, 4, 6, 5, 7, 8]);
+    }
+
+    #[test]
+    fn k_point_accepts_any_number_of_points() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let (parent1, parent2) = ([0u8; 6], [1u8; 6]);
+        let (mut child1, mut child2) = ([9; 6], [9; 6]);
+
+        k_point(&parent1, &parent2, &mut child1, &mut child2, 6, 0, &mut rng);
+        assert_eq!((child1, child2), (parent1, parent2));
+
+        for k in [5, 6, 100] {
+            k_point(&parent1, &parent2, &mut child1, &mut child2, 6, k, &mut rng);
+            // every position is a cut, so the children alternate
+            assert_eq!(child1, [0, 1, 0, 1, 0, 1]);
+            assert_eq!(child2, [1, 0, 1, 0, 1, 0]);
+        }
+
+        let (mut short1, mut short2) = ([9], [9]);
+        k_point(&[0], &[1], &mut short1, &mut short2, 1, 3, &mut rng);
+        assert_eq!((short1, short2), ([0], [1]));
+    }
+
+    #[test]
+    fn k_point_and_uniform_exchange_genes_position_by_position() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let (parent1, parent2) = ([0u8; 20], [1u8; 20]);
+        let (mut child1, mut child2) = ([9; 20], [9; 20]);
+        for k in 1..5 {
+            k_point(
+                &parent1,
+                &parent2,
+                &mut child1,
+                &mut child2,
+                20,
+                k,
+                &mut rng,
+            );
+            let changes = child1.windows(2).filter(|w| w[0] != w[1]).count();
+            assert_eq!(changes, k);
+            assert!(child1.iter().zip(&child2).all(|(a, b)| a + b == 1));
+        }
+        uniform(&parent1, &parent2, &mut child1, &mut child2, 20, &mut rng);
+        assert!(child1.iter().zip(&child2).all(|(a, b)| a + b == 1));
+    }
+
+    #[test]
+    fn blend_stays_within_the_extended_interval() {
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let (parent1, parent2): ([f64; 3], [f64; 3]) = ([1.0, -2.0, 5.0], [3.0, -2.0, -5.0]);
+        let (mut child1, mut child2) = ([0.0; 3], [0.0; 3]);
+        for alpha in [0.0, 0.5] {
+            for _ in 0..1000 {
+                blend(
+                    &parent1,
+                    &parent2,
+                    &mut child1,
+                    &mut child2,
+                    3,
+                    alpha,
+                    &mut rng,
+                );
+                for i in 0..3 {
+                    let (low, high) = (parent1[i].min(parent2[i]), parent1[i].max(parent2[i]));
+                    let extent = alpha * (high - low);
+                    for child in [child1[i], child2[i]] {
+                        assert!(low - extent <= child && child <= high + extent);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simulated_binary_spreads_the_children_around_the_parents() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let (parent1, parent2): ([f64; 3], [f64; 3]) = ([1.0, -2.0, 5.0], [3.0, -2.0, -5.0]);
+        let (mut child1, mut child2) = ([0.0; 3], [0.0; 3]);
+        let eta = 20.0;
+        // the spread factor of the largest uniform sample below 1
+        let max_beta = 2f64.powi(52).powf(1.0 / (eta + 1.0));
+        let mut inside = 0;
+        for _ in 0..1000 {
+            simulated_binary(
+                &parent1,
+                &parent2,
+                &mut child1,
+                &mut child2,
+                3,
+                eta,
+                &mut rng,
+            );
+            for i in 0..3 {
+                let (x1, x2) = (parent1[i], parent2[i]);
+                assert!((child1[i] + child2[i] - (x1 + x2)).abs() < 1e-9);
+                assert!((child1[i] - child2[i]).abs() <= max_beta * (x1 - x2).abs() + 1e-9);
+                let (low, high) = (x1.min(x2), x1.max(x2));
+                if low <= child1[i].min(child2[i]) && child1[i].max(child2[i]) <= high {
+                    inside += 1;
+                }
+            }
+        }
+        // identical parents never spread, the others contract half of the time
+        assert!((1800..2200).contains(&inside), "{inside}");
+    }
+
+    #[test]
+    fn integer_genes_saturate_at_their_bounds() {
+        let mut rng = ChaCha8Rng::seed_from_u64(6);
+        let (mut child1, mut child2) = ([0u8], [0u8]);
+        let (mut lowest, mut highest) = (false, false);
+        for _ in 0..100 {
+            // the interval reaches from -127.5 to 382.5
+            blend(&[0u8], &[255u8], &mut child1, &mut child2, 1, 0.5, &mut rng);
+            lowest |= child1[0] == 0 || child2[0] == 0;
+            highest |= child1[0] == 255 || child2[0] == 255;
+        }
+        assert!(lowest && highest);
+        assert_eq!(u8::from_f64(300.0), 255);
+        assert_eq!(i8::from_f64(-300.0), -128);
+    }
+}
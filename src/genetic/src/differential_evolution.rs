@@ -0,0 +1,409 @@
+//! # Differential Evolution
+//!
+//! [`DifferentialEvolution`] (Storn & Price, 1997) optimises real-valued
+//! genotypes, see [`RealGene`]. Instead of selecting parents and crossing
+//! them over, every member of the population competes with a trial vector:
+//!
+//! 1. A mutant is built from other members, `base + F * (a - b)`, where the
+//!    [`Strategy`] picks the base and `a`, `b` are distinct random members.
+//! 2. The trial takes every gene from the mutant with probability `CR`, and
+//!    at least one, the others from the member (binomial crossover).
+//! 3. The trial replaces the member if it is at least as fit.
+//!
+//! Only `Evaluator::evaluate` is used, `Genome::mutate` and
+//! `Genome::crossover` are not called. A phenotype written for a
+//! [`Population`](crate::Population) of real-valued genes can therefore be
+//! optimised by either without changes.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    Evaluator, Gene, GenotypeInitializer, Individual,
+    crossover::RealGene,
+    fitness, mutation,
+    statistics::{self, Observer, Observers, Statistics},
+    termination::{self, RunReport, Termination},
+};
+
+/// How the base vector of a mutant is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// DE/rand/1/bin: a random member. Explores well and converges slowly.
+    #[default]
+    Rand1Bin,
+    /// DE/best/1/bin: the fittest member. Converges fast, but may get stuck
+    /// in a local optimum.
+    Best1Bin,
+}
+
+/// A differential evolution population.
+///
+/// Every member has two slots, one holding the member and one for its
+/// trial. A trial that wins takes over as the member and the old slot is
+/// reused for the next trial, so the phenotype of a member always describes
+/// its genotype.
+#[derive(Debug)]
+pub struct DifferentialEvolution<E: Evaluator, R: Rng = ChaCha8Rng> {
+    genotype_arena: Vec<Gene<E>>,
+    phenotypes: Vec<E>,
+    genotype_size: usize,
+    rng: R,
+    strategy: Strategy,
+    weight: f64,
+    crossover_rate: f64,
+    /// The slot of every member.
+    members: Vec<usize>,
+    fitness: Vec<f64>,
+    evaluations: usize,
+    generation: usize,
+    history: Vec<Statistics>,
+    observers: Observers,
+}
+
+impl<E: Evaluator, R: Rng + SeedableRng> DifferentialEvolution<E, R>
+where
+    Gene<E>: RealGene,
+{
+    /// Creates a population seeded from the thread-local entropy source.
+    pub fn new(population_size: usize, genotype_size: usize) -> Self {
+        Self::with_rng(
+            population_size,
+            genotype_size,
+            R::from_rng(&mut rand::rng()),
+        )
+    }
+
+    /// Creates a population whose whole run is determined by `seed`.
+    pub fn with_seed(population_size: usize, genotype_size: usize, seed: u64) -> Self {
+        Self::with_rng(population_size, genotype_size, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: Evaluator, R: Rng> DifferentialEvolution<E, R>
+where
+    Gene<E>: RealGene,
+{
+    /// Creates a population driven by the given random number generator.
+    ///
+    /// # Panics
+    /// Panics if `population_size` is less than 4, the smallest population
+    /// a mutant can be built from.
+    pub fn with_rng(population_size: usize, genotype_size: usize, mut rng: R) -> Self {
+        assert!(
+            population_size >= 4,
+            "differential evolution needs at least 4 members"
+        );
+        let slots = 2 * population_size;
+        let mut genotype_arena = vec![Gene::<E>::default(); slots * genotype_size];
+        for genotype in genotype_arena[..population_size * genotype_size].chunks_mut(genotype_size)
+        {
+            Gene::<E>::initial_genotypes(genotype, &mut rng);
+        }
+
+        Self {
+            genotype_arena,
+            phenotypes: (0..slots).map(|i| E::new(Individual(i))).collect(),
+            genotype_size,
+            rng,
+            strategy: Strategy::default(),
+            weight: 0.8,
+            crossover_rate: 0.9,
+            members: (0..population_size).collect(),
+            fitness: vec![0.0; slots],
+            evaluations: 0,
+            generation: 0,
+            history: Vec::new(),
+            observers: Observers::default(),
+        }
+    }
+
+    /// Sets how the base vector of a mutant is chosen. Defaults to
+    /// [`Strategy::Rand1Bin`].
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the differential weight `F`, usually between 0.4 and 1. Defaults
+    /// to 0.8.
+    pub fn with_differential_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Sets the crossover rate `CR`, the probability of a trial gene coming
+    /// from the mutant. Defaults to 0.9.
+    pub fn with_crossover_rate(mut self, rate: f64) -> Self {
+        self.crossover_rate = mutation::probability(rate);
+        self
+    }
+
+    /// Registers an observer that is notified after every evaluated generation.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    /// Evaluates the initial members on the first call. Every later call
+    /// creates a trial for every member, evaluates the trials and keeps the
+    /// winners.
+    pub fn evolve(&mut self, param: &E::FitnessParam) {
+        if self.generation == 0 {
+            let members = self.members.clone();
+            self.calculate_fitness(&members, param);
+        } else {
+            let trials = self.create_trials();
+            self.calculate_fitness(&trials, param);
+            self.select(&trials);
+        }
+        self.record_statistics();
+    }
+
+    /// Evolves the population until `termination` is satisfied.
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<Gene<E>> {
+        termination::drive(
+            self,
+            |de| de.evolve(param),
+            |de| &de.history,
+            |de, index| de.genotype(index),
+            termination,
+        )
+    }
+
+    /// The slot of the trial of every member.
+    fn trial_slot(&self, member: usize) -> usize {
+        let n = self.members.len();
+        let slot = self.members[member];
+        if slot < n { slot + n } else { slot - n }
+    }
+
+    /// Writes a trial for every member into its trial slot.
+    fn create_trials(&mut self) -> Vec<usize> {
+        let n = self.members.len();
+        let genotype_size = self.genotype_size;
+        let best = fitness::argmax(self.members.iter().map(|&slot| self.fitness[slot]))
+            .expect("the population is not empty");
+        let mut trial = vec![Gene::<E>::default(); genotype_size];
+        let mut trials = Vec::with_capacity(n);
+
+        for member in 0..n {
+            let base = match self.strategy {
+                Strategy::Rand1Bin => self.pick(&[member]),
+                Strategy::Best1Bin => best,
+            };
+            let a = self.pick(&[member, base]);
+            let b = self.pick(&[member, base, a]);
+            let forced = self.rng.random_range(0..genotype_size.max(1));
+
+            let [base, a, b, target] =
+                [base, a, b, member].map(|m| self.members[m] * genotype_size);
+            let arena = &self.genotype_arena;
+            for (i, gene) in trial.iter_mut().enumerate() {
+                *gene = if i == forced || self.rng.random_bool(self.crossover_rate) {
+                    let difference = arena[a + i].to_f64() - arena[b + i].to_f64();
+                    Gene::<E>::from_f64(arena[base + i].to_f64() + self.weight * difference)
+                } else {
+                    arena[target + i]
+                };
+            }
+
+            let slot = self.trial_slot(member);
+            let start = slot * genotype_size;
+            self.genotype_arena[start..start + genotype_size].copy_from_slice(&trial);
+            trials.push(slot);
+        }
+        trials
+    }
+
+    /// A random member other than the `excluded` ones.
+    fn pick(&mut self, excluded: &[usize]) -> usize {
+        loop {
+            let member = self.rng.random_range(0..self.members.len());
+            if !excluded.contains(&member) {
+                return member;
+            }
+        }
+    }
+
+    fn calculate_fitness(&mut self, slots: &[usize], param: &E::FitnessParam) {
+        for &slot in slots {
+            let start = slot * self.genotype_size;
+            let genotype = &self.genotype_arena[start..start + self.genotype_size];
+            self.phenotypes[slot].evaluate(genotype, param);
+            self.fitness[slot] = self.phenotypes[slot].fitness();
+        }
+        self.evaluations += slots.len();
+
+        // Clamp the new values into the range of the members and new values.
+        let mut values: Vec<f64> = self
+            .members
+            .iter()
+            .chain(slots)
+            .map(|&slot| self.fitness[slot])
+            .collect();
+        fitness::sanitize(&mut values);
+        for (&slot, value) in self.members.iter().chain(slots).zip(values) {
+            self.fitness[slot] = value;
+        }
+    }
+
+    /// Every trial at least as fit as its member takes the member's place.
+    fn select(&mut self, trials: &[usize]) {
+        for (member, &trial) in trials.iter().enumerate() {
+            let current = self.members[member];
+            if fitness::compare(self.fitness[trial], self.fitness[current]).is_ge() {
+                self.members[member] = trial;
+            }
+        }
+    }
+
+    /// Summarises the fitness of the members. `elite_index` is the slot of
+    /// the fittest member.
+    fn record_statistics(&mut self) {
+        let fitness: Vec<f64> = self
+            .members
+            .iter()
+            .map(|&slot| self.fitness[slot])
+            .collect();
+        let mut statistics = Statistics::from_fitness(self.generation, &fitness);
+
+        let members: Vec<Gene<E>> = self
+            .members
+            .iter()
+            .flat_map(|&slot| self.genotype(slot).iter().copied())
+            .collect();
+        statistics.diversity =
+            statistics::diversity(&members, self.genotype_size, statistics.elite_index);
+        statistics.elite_index = self.members[statistics.elite_index];
+        statistics.evaluations = self.evaluations;
+
+        self.observers.notify(&statistics);
+        self.history.push(statistics);
+        self.generation += 1;
+    }
+
+    fn genotype(&self, slot: usize) -> &[Gene<E>] {
+        let start = slot * self.genotype_size;
+        &self.genotype_arena[start..start + self.genotype_size]
+    }
+
+    /// The fittest member.
+    pub fn fittest_phenotype(&self) -> Option<&E> {
+        fitness::argmax(self.members.iter().map(|&slot| self.fitness[slot]))
+            .map(|member| &self.phenotypes[self.members[member]])
+    }
+
+    /// The current members.
+    pub fn members(&self) -> impl Iterator<Item = &E> {
+        self.members.iter().map(|&slot| &self.phenotypes[slot])
+    }
+
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The statistics of every evaluated generation, oldest first.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
+    /// All phenotypes, members and trials.
+    pub fn get_phenotypes(&self) -> &[E] {
+        &self.phenotypes
+    }
+
+    /// Retrieves the genotype of a phenotype.
+    ///
+    /// # Panics
+    /// Panics if the phenotype's index is out of bounds.
+    pub fn get_genotype(&self, phenotype: &E) -> &[Gene<E>] {
+        self.genotype(crate::slot_of(&self.phenotypes, phenotype))
+    }
+
+    /// Iterates over each member mutably, providing its genotype slice.
+    pub fn for_each_phenotype_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut E, &[Gene<E>]),
+    {
+        let genotype_size = self.genotype_size;
+        for &slot in &self.members {
+            let start = slot * genotype_size;
+            func(
+                &mut self.phenotypes[slot],
+                &self.genotype_arena[start..start + genotype_size],
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Genome, termination::MaxGenerations};
+
+    struct Reals;
+
+    impl Genome for Reals {
+        type Gene = f64;
+
+        fn mutate<R: Rng + ?Sized>(_: &mut [f64], _: &mut R) {}
+
+        fn crossover<R: Rng + ?Sized>(
+            parent1: &[f64],
+            parent2: &[f64],
+            child1: &mut [f64],
+            child2: &mut [f64],
+            _: usize,
+            _: &mut R,
+        ) {
+            child1.copy_from_slice(parent1);
+            child2.copy_from_slice(parent2);
+        }
+    }
+
+    /// The negated squared distance to 0.3 in every gene.
+    struct Sphere {
+        fitness: f64,
+    }
+
+    impl Evaluator for Sphere {
+        type Genome = Reals;
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            Self { fitness: 0.0 }
+        }
+
+        fn evaluate(&mut self, genotype: &[f64], _: &()) {
+            self.fitness = -genotype.iter().map(|x| (x - 0.3) * (x - 0.3)).sum::<f64>();
+        }
+
+        fn fitness(&self) -> f64 {
+            self.fitness
+        }
+    }
+
+    #[test]
+    fn converges_on_the_sphere() {
+        for strategy in [Strategy::Rand1Bin, Strategy::Best1Bin] {
+            let mut de =
+                DifferentialEvolution::<Sphere>::with_seed(30, 5, 1).with_strategy(strategy);
+            let report = de.run(&(), MaxGenerations::new(500));
+            assert!(
+                report.best_fitness > -1e-8,
+                "{strategy:?}: {}",
+                report.best_fitness
+            );
+        }
+    }
+}
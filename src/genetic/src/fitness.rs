@@ -0,0 +1,58 @@
+//! # Fitness Ordering
+//!
+//! Fitness values are plain `f64`s returned by user code, so they may be NaN
+//! or infinite. The crate never compares them with `partial_cmp(..).unwrap()`,
+//! instead it uses the policy defined here:
+//!
+//! - NaN is the worst possible fitness. It compares equal to other NaNs and
+//!   below everything else, including negative infinity.
+//! - When the fitness of a generation is collected, NaN and infinite values
+//!   are clamped into the finite range of that generation (see [`sanitize`]),
+//!   so a broken individual only loses its chance to reproduce instead of
+//!   poisoning the statistics or the selection of everybody else.
+
+use std::cmp::Ordering;
+
+/// Total order of fitness values, NaN being the worst.
+pub fn compare(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        // adding 0.0 turns -0.0 into 0.0, so that both compare equal
+        (false, false) => (a + 0.0).total_cmp(&(b + 0.0)),
+    }
+}
+
+/// Replaces the non-finite values of a generation's fitness.
+///
+/// NaN and negative infinity become the lowest finite fitness of the slice,
+/// positive infinity becomes the highest. If no value is finite, all of them
+/// are set to `0.0`.
+pub fn sanitize(fitness: &mut [f64]) {
+    let (min, max) = fitness
+        .iter()
+        .copied()
+        .filter(|f| f.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), f| {
+            (min.min(f), max.max(f))
+        });
+    let (min, max) = if min > max { (0.0, 0.0) } else { (min, max) };
+
+    for f in fitness.iter_mut() {
+        if f.is_nan() || *f == f64::NEG_INFINITY {
+            *f = min;
+        } else if *f == f64::INFINITY {
+            *f = max;
+        }
+    }
+}
+
+/// Index of the fittest value, `None` for an empty slice.
+pub fn argmax(fitness: impl IntoIterator<Item = f64>) -> Option<usize> {
+    fitness
+        .into_iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| compare(*a, *b))
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,1167 @@
+//! # Genetic Programming
+//!
+//! Evolves programs, expression trees built from a [`PrimitiveSet`] of
+//! functions and terminals, instead of fixed-length genotypes.
+//!
+//! - Primitives are typed: every primitive has a return type and a type for
+//!   each argument, and trees are only ever built, crossed over and mutated
+//!   so that every argument gets a value of its type. Untyped programs use
+//!   `()` as the only type.
+//! - The initial programs are created by [`ramped_half_and_half`].
+//! - [`subtree_crossover`] swaps subtrees of the same type between two
+//!   parents, [`point_mutation`] and [`subtree_mutation`] change single
+//!   nodes or whole subtrees.
+//! - Bloat, programs growing without getting fitter, is held back by limits
+//!   on the depth and size of the offspring and by parsimony pressure, see
+//!   [`GpPopulation::with_max_depth`] and [`GpPopulation::with_parsimony`].
+//!
+//! A [`Program`] is interpreted by the [`ProgramEvaluator`], either with
+//! [`Program::evaluate`] or by walking it from [`Program::root`]. A maze
+//! policy such as "if there is a wall ahead, turn left, otherwise move
+//! forward" could be built from these primitives:
+//!
+//! ```
+//! use genetic::gp::{PrimitiveSet, ramped_half_and_half};
+//! use rand::SeedableRng;
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq)]
+//! enum Policy {
+//!     IfThenElse,
+//!     WallAhead,
+//!     WallLeft,
+//!     Forward,
+//!     Left,
+//!     Right,
+//! }
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+//! enum Type {
+//!     Bool,
+//!     Action,
+//! }
+//!
+//! let primitives = PrimitiveSet::new()
+//!     .with_function(Policy::IfThenElse, Type::Action, &[Type::Bool, Type::Action, Type::Action])
+//!     .with_terminal(Policy::WallAhead, Type::Bool)
+//!     .with_terminal(Policy::WallLeft, Type::Bool)
+//!     .with_terminal(Policy::Forward, Type::Action)
+//!     .with_terminal(Policy::Left, Type::Action)
+//!     .with_terminal(Policy::Right, Type::Action);
+//!
+//! let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+//! let programs = ramped_half_and_half(&primitives, Type::Action, 1..=4, 100, &mut rng);
+//! assert!(programs.iter().all(|program| program.depth() <= 4));
+//! ```
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    Individual, fitness, mutation,
+    selection::{Selection, Tournament},
+    statistics::{Observer, Observers, Statistics},
+    termination::{self, RunReport, Termination},
+};
+
+/// The functions and terminals programs are built from.
+#[derive(Debug, Clone)]
+pub struct PrimitiveSet<P, T = ()> {
+    signatures: Vec<Signature<P, T>>,
+}
+
+#[derive(Debug, Clone)]
+struct Signature<P, T> {
+    kind: Kind<P>,
+    returns: T,
+    arguments: Vec<T>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind<P> {
+    Fixed(P),
+    /// A terminal whose value is drawn whenever a node is created.
+    Ephemeral(fn(&mut dyn RngCore) -> P),
+}
+
+impl<P: Copy, T: Copy + Eq + fmt::Debug> Default for PrimitiveSet<P, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Copy, T: Copy + Eq + fmt::Debug> PrimitiveSet<P, T> {
+    pub fn new() -> Self {
+        Self {
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Adds a function returning a `returns` from arguments of the given
+    /// types.
+    pub fn with_function(mut self, primitive: P, returns: T, arguments: &[T]) -> Self {
+        self.signatures.push(Signature {
+            kind: Kind::Fixed(primitive),
+            returns,
+            arguments: arguments.to_vec(),
+        });
+        self
+    }
+
+    /// Adds a terminal, a primitive without arguments.
+    pub fn with_terminal(self, primitive: P, returns: T) -> Self {
+        self.with_function(primitive, returns, &[])
+    }
+
+    /// Adds an ephemeral random constant: a terminal whose value is drawn
+    /// by `sample` for every node created from it, e.g. a random number.
+    pub fn with_ephemeral(mut self, returns: T, sample: fn(&mut dyn RngCore) -> P) -> Self {
+        self.signatures.push(Signature {
+            kind: Kind::Ephemeral(sample),
+            returns,
+            arguments: Vec::new(),
+        });
+        self
+    }
+
+    fn node<R: Rng + ?Sized>(&self, signature: usize, rng: &mut R) -> Node<P, T> {
+        let Signature {
+            kind,
+            returns,
+            arguments,
+        } = &self.signatures[signature];
+        let primitive = match kind {
+            Kind::Fixed(primitive) => *primitive,
+            Kind::Ephemeral(sample) => {
+                let mut rng = rng;
+                sample(&mut rng)
+            }
+        };
+        Node {
+            primitive,
+            returns: *returns,
+            arity: arguments.len(),
+            signature,
+        }
+    }
+
+    /// Appends a random tree returning `returns` to `nodes`. `depth` is the
+    /// depth still allowed, `full` builds only functions above it.
+    ///
+    /// # Panics
+    /// Panics if no finite tree returns `returns`, e.g. because every
+    /// primitive returning it needs an argument of its own type and there
+    /// is no terminal of that type.
+    fn generate<R: Rng + ?Sized>(
+        &self,
+        returns: T,
+        depth: usize,
+        full: bool,
+        nodes: &mut Vec<Node<P, T>>,
+        rng: &mut R,
+    ) {
+        let heights = self.heights();
+        assert!(
+            self.type_height(&heights, returns).is_some(),
+            "the primitive set has no finite program returning {returns:?}: every primitive \
+             returning it needs an argument of a type that never ends in a terminal"
+        );
+        self.generate_with(&heights, returns, depth, full, nodes, rng);
+    }
+
+    fn generate_with<R: Rng + ?Sized>(
+        &self,
+        heights: &[Option<usize>],
+        returns: T,
+        depth: usize,
+        full: bool,
+        nodes: &mut Vec<Node<P, T>>,
+        rng: &mut R,
+    ) {
+        // Only primitives whose lowest tree fits into the depth are used. If
+        // there is none, the type needs a deeper tree, and the lowest ones
+        // are used so that every branch ends as soon as the types allow.
+        let lowest = self.type_height(heights, returns).unwrap_or(0);
+        let fits = |height: Option<usize>| height.is_some_and(|h| h <= depth.max(lowest));
+        let typed = |terminal: bool| -> Vec<usize> {
+            (0..self.signatures.len())
+                .filter(|&i| {
+                    let s = &self.signatures[i];
+                    s.returns == returns && s.arguments.is_empty() == terminal && fits(heights[i])
+                })
+                .collect()
+        };
+        let terminals = typed(true);
+        let functions = typed(false);
+
+        let candidates = if functions.is_empty() || (depth == 0 && !terminals.is_empty()) {
+            &terminals
+        } else if full || terminals.is_empty() {
+            &functions
+        } else {
+            // grow: every primitive of the type is equally likely
+            let i = rng.random_range(0..terminals.len() + functions.len());
+            if i < terminals.len() {
+                &terminals
+            } else {
+                &functions
+            }
+        };
+
+        let signature = candidates[rng.random_range(0..candidates.len())];
+        nodes.push(self.node(signature, rng));
+        for &argument in &self.signatures[signature].arguments {
+            self.generate_with(heights, argument, depth.saturating_sub(1), full, nodes, rng);
+        }
+    }
+
+    /// The depth of the lowest tree rooted at every signature, `None` if
+    /// one of its arguments has no finite tree.
+    fn heights(&self) -> Vec<Option<usize>> {
+        let mut heights = vec![None; self.signatures.len()];
+        loop {
+            let mut changed = false;
+            for (i, signature) in self.signatures.iter().enumerate() {
+                let height = signature
+                    .arguments
+                    .iter()
+                    .try_fold(0, |height: usize, &argument| {
+                        Some(height.max(self.type_height(&heights, argument)? + 1))
+                    });
+                if height.is_some() && height != heights[i] {
+                    heights[i] = height;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return heights;
+            }
+        }
+    }
+
+    /// The depth of the lowest tree returning `returns`.
+    fn type_height(&self, heights: &[Option<usize>], returns: T) -> Option<usize> {
+        self.signatures
+            .iter()
+            .zip(heights)
+            .filter(|(signature, _)| signature.returns == returns)
+            .filter_map(|(_, height)| *height)
+            .min()
+    }
+}
+
+/// A node of a [`Program`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Node<P, T = ()> {
+    primitive: P,
+    returns: T,
+    arity: usize,
+    /// Index into the signatures of the primitive set.
+    signature: usize,
+}
+
+impl<P, T: Copy> Node<P, T> {
+    pub fn primitive(&self) -> &P {
+        &self.primitive
+    }
+
+    pub fn returns(&self) -> T {
+        self.returns
+    }
+
+    /// The number of arguments.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+}
+
+/// An expression tree, stored as its nodes in prefix order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program<P, T = ()> {
+    nodes: Vec<Node<P, T>>,
+}
+
+impl<P, T: Copy> Program<P, T> {
+    /// Rebuilds a program from its nodes in prefix order, e.g. the
+    /// `best_genotype` of a [`RunReport`].
+    ///
+    /// # Panics
+    /// Panics if the nodes do not form exactly one complete tree.
+    pub fn from_nodes(nodes: Vec<Node<P, T>>) -> Self {
+        assert!(
+            !nodes.is_empty() && subtree_end(&nodes, 0) == nodes.len(),
+            "the nodes must form exactly one tree"
+        );
+        Self { nodes }
+    }
+
+    pub fn nodes(&self) -> &[Node<P, T>] {
+        &self.nodes
+    }
+
+    /// The number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always `false`, a program has at least one node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The number of edges on the longest path from the root to a leaf. A
+    /// single terminal has depth 0.
+    pub fn depth(&self) -> usize {
+        // the number of arguments still missing on every level
+        let mut open: Vec<usize> = Vec::new();
+        let mut depth = 0;
+        for node in &self.nodes {
+            depth = depth.max(open.len());
+            if let Some(missing) = open.last_mut() {
+                *missing -= 1;
+            }
+            if node.arity > 0 {
+                open.push(node.arity);
+            }
+            while open.last() == Some(&0) {
+                open.pop();
+            }
+        }
+        depth
+    }
+
+    pub fn root(&self) -> Subtree<'_, P, T> {
+        Subtree { nodes: &self.nodes }
+    }
+
+    /// Evaluates the program bottom-up: `apply` is called for every node
+    /// with the values of its arguments, the children before their parent.
+    ///
+    /// All arguments are evaluated, so primitives with side effects or
+    /// short-circuiting, like an `if` that must only run one branch, are
+    /// better interpreted by walking the tree from [`Program::root`].
+    pub fn evaluate<V>(&self, mut apply: impl FnMut(&P, Vec<V>) -> V) -> V {
+        let mut stack: Vec<V> = Vec::new();
+        for node in self.nodes.iter().rev() {
+            // the arguments are on top of the stack, the first one last
+            let arguments: Vec<V> = stack.drain(stack.len() - node.arity..).rev().collect();
+            stack.push(apply(&node.primitive, arguments));
+        }
+        stack.pop().expect("a program has at least one node")
+    }
+
+    /// The range of the subtree rooted at `start`.
+    fn subtree(&self, start: usize) -> std::ops::Range<usize> {
+        start..subtree_end(&self.nodes, start)
+    }
+}
+
+/// Prints the program as an S-expression, e.g. `(IfThenElse WallAhead Left Forward)`.
+impl<P: fmt::Debug, T: Copy> fmt::Display for Program<P, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root().fmt(f)
+    }
+}
+
+/// The end of the subtree rooted at `start`, one past its last node.
+fn subtree_end<P, T>(nodes: &[Node<P, T>], start: usize) -> usize {
+    let mut open = 1;
+    let mut end = start;
+    while open > 0 {
+        open = open + nodes[end].arity - 1;
+        end += 1;
+    }
+    end
+}
+
+/// A subtree of a [`Program`], for interpreting it top-down.
+#[derive(Debug, Clone, Copy)]
+pub struct Subtree<'a, P, T = ()> {
+    /// Exactly the nodes of the subtree.
+    nodes: &'a [Node<P, T>],
+}
+
+impl<'a, P, T: Copy> Subtree<'a, P, T> {
+    pub fn primitive(&self) -> &'a P {
+        &self.nodes[0].primitive
+    }
+
+    pub fn node(&self) -> &'a Node<P, T> {
+        &self.nodes[0]
+    }
+
+    /// The subtrees of the arguments, in order.
+    pub fn children(&self) -> impl Iterator<Item = Subtree<'a, P, T>> + use<'a, P, T> {
+        let nodes = self.nodes;
+        let mut start = 1;
+        (0..nodes[0].arity).map(move |_| {
+            let end = subtree_end(nodes, start);
+            let child = Subtree {
+                nodes: &nodes[start..end],
+            };
+            start = end;
+            child
+        })
+    }
+
+    /// The subtree of argument `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is not less than the arity.
+    pub fn child(&self, index: usize) -> Subtree<'a, P, T> {
+        self.children()
+            .nth(index)
+            .expect("the argument index must be less than the arity")
+    }
+}
+
+impl<P: fmt::Debug, T: Copy> fmt::Display for Subtree<'_, P, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.node().arity == 0 {
+            return write!(f, "{:?}", self.primitive());
+        }
+        write!(f, "({:?}", self.primitive())?;
+        for child in self.children() {
+            write!(f, " {child}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Creates a tree in which every leaf is at exactly `depth`, as far as the
+/// types allow.
+///
+/// # Panics
+/// Panics if no finite tree returns `returns`, e.g. because the set has
+/// no terminal to end a type that only its own functions return.
+pub fn full<P: Copy, T: Copy + Eq + fmt::Debug, R: Rng + ?Sized>(
+    primitives: &PrimitiveSet<P, T>,
+    returns: T,
+    depth: usize,
+    rng: &mut R,
+) -> Program<P, T> {
+    let mut nodes = Vec::new();
+    primitives.generate(returns, depth, true, &mut nodes, rng);
+    Program { nodes }
+}
+
+/// Creates a tree of at most `max_depth`, as far as the types allow, whose
+/// branches stop growing at random.
+///
+/// # Panics
+/// Panics if no finite tree returns `returns`, e.g. because the set has
+/// no terminal to end a type that only its own functions return.
+pub fn grow<P: Copy, T: Copy + Eq + fmt::Debug, R: Rng + ?Sized>(
+    primitives: &PrimitiveSet<P, T>,
+    returns: T,
+    max_depth: usize,
+    rng: &mut R,
+) -> Program<P, T> {
+    let mut nodes = Vec::new();
+    primitives.generate(returns, max_depth, false, &mut nodes, rng);
+    Program { nodes }
+}
+
+/// Creates `count` programs, spread evenly over the maximum depths in
+/// `depths`. For every depth, half of the programs are built with [`full`]
+/// and half with [`grow`].
+///
+/// If the set lacks a terminal of some type, trees grow deeper than the
+/// range until they can be completed.
+pub fn ramped_half_and_half<P: Copy, T: Copy + Eq + fmt::Debug, R: Rng + ?Sized>(
+    primitives: &PrimitiveSet<P, T>,
+    returns: T,
+    depths: RangeInclusive<usize>,
+    count: usize,
+    rng: &mut R,
+) -> Vec<Program<P, T>> {
+    let (min, max) = (*depths.start(), *depths.end().max(depths.start()));
+    let steps = max - min + 1;
+    (0..count)
+        .map(|i| {
+            let depth = min + i % steps;
+            if (i / steps).is_multiple_of(2) {
+                full(primitives, returns, depth, rng)
+            } else {
+                grow(primitives, returns, depth, rng)
+            }
+        })
+        .collect()
+}
+
+/// Picks a random node accepted by `filter`. Like Koza, a function is picked
+/// with probability 0.9 if there is one, a terminal otherwise.
+fn pick_node<P, T: Copy, R: Rng + ?Sized>(
+    program: &Program<P, T>,
+    filter: impl Fn(&Node<P, T>) -> bool,
+    rng: &mut R,
+) -> Option<usize> {
+    let candidates = |function: bool| -> Vec<usize> {
+        (0..program.len())
+            .filter(|&i| (program.nodes[i].arity > 0) == function && filter(&program.nodes[i]))
+            .collect()
+    };
+    let functions = candidates(true);
+    let terminals = candidates(false);
+    let pool = if !functions.is_empty() && (terminals.is_empty() || rng.random_bool(0.9)) {
+        functions
+    } else {
+        terminals
+    };
+    (!pool.is_empty()).then(|| pool[rng.random_range(0..pool.len())])
+}
+
+/// Subtree crossover: swaps a random subtree of `parent1` with a random
+/// subtree of the same type of `parent2`. If `parent2` has no subtree of
+/// that type, the children are copies of the parents.
+pub fn subtree_crossover<P: Copy, T: Copy + Eq, R: Rng + ?Sized>(
+    parent1: &Program<P, T>,
+    parent2: &Program<P, T>,
+    rng: &mut R,
+) -> (Program<P, T>, Program<P, T>) {
+    let cut1 = pick_node(parent1, |_| true, rng).expect("a program has at least one node");
+    let returns = parent1.nodes[cut1].returns;
+    let Some(cut2) = pick_node(parent2, |node| node.returns == returns, rng) else {
+        return (parent1.clone(), parent2.clone());
+    };
+
+    let (range1, range2) = (parent1.subtree(cut1), parent2.subtree(cut2));
+    let splice = |into: &Program<P, T>, range: std::ops::Range<usize>, from: &[Node<P, T>]| {
+        let mut nodes = Vec::with_capacity(into.len() - range.len() + from.len());
+        nodes.extend_from_slice(&into.nodes[..range.start]);
+        nodes.extend_from_slice(from);
+        nodes.extend_from_slice(&into.nodes[range.end..]);
+        Program { nodes }
+    };
+    (
+        splice(parent1, range1.clone(), &parent2.nodes[range2.clone()]),
+        splice(parent2, range2, &parent1.nodes[range1]),
+    )
+}
+
+/// Point mutation: replaces every node with probability `rate` by a random
+/// primitive with the same signature. Ephemeral constants get a new value.
+pub fn point_mutation<P: Copy, T: Copy + Eq + fmt::Debug, R: Rng + ?Sized>(
+    program: &mut Program<P, T>,
+    primitives: &PrimitiveSet<P, T>,
+    rate: f64,
+    rng: &mut R,
+) {
+    let rate = mutation::probability(rate);
+    for node in program.nodes.iter_mut() {
+        if !rng.random_bool(rate) {
+            continue;
+        }
+        let current = &primitives.signatures[node.signature];
+        let alternatives: Vec<usize> = primitives
+            .signatures
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.returns == current.returns && s.arguments == current.arguments)
+            .map(|(i, _)| i)
+            .collect();
+        let signature = alternatives[rng.random_range(0..alternatives.len())];
+        *node = primitives.node(signature, rng);
+    }
+}
+
+/// Subtree mutation: replaces a random subtree by a new one of the same
+/// type, built with [`grow`] up to `max_depth`.
+pub fn subtree_mutation<P: Copy, T: Copy + Eq + fmt::Debug, R: Rng + ?Sized>(
+    program: &mut Program<P, T>,
+    primitives: &PrimitiveSet<P, T>,
+    max_depth: usize,
+    rng: &mut R,
+) {
+    let start = rng.random_range(0..program.len());
+    let range = program.subtree(start);
+    let replacement = grow(primitives, program.nodes[start].returns, max_depth, rng);
+    program.nodes.splice(range, replacement.nodes);
+}
+
+/// Evaluates the program of one individual of a [`GpPopulation`], the
+/// counterpart of [`Evaluator`](crate::Evaluator) for programs.
+pub trait ProgramEvaluator {
+    /// The primitives programs are built from.
+    type Primitive: Copy + PartialEq + fmt::Debug;
+    /// The types of the primitives, `()` for untyped programs.
+    type Type: Copy + Eq + fmt::Debug;
+    /// The type of the parameter needed for fitness calculation.
+    type FitnessParam;
+
+    fn new(individual: Individual) -> Self;
+
+    /// Calculates the fitness of `program`, to be returned by
+    /// [`ProgramEvaluator::fitness`].
+    fn evaluate(
+        &mut self,
+        program: &Program<Self::Primitive, Self::Type>,
+        param: &Self::FitnessParam,
+    );
+
+    fn fitness(&self) -> f64;
+}
+
+type ProgramOf<E> = Program<<E as ProgramEvaluator>::Primitive, <E as ProgramEvaluator>::Type>;
+
+/// A population of programs.
+///
+/// Every generation the elites are copied, and the other programs are bred
+/// from selected parents by [`subtree_crossover`], [`subtree_mutation`] and
+/// [`point_mutation`]. A child deeper or larger than the limits is replaced
+/// by its parent.
+#[derive(Debug)]
+pub struct GpPopulation<E: ProgramEvaluator, R: Rng = ChaCha8Rng> {
+    programs: Vec<ProgramOf<E>>,
+    next_programs: Vec<ProgramOf<E>>,
+    phenotypes: Vec<E>,
+    primitives: PrimitiveSet<E::Primitive, E::Type>,
+    returns: E::Type,
+    rng: R,
+    selection: Box<dyn Selection>,
+    crossover_rate: f64,
+    subtree_mutation_rate: f64,
+    point_mutation_rate: f64,
+    max_depth: usize,
+    max_size: usize,
+    parsimony: f64,
+    fitness: Vec<f64>,
+    parents: Vec<usize>,
+    elitism: usize,
+    evaluations: usize,
+    generation: usize,
+    history: Vec<Statistics>,
+    observers: Observers,
+}
+
+impl<E: ProgramEvaluator, R: Rng + SeedableRng> GpPopulation<E, R> {
+    /// Creates a population seeded from the thread-local entropy source.
+    /// The programs return `returns`.
+    pub fn new(
+        population_size: usize,
+        primitives: PrimitiveSet<E::Primitive, E::Type>,
+        returns: E::Type,
+    ) -> Self {
+        Self::with_rng(
+            population_size,
+            primitives,
+            returns,
+            R::from_rng(&mut rand::rng()),
+        )
+    }
+
+    /// Creates a population whose whole run is determined by `seed`.
+    pub fn with_seed(
+        population_size: usize,
+        primitives: PrimitiveSet<E::Primitive, E::Type>,
+        returns: E::Type,
+        seed: u64,
+    ) -> Self {
+        Self::with_rng(population_size, primitives, returns, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: ProgramEvaluator, R: Rng> GpPopulation<E, R> {
+    /// Creates a population driven by the given random number generator.
+    ///
+    /// The programs are created by [`ramped_half_and_half`] with depths
+    /// from 2 to 6.
+    pub fn with_rng(
+        population_size: usize,
+        primitives: PrimitiveSet<E::Primitive, E::Type>,
+        returns: E::Type,
+        rng: R,
+    ) -> Self {
+        Self {
+            programs: Vec::new(),
+            next_programs: Vec::new(),
+            phenotypes: (0..population_size)
+                .map(|i| E::new(Individual(i)))
+                .collect(),
+            primitives,
+            returns,
+            rng,
+            selection: Box::new(Tournament::default()),
+            crossover_rate: 0.9,
+            subtree_mutation_rate: 0.1,
+            point_mutation_rate: 0.0,
+            max_depth: 17,
+            max_size: usize::MAX,
+            parsimony: 0.0,
+            fitness: vec![0.0; population_size],
+            parents: Vec::new(),
+            elitism: 1.min(population_size),
+            evaluations: 0,
+            generation: 0,
+            history: Vec::new(),
+            observers: Observers::default(),
+        }
+        .with_initial_depth(2..=6)
+    }
+
+    /// Replaces the programs by new ones created by [`ramped_half_and_half`]
+    /// with the given depths.
+    pub fn with_initial_depth(mut self, depths: RangeInclusive<usize>) -> Self {
+        self.programs = ramped_half_and_half(
+            &self.primitives,
+            self.returns,
+            depths,
+            self.phenotypes.len(),
+            &mut self.rng,
+        );
+        self.next_programs = self.programs.clone();
+        self
+    }
+
+    /// Replaces the parent selection strategy. Defaults to a tournament of size 3.
+    pub fn with_selection(mut self, selection: impl Selection + 'static) -> Self {
+        self.selection = Box::new(selection);
+        self
+    }
+
+    /// Sets the number of the fittest programs that are copied unchanged
+    /// into the next generation. Defaults to 1, capped at the population size.
+    pub fn with_elitism(mut self, count: usize) -> Self {
+        self.elitism = count.min(self.phenotypes.len());
+        self
+    }
+
+    /// Sets the probability that a pair of parents is crossed over instead of
+    /// copied. Defaults to 0.9.
+    pub fn with_crossover_rate(mut self, rate: f64) -> Self {
+        self.crossover_rate = mutation::probability(rate);
+        self
+    }
+
+    /// Sets the probability of a child undergoing [`subtree_mutation`].
+    /// Defaults to 0.1.
+    pub fn with_subtree_mutation_rate(mut self, rate: f64) -> Self {
+        self.subtree_mutation_rate = mutation::probability(rate);
+        self
+    }
+
+    /// Sets the probability of every node of a child undergoing
+    /// [`point_mutation`]. Defaults to 0.
+    pub fn with_point_mutation_rate(mut self, rate: f64) -> Self {
+        self.point_mutation_rate = mutation::probability(rate);
+        self
+    }
+
+    /// Sets the maximum depth of a child. Deeper children are replaced by
+    /// their parent. Defaults to 17.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets the maximum number of nodes of a child. Larger children are
+    /// replaced by their parent. Defaults to no limit.
+    pub fn with_max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Parsimony pressure: parents are selected by their fitness minus
+    /// `coefficient` times their number of nodes, so that of two equally fit
+    /// programs the smaller one is preferred. The statistics still report
+    /// the unpenalised fitness. Defaults to 0.
+    pub fn with_parsimony(mut self, coefficient: f64) -> Self {
+        self.parsimony = coefficient;
+        self
+    }
+
+    /// Registers an observer that is notified after every evaluated generation.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    pub fn evolve(&mut self, param: &E::FitnessParam) {
+        // 1. Calculate fitness for the current generation.
+        self.calculate_fitness(param);
+        self.record_statistics();
+
+        // 2. Select parents and create the next generation.
+        self.copy_elite();
+        self.create_offspring();
+
+        // 3. Swap. The new generation is now the current one.
+        std::mem::swap(&mut self.programs, &mut self.next_programs);
+    }
+
+    /// Evolves the population until `termination` is satisfied.
+    ///
+    /// The `best_genotype` of the report holds the nodes of the best
+    /// program, see [`Program::from_nodes`].
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<Node<E::Primitive, E::Type>> {
+        termination::drive(
+            self,
+            |population| population.evolve(param),
+            |population| &population.history,
+            // after `evolve` the evaluated generation lives in `next_programs`
+            |population, index| population.next_programs[index].nodes(),
+            termination,
+        )
+    }
+
+    fn calculate_fitness(&mut self, param: &E::FitnessParam) {
+        for (p, program) in self.phenotypes.iter_mut().zip(&self.programs) {
+            p.evaluate(program, param);
+        }
+        self.evaluations += self.phenotypes.len();
+        self.fitness.clear();
+        self.fitness
+            .extend(self.phenotypes.iter().map(|p| p.fitness()));
+        fitness::sanitize(&mut self.fitness);
+    }
+
+    /// Summarises the evaluated generation. The diversity is the fraction of
+    /// programs that differ from the elite.
+    fn record_statistics(&mut self) {
+        let mut statistics = Statistics::from_fitness(self.generation, &self.fitness);
+        if let Some(elite) = self.programs.get(statistics.elite_index) {
+            let differing = self.programs.iter().filter(|p| *p != elite).count();
+            statistics.diversity = differing as f64 / self.programs.len() as f64;
+        }
+        statistics.evaluations = self.evaluations;
+        self.observers.notify(&statistics);
+        self.history.push(statistics);
+        self.generation += 1;
+    }
+
+    fn copy_elite(&mut self) {
+        let mut order: Vec<usize> = (0..self.fitness.len()).collect();
+        order.sort_by(|&a, &b| fitness::compare(self.fitness[b], self.fitness[a]));
+
+        for (slot, &elite) in self.next_programs[..self.elitism].iter_mut().zip(&order) {
+            slot.clone_from(&self.programs[elite]);
+        }
+    }
+
+    /// Fills all slots after the elites with children, two for every pair of
+    /// selected parents.
+    fn create_offspring(&mut self) {
+        let children = self.phenotypes.len() - self.elitism;
+        self.parents.resize(children.div_ceil(2) * 2, 0);
+        let selection_fitness: Vec<f64> = self
+            .fitness
+            .iter()
+            .zip(&self.programs)
+            .map(|(f, program)| f - self.parsimony * program.len() as f64)
+            .collect();
+        self.selection
+            .select(&selection_fitness, &mut self.parents, &mut self.rng);
+
+        for (slots, pair) in self.next_programs[self.elitism..]
+            .chunks_mut(2)
+            .zip(self.parents.chunks_exact(2))
+        {
+            let (parent1, parent2) = (&self.programs[pair[0]], &self.programs[pair[1]]);
+            let (child1, child2) = if self.rng.random_bool(self.crossover_rate) {
+                subtree_crossover(parent1, parent2, &mut self.rng)
+            } else {
+                (parent1.clone(), parent2.clone())
+            };
+
+            for ((slot, mut child), parent) in slots.iter_mut().zip([child1, child2]).zip(pair) {
+                if self.rng.random_bool(self.subtree_mutation_rate) {
+                    // keep new subtrees at the size of the initial programs
+                    subtree_mutation(&mut child, &self.primitives, 4, &mut self.rng);
+                }
+                if self.point_mutation_rate > 0.0 {
+                    point_mutation(
+                        &mut child,
+                        &self.primitives,
+                        self.point_mutation_rate,
+                        &mut self.rng,
+                    );
+                }
+                if child.depth() > self.max_depth || child.len() > self.max_size {
+                    slot.clone_from(&self.programs[*parent]);
+                } else {
+                    *slot = child;
+                }
+            }
+        }
+    }
+
+    /// The phenotype with the highest fitness according to [`fitness::compare`].
+    pub fn fittest_phenotype(&self) -> Option<&E> {
+        fitness::argmax(self.phenotypes.iter().map(|p| p.fitness()))
+            .map(|index| &self.phenotypes[index])
+    }
+
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The statistics of every evaluated generation, oldest first.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
+    /// The programs of the current generation.
+    pub fn programs(&self) -> &[ProgramOf<E>] {
+        &self.programs
+    }
+
+    pub fn get_phenotypes(&self) -> &[E] {
+        &self.phenotypes
+    }
+    pub fn get_phenotypes_mut(&mut self) -> &mut [E] {
+        &mut self.phenotypes
+    }
+
+    /// The program of the given phenotype.
+    ///
+    /// The phenotype is found by its address, so it must be borrowed from
+    /// this population, e.g. from [`GpPopulation::get_phenotypes`].
+    ///
+    /// # Panics
+    /// Panics if the phenotype does not belong to this population.
+    pub fn get_program(&self, phenotype: &E) -> &ProgramOf<E> {
+        &self.programs[crate::slot_of(&self.phenotypes, phenotype)]
+    }
+
+    /// Iterates over each phenotype mutably, providing its program.
+    pub fn for_each_phenotype_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut E, &ProgramOf<E>),
+    {
+        for (p, program) in self.phenotypes.iter_mut().zip(&self.programs) {
+            func(p, program);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Arith {
+        Sub,
+        A,
+        B,
+    }
+
+    fn arithmetic() -> PrimitiveSet<Arith> {
+        PrimitiveSet::new()
+            .with_function(Arith::Sub, (), &[(), ()])
+            .with_terminal(Arith::A, ())
+            .with_terminal(Arith::B, ())
+    }
+
+    /// Builds a program from primitives in prefix order.
+    fn program(primitives: &PrimitiveSet<Arith>, prefix: &[Arith]) -> Program<Arith> {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let nodes = prefix
+            .iter()
+            .map(|&primitive| {
+                let signature = primitives
+                    .signatures
+                    .iter()
+                    .position(|s| matches!(s.kind, Kind::Fixed(p) if p == primitive))
+                    .unwrap();
+                primitives.node(signature, &mut rng)
+            })
+            .collect();
+        Program::from_nodes(nodes)
+    }
+
+    fn interpret(program: &Program<Arith>) -> f64 {
+        program.evaluate(|primitive, arguments: Vec<f64>| match primitive {
+            Arith::Sub => arguments[0] - arguments[1],
+            Arith::A => 5.0,
+            Arith::B => 3.0,
+        })
+    }
+
+    #[test]
+    fn evaluate_passes_arguments_in_order() {
+        let primitives = arithmetic();
+        use Arith::*;
+        assert_eq!(interpret(&program(&primitives, &[Sub, A, B])), 2.0);
+        assert_eq!(interpret(&program(&primitives, &[Sub, B, A])), -2.0);
+        // (a - b) - b and a - (a - b)
+        assert_eq!(interpret(&program(&primitives, &[Sub, Sub, A, B, B])), -1.0);
+        assert_eq!(interpret(&program(&primitives, &[Sub, A, Sub, A, B])), 3.0);
+    }
+
+    #[test]
+    fn depth_counts_the_edges_of_the_longest_branch() {
+        let primitives = arithmetic();
+        use Arith::*;
+        assert_eq!(program(&primitives, &[A]).depth(), 0);
+        assert_eq!(program(&primitives, &[Sub, A, B]).depth(), 1);
+        assert_eq!(program(&primitives, &[Sub, Sub, A, B, B]).depth(), 2);
+        assert_eq!(
+            program(&primitives, &[Sub, A, Sub, A, Sub, A, B]).depth(),
+            3
+        );
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Policy {
+        IfThenElse,
+        Not,
+        WallAhead,
+        Forward,
+        Left,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Type {
+        Bool,
+        Action,
+    }
+
+    fn policies() -> PrimitiveSet<Policy, Type> {
+        PrimitiveSet::new()
+            .with_function(
+                Policy::IfThenElse,
+                Type::Action,
+                &[Type::Bool, Type::Action, Type::Action],
+            )
+            .with_function(Policy::Not, Type::Bool, &[Type::Bool])
+            .with_terminal(Policy::WallAhead, Type::Bool)
+            .with_terminal(Policy::Forward, Type::Action)
+            .with_terminal(Policy::Left, Type::Action)
+    }
+
+    /// Whether every argument of every node has the type its signature asks for.
+    fn well_typed(
+        program: &Program<Policy, Type>,
+        primitives: &PrimitiveSet<Policy, Type>,
+    ) -> bool {
+        fn check(
+            subtree: Subtree<'_, Policy, Type>,
+            primitives: &PrimitiveSet<Policy, Type>,
+        ) -> bool {
+            let signature = &primitives.signatures[subtree.node().signature];
+            subtree.node().returns == signature.returns
+                && subtree.children().count() == signature.arguments.len()
+                && subtree
+                    .children()
+                    .zip(&signature.arguments)
+                    .all(|(child, &argument)| {
+                        child.node().returns == argument && check(child, primitives)
+                    })
+        }
+        program.root().node().returns == Type::Action && check(program.root(), primitives)
+    }
+
+    #[test]
+    fn subtree_crossover_preserves_types() {
+        let primitives = policies();
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let parents = ramped_half_and_half(&primitives, Type::Action, 1..=5, 100, &mut rng);
+        assert!(parents.iter().all(|p| well_typed(p, &primitives)));
+        for pair in parents.chunks(2) {
+            let (child1, child2) = subtree_crossover(&pair[0], &pair[1], &mut rng);
+            assert!(well_typed(&child1, &primitives), "{child1}");
+            assert!(well_typed(&child2, &primitives), "{child2}");
+            assert_eq!(child1.len() + child2.len(), pair[0].len() + pair[1].len());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "no finite program")]
+    fn types_without_a_terminal_are_rejected() {
+        // a Bool is only ever made from another Bool
+        let primitives = PrimitiveSet::new()
+            .with_function(
+                Policy::IfThenElse,
+                Type::Action,
+                &[Type::Bool, Type::Action, Type::Action],
+            )
+            .with_function(Policy::Not, Type::Bool, &[Type::Bool])
+            .with_terminal(Policy::Forward, Type::Action);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        full(&primitives, Type::Bool, 3, &mut rng);
+    }
+
+    #[test]
+    fn trees_end_as_soon_as_the_types_allow() {
+        // an Action needs an IfThenElse, so no tree is shallower than 1
+        let primitives = PrimitiveSet::new()
+            .with_function(
+                Policy::IfThenElse,
+                Type::Action,
+                &[Type::Bool, Type::Action, Type::Action],
+            )
+            .with_function(Policy::Not, Type::Bool, &[Type::Bool])
+            .with_terminal(Policy::WallAhead, Type::Bool)
+            .with_function(Policy::Forward, Type::Action, &[Type::Bool]);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..50 {
+            assert_eq!(full(&primitives, Type::Action, 0, &mut rng).depth(), 1);
+            assert!(grow(&primitives, Type::Action, 3, &mut rng).depth() <= 3);
+        }
+    }
+
+    /// Scores programs by how close they come to 1.
+    struct One {
+        fitness: f64,
+    }
+
+    impl ProgramEvaluator for One {
+        type Primitive = Arith;
+        type Type = ();
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            One { fitness: 0.0 }
+        }
+
+        fn evaluate(&mut self, program: &Program<Arith>, _: &()) {
+            self.fitness = -(interpret(program) - 1.0).abs();
+        }
+
+        fn fitness(&self) -> f64 {
+            self.fitness
+        }
+    }
+
+    #[test]
+    fn offspring_respect_the_bloat_limits() {
+        let mut population: GpPopulation<One> = GpPopulation::with_seed(50, arithmetic(), (), 5)
+            .with_initial_depth(1..=3)
+            .with_subtree_mutation_rate(0.5)
+            .with_max_depth(4)
+            .with_max_size(15);
+        for _ in 0..20 {
+            population.evolve(&());
+            assert!(
+                population
+                    .programs()
+                    .iter()
+                    .all(|p| p.depth() <= 4 && p.len() <= 15)
+            );
+        }
+    }
+
+    #[test]
+    fn get_program_finds_the_program_of_a_phenotype() {
+        let mut population: GpPopulation<One> = GpPopulation::with_seed(10, arithmetic(), (), 1);
+        population.evolve(&());
+        for (phenotype, program) in population
+            .get_phenotypes()
+            .iter()
+            .zip(population.programs())
+        {
+            assert_eq!(population.get_program(phenotype), program);
+        }
+    }
+}
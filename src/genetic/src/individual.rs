@@ -0,0 +1,142 @@
+//! Looking up individuals by their handle, and adding and removing
+//! individuals between generations, see [`Individual`].
+
+use rand::prelude::*;
+
+use crate::{Evaluator, Gene, GenotypeInitializer, Individual, Population};
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// The individuals of the current generation, in the order of
+    /// [`Population::get_phenotypes`].
+    pub fn individuals(&self) -> &[Individual] {
+        &self.individuals
+    }
+
+    /// The slot of an individual. Individuals are only ever appended or
+    /// removed, so the slots stay sorted by id.
+    fn slot(&self, individual: Individual) -> Option<usize> {
+        self.individuals.binary_search(&individual).ok()
+    }
+
+    /// The genotype of an individual, or `None` if it is not part of the
+    /// population.
+    pub fn genotype(&self, individual: Individual) -> Option<&[Gene<E>]> {
+        let start = self.slot(individual)? * self.genotype_size;
+        Some(&self.genotype_arena[start..start + self.genotype_size])
+    }
+
+    /// The evaluator of an individual.
+    pub fn phenotype(&self, individual: Individual) -> Option<&E> {
+        self.slot(individual).map(|slot| &self.phenotypes[slot])
+    }
+
+    pub fn phenotype_mut(&mut self, individual: Individual) -> Option<&mut E> {
+        self.slot(individual).map(|slot| &mut self.phenotypes[slot])
+    }
+
+    /// The fitness already known of an individual's current genotype: that of
+    /// an elite or other survivor of the last evaluated generation, NaN for a
+    /// child not evaluated yet or an individual added since.
+    pub fn fitness_of(&self, individual: Individual) -> Option<f64> {
+        self.slot(individual)
+            .map(|slot| self.inherited_fitness[slot])
+    }
+
+    /// Adds an individual with a copy of `genotype`, evaluated with the next
+    /// generation. Its evaluator is created by [`Evaluator::new`].
+    ///
+    /// # Panics
+    /// Panics if the genotype does not have the population's genotype size.
+    pub fn insert(&mut self, genotype: &[Gene<E>]) -> Individual {
+        assert_eq!(
+            genotype.len(),
+            self.genotype_size,
+            "the genotype must have the population's genotype size"
+        );
+        let individual = Individual(self.next_individual);
+        self.next_individual += 1;
+
+        let n = self.phenotypes.len();
+        // keep the evaluated generation aligned; the slot has no fitness yet
+        if self.next_gen_arena.len() == self.genotype_arena.len() {
+            self.next_gen_arena.extend_from_slice(genotype);
+        }
+        self.genotype_arena.extend_from_slice(genotype);
+        self.phenotypes.push(E::new(individual));
+        self.individuals.push(individual);
+        self.fitness.push(f64::NAN);
+        self.inherited_fitness.push(f64::NAN);
+        // an individual without parents, like an elite
+        if self.parent_fitness.len() == n {
+            self.parent_fitness.push(f64::NAN);
+        }
+        if self.niches.rivals.len() == n {
+            self.niches.rivals.push(None);
+        }
+        individual
+    }
+
+    /// Removes an individual and returns its genotype, or `None` if it is not
+    /// part of the population. The elitism is capped at the new size.
+    pub fn remove(&mut self, individual: Individual) -> Option<Vec<Gene<E>>> {
+        let slot = self.slot(individual)?;
+        let start = slot * self.genotype_size;
+        let genotype = self
+            .genotype_arena
+            .drain(start..start + self.genotype_size)
+            .collect();
+        // the evaluated generation shifts with `fitness`, see `Population::fittest`
+        if start + self.genotype_size <= self.next_gen_arena.len() {
+            self.next_gen_arena.drain(start..start + self.genotype_size);
+        }
+        self.phenotypes.remove(slot);
+        self.individuals.remove(slot);
+        self.fitness.remove(slot);
+        self.inherited_fitness.remove(slot);
+        if slot < self.parent_fitness.len() {
+            self.parent_fitness.remove(slot);
+        }
+        if slot < self.niches.rivals.len() {
+            self.niches.rivals.remove(slot);
+        }
+        self.elitism = self.elitism.min(self.phenotypes.len());
+        Some(genotype)
+    }
+
+    /// Sizes the arena of the next generation for the current one. Called
+    /// before breeding: until then `next_gen_arena` still holds the previous
+    /// generation, which deterministic crowding refers to by slot.
+    pub(crate) fn align_next_generation(&mut self) {
+        self.next_gen_arena
+            .resize(self.genotype_arena.len(), Gene::<E>::default());
+    }
+
+    /// Grows or shrinks the population to `size` individuals before the next
+    /// generation is evaluated.
+    ///
+    /// Shrinking removes the children not evaluated yet first, the newest
+    /// slots first, then the least fit survivors of the last evaluated
+    /// generation, so the elites are kept as long as possible. Growing adds
+    /// individuals with random genotypes.
+    pub fn resize(&mut self, size: usize) {
+        let n = self.phenotypes.len();
+        if size < n {
+            let inherited = &self.inherited_fitness;
+            let mut order: Vec<usize> = (0..n).rev().collect();
+            order.sort_by(|&a, &b| crate::fitness::compare(inherited[a], inherited[b]));
+            let culled: Vec<Individual> = order[..n - size]
+                .iter()
+                .map(|&slot| self.individuals[slot])
+                .collect();
+            for individual in culled {
+                self.remove(individual);
+            }
+        } else {
+            let mut genotype = vec![Gene::<E>::default(); self.genotype_size];
+            for _ in n..size {
+                Gene::<E>::initial_genotypes(&mut genotype, &mut self.rng);
+                self.insert(&genotype);
+            }
+        }
+    }
+}
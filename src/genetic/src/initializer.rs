@@ -0,0 +1,322 @@
+//! # Gene Initializers
+//!
+//! Built-in [`GenotypeInitializer`] implementations and helpers to write new
+//! ones.
+//!
+//! - `bool` genes are drawn with equal probability.
+//! - Integer genes are drawn uniformly from their whole range, except for
+//!   `u8`, which draws printable ASCII. Other alphabets are available as
+//!   [`Symbol`] genes.
+//! - `f32` and `f64` genes are drawn uniformly from `0.0..1.0`.
+//! - [`bounded_gene!`](crate::bounded_gene) defines a numeric gene with its
+//!   own bounds and distribution.
+//! - [`enum_gene!`](crate::enum_gene) implements the trait for an enum by
+//!   drawing one of its variants.
+
+use std::marker::PhantomData;
+
+use rand::distr::{Uniform, uniform::SampleUniform};
+// Re-exported for the macros, which expand in crates that may not name `rand`.
+#[doc(hidden)]
+pub use rand::Rng;
+
+use crate::GenotypeInitializer;
+
+impl GenotypeInitializer for bool {
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [bool], rng: &mut R) {
+        for gene in genotype.iter_mut() {
+            *gene = rng.random();
+        }
+    }
+}
+
+impl GenotypeInitializer for u8 {
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [u8], rng: &mut R) {
+        let char_range = Uniform::new_inclusive(32u8, 126u8).unwrap();
+        for gene in genotype.iter_mut() {
+            *gene = rng.sample(char_range);
+        }
+    }
+}
+
+macro_rules! impl_standard {
+    ($($ty:ty),*) => {
+        $(
+            impl GenotypeInitializer for $ty {
+                fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [$ty], rng: &mut R) {
+                    for gene in genotype.iter_mut() {
+                        *gene = rng.random();
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_standard!(u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+// `rand` has no standard distribution for the platform dependent integers.
+// Truncating a 64 bit value keeps it uniform on 32 bit platforms.
+impl GenotypeInitializer for usize {
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [usize], rng: &mut R) {
+        for gene in genotype.iter_mut() {
+            *gene = rng.random::<u64>() as usize;
+        }
+    }
+}
+
+impl GenotypeInitializer for isize {
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [isize], rng: &mut R) {
+        for gene in genotype.iter_mut() {
+            *gene = rng.random::<i64>() as isize;
+        }
+    }
+}
+
+/// Sets every gene to one of `options`, drawn with equal probability.
+///
+/// # Panics
+/// Panics if `options` is empty.
+pub fn choose<T: Copy, R: Rng + ?Sized>(genotype: &mut [T], options: &[T], rng: &mut R) {
+    assert!(!options.is_empty(), "there must be at least one option");
+    for gene in genotype.iter_mut() {
+        *gene = options[rng.random_range(0..options.len())];
+    }
+}
+
+/// Sets every gene to a value drawn uniformly from `low..=high`.
+pub fn uniform<T: SampleUniform + PartialOrd + Copy, R: Rng + ?Sized>(
+    genotype: &mut [T],
+    low: T,
+    high: T,
+    rng: &mut R,
+) {
+    for gene in genotype.iter_mut() {
+        *gene = rng.random_range(low..=high);
+    }
+}
+
+/// Samples the standard normal distribution, for [`bounded_gene!`](crate::bounded_gene).
+pub fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    crate::mutation::standard_normal(rng)
+}
+
+/// A set of bytes that [`Symbol`] genes are drawn from.
+pub trait Alphabet {
+    const SYMBOLS: &'static [u8];
+}
+
+/// Printable ASCII, from the space to `~`. The alphabet of plain `u8` genes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Printable;
+
+impl Alphabet for Printable {
+    const SYMBOLS: &'static [u8] = &{
+        let mut symbols = [0u8; 95];
+        let mut i = 0;
+        while i < symbols.len() {
+            symbols[i] = b' ' + i as u8;
+            i += 1;
+        }
+        symbols
+    };
+}
+
+/// The lowercase letters `a` to `z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Lowercase;
+
+impl Alphabet for Lowercase {
+    const SYMBOLS: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz";
+}
+
+/// The digits `0` and `1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Binary;
+
+impl Alphabet for Binary {
+    const SYMBOLS: &'static [u8] = b"01";
+}
+
+/// A byte gene drawn from the alphabet `A`.
+///
+/// Mutations built on [`GenotypeInitializer`], like
+/// [`mutation::random_reset`](crate::mutation::random_reset), stay within the
+/// alphabet as well.
+pub struct Symbol<A: Alphabet>(pub u8, PhantomData<A>);
+
+impl<A: Alphabet> Symbol<A> {
+    pub fn new(byte: u8) -> Self {
+        Self(byte, PhantomData)
+    }
+
+    /// The bytes of a genotype of symbols.
+    pub fn to_bytes(genotype: &[Self]) -> Vec<u8> {
+        genotype.iter().map(|symbol| symbol.0).collect()
+    }
+}
+
+impl<A: Alphabet> GenotypeInitializer for Symbol<A> {
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [Self], rng: &mut R) {
+        for gene in genotype.iter_mut() {
+            *gene = Self::new(A::SYMBOLS[rng.random_range(0..A::SYMBOLS.len())]);
+        }
+    }
+}
+
+// Implemented by hand, as deriving would require the alphabet to implement
+// the traits as well.
+impl<A: Alphabet> Clone for Symbol<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A: Alphabet> Copy for Symbol<A> {}
+
+impl<A: Alphabet> PartialEq for Symbol<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<A: Alphabet> Eq for Symbol<A> {}
+
+impl<A: Alphabet> std::hash::Hash for Symbol<A> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+/// The first symbol of the alphabet.
+impl<A: Alphabet> Default for Symbol<A> {
+    fn default() -> Self {
+        Self::new(A::SYMBOLS[0])
+    }
+}
+
+impl<A: Alphabet> std::fmt::Debug for Symbol<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Symbol({:?})", self.0 as char)
+    }
+}
+
+impl<A: Alphabet> From<Symbol<A>> for u8 {
+    fn from(symbol: Symbol<A>) -> u8 {
+        symbol.0
+    }
+}
+
+/// Defines a numeric gene newtype with bounds and an initial distribution.
+///
+/// The gene implements [`GenotypeInitializer`](crate::GenotypeInitializer)
+/// and [`RealGene`](crate::crossover::RealGene), whose `from_f64` clamps into
+/// the bounds, so the real-valued crossover and mutation operators respect
+/// them as well. The bounds are available as `MIN` and `MAX`, the default
+/// value is `MIN`.
+///
+/// ```
+/// genetic::bounded_gene! {
+///     /// Weight of a connection, drawn uniformly from -1 to 1.
+///     pub struct Weight(f64): uniform(-1.0, 1.0);
+/// }
+/// genetic::bounded_gene! {
+///     /// Drawn from the normal distribution around 0 with standard
+///     /// deviation 5, then clamped to -20..=20 and rounded.
+///     pub struct Offset(i32): normal(0.0, 5.0), clamp(-20, 20);
+/// }
+/// ```
+#[macro_export]
+macro_rules! bounded_gene {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($ty:ty): uniform($low:expr, $high:expr);
+    ) => {
+        $crate::bounded_gene!(@define $(#[$meta])* $vis $name($ty), $low, $high, |rng| {
+            $crate::initializer::Rng::random_range(rng, $low..=$high)
+        });
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($ty:ty): normal($mean:expr, $std_dev:expr), clamp($low:expr, $high:expr);
+    ) => {
+        $crate::bounded_gene!(@define $(#[$meta])* $vis $name($ty), $low, $high, |rng| {
+            <$ty as $crate::crossover::RealGene>::from_f64(
+                $mean + $std_dev * $crate::initializer::standard_normal(rng),
+            )
+        });
+    };
+    (
+        @define $(#[$meta:meta])* $vis:vis $name:ident($ty:ty), $low:expr, $high:expr,
+        |$rng:ident| $sample:expr
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+        $vis struct $name(pub $ty);
+
+        impl $name {
+            pub const MIN: $ty = $low;
+            pub const MAX: $ty = $high;
+
+            /// Wraps `value`, clamped into the bounds.
+            pub fn clamped(value: $ty) -> Self {
+                Self(value.clamp(Self::MIN, Self::MAX))
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self(Self::MIN)
+            }
+        }
+
+        impl $crate::GenotypeInitializer for $name {
+            fn initial_genotypes<R: $crate::initializer::Rng + ?Sized>(
+                genotype: &mut [Self],
+                $rng: &mut R,
+            ) {
+                for gene in genotype.iter_mut() {
+                    *gene = Self::clamped($sample);
+                }
+            }
+        }
+
+        impl $crate::crossover::RealGene for $name {
+            fn to_f64(self) -> f64 {
+                $crate::crossover::RealGene::to_f64(self.0)
+            }
+            fn from_f64(value: f64) -> Self {
+                Self::clamped(<$ty as $crate::crossover::RealGene>::from_f64(value))
+            }
+        }
+    };
+}
+
+/// Implements [`GenotypeInitializer`](crate::GenotypeInitializer) for a
+/// `Copy` enum by drawing one of the listed variants with equal probability.
+///
+/// ```
+/// #[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// enum Move {
+///     #[default]
+///     Forward,
+///     Left,
+///     Right,
+/// }
+///
+/// genetic::enum_gene!(Move { Forward, Left, Right });
+/// ```
+#[macro_export]
+macro_rules! enum_gene {
+    ($name:ident { $($variant:ident),+ $(,)? }) => {
+        impl $crate::GenotypeInitializer for $name {
+            fn initial_genotypes<R: $crate::initializer::Rng + ?Sized>(
+                genotype: &mut [Self],
+                rng: &mut R,
+            ) {
+                const VARIANTS: &[$name] = &[$($name::$variant),+];
+                $crate::initializer::choose(genotype, VARIANTS, rng);
+            }
+        }
+    };
+}
@@ -0,0 +1,477 @@
+//! # Island Model
+//!
+//! An [`Archipelago`] evolves several [`Population`]s side by side. Every
+//! island keeps its own selection, elitism and mutation schedule, so the
+//! islands can be tuned differently. Every few generations the islands send
+//! copies of their fittest individuals to their neighbours on the
+//! [`Topology`], where they replace the least fit individuals before the
+//! next generation is bred.
+//!
+//! Isolated sub-populations drift towards different optima, while migration
+//! keeps spreading good building blocks between them. This makes the whole
+//! run less likely to get stuck in a single local optimum.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    Evaluator, Gene, Population, fitness,
+    statistics::Statistics,
+    termination::{self, RunReport, Termination},
+};
+
+/// Which islands send migrants to which.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Island `i` sends to island `i + 1`, the last island to the first.
+    #[default]
+    Ring,
+    /// Every island sends to every other island.
+    FullyConnected,
+    /// Every island sends to one other island, drawn anew at every migration.
+    Random,
+}
+
+/// Several populations evolving side by side and exchanging individuals.
+///
+/// All islands must have the same genotype size, but may differ in size
+/// and configuration.
+pub struct Archipelago<E: Evaluator, R: Rng = ChaCha8Rng> {
+    islands: Vec<Population<E, R>>,
+    topology: Topology,
+    interval: usize,
+    migrants: usize,
+    rng: R,
+    generation: usize,
+    history: Vec<Statistics>,
+}
+
+impl<E: Evaluator, R: Rng> std::fmt::Debug for Archipelago<E, R>
+where
+    Population<E, R>: std::fmt::Debug,
+    R: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Archipelago")
+            .field("islands", &self.islands)
+            .field("topology", &self.topology)
+            .field("interval", &self.interval)
+            .field("migrants", &self.migrants)
+            .field("rng", &self.rng)
+            .field("generation", &self.generation)
+            .field("history", &self.history)
+            .finish()
+    }
+}
+
+impl<E: Evaluator, R: Rng + SeedableRng> Archipelago<E, R> {
+    /// Creates an archipelago whose migration is seeded from the thread-local
+    /// entropy source.
+    ///
+    /// # Panics
+    /// Panics if the islands have different genotype sizes.
+    pub fn new(islands: Vec<Population<E, R>>) -> Self {
+        Self::with_rng(islands, R::from_rng(&mut rand::rng()))
+    }
+
+    /// Creates an archipelago whose migration is determined by `seed`. For a
+    /// reproducible run the islands have to be seeded as well.
+    ///
+    /// # Panics
+    /// Panics if the islands have different genotype sizes.
+    pub fn with_seed(islands: Vec<Population<E, R>>, seed: u64) -> Self {
+        Self::with_rng(islands, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: Evaluator, R: Rng> Archipelago<E, R> {
+    /// Creates an archipelago whose migration is driven by the given random
+    /// number generator.
+    ///
+    /// # Panics
+    /// Panics if the islands have different genotype sizes.
+    pub fn with_rng(islands: Vec<Population<E, R>>, rng: R) -> Self {
+        assert!(
+            islands
+                .windows(2)
+                .all(|pair| pair[0].genotype_size == pair[1].genotype_size),
+            "all islands must have the same genotype size"
+        );
+
+        Self {
+            islands,
+            topology: Topology::default(),
+            interval: 10,
+            migrants: 1,
+            rng,
+            generation: 0,
+            history: Vec::new(),
+        }
+    }
+
+    /// Sets which islands exchange migrants. Defaults to [`Topology::Ring`].
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Sets the number of generations between two migrations. Defaults to 10,
+    /// 0 disables migration.
+    pub fn with_migration_interval(mut self, generations: usize) -> Self {
+        self.interval = generations;
+        self
+    }
+
+    /// Sets the number of individuals every island sends to each of its
+    /// destinations. Defaults to 1.
+    pub fn with_migrants(mut self, count: usize) -> Self {
+        self.migrants = count;
+        self
+    }
+
+    /// Evolves every island by one generation and migrates if it is due.
+    pub fn evolve(&mut self, param: &E::FitnessParam) {
+        for island in self.islands.iter_mut() {
+            island.evaluate_generation(param);
+        }
+        self.record_statistics();
+        self.migrate_if_due();
+        for island in self.islands.iter_mut() {
+            island.breed_generation();
+        }
+    }
+
+    /// Evolves the archipelago until `termination` is satisfied.
+    ///
+    /// The termination is checked against the combined statistics, see
+    /// [`Archipelago::history`].
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<Gene<E>> {
+        termination::drive(
+            self,
+            |archipelago| archipelago.evolve(param),
+            |archipelago| &archipelago.history,
+            |archipelago, index| {
+                let (island, index) = archipelago.locate(index);
+                archipelago.islands[island].evaluated_genotype(index)
+            },
+            termination,
+        )
+    }
+
+    /// Summarises the last evaluated generation of all islands together.
+    ///
+    /// `elite_index` counts through the islands one after another, the
+    /// diversity is the mean of the islands' diversities weighted by their
+    /// size.
+    fn record_statistics(&mut self) {
+        let fitness: Vec<f64> = self
+            .islands
+            .iter()
+            .flat_map(|island| island.fitness.iter().copied())
+            .collect();
+
+        let mut statistics = Statistics::from_fitness(self.generation, &fitness);
+        statistics.evaluations = self.islands.iter().map(|island| island.evaluations).sum();
+        if !fitness.is_empty() {
+            statistics.diversity = self
+                .islands
+                .iter()
+                .filter_map(|island| {
+                    let diversity = island.statistics()?.diversity;
+                    Some(diversity * island.phenotypes.len() as f64)
+                })
+                .sum::<f64>()
+                / fitness.len() as f64;
+        }
+        self.history.push(statistics);
+        self.generation += 1;
+    }
+
+    fn migrate_if_due(&mut self) {
+        if self.interval == 0
+            || self.migrants == 0
+            || self.islands.len() < 2
+            || !self.generation.is_multiple_of(self.interval)
+        {
+            return;
+        }
+
+        // Collect all emigrants first, so that no island passes on an
+        // individual it has only just received.
+        let emigrants: Vec<_> = self
+            .islands
+            .iter()
+            .map(|island| island.emigrants(self.migrants))
+            .collect();
+
+        let mut arrivals: Vec<Vec<(f64, Vec<Gene<E>>)>> =
+            (0..self.islands.len()).map(|_| Vec::new()).collect();
+        for (source, emigrants) in emigrants.iter().enumerate() {
+            for destination in self.destinations(source) {
+                arrivals[destination].extend(emigrants.iter().cloned());
+            }
+        }
+
+        for (island, arrivals) in self.islands.iter_mut().zip(arrivals) {
+            island.immigrate(arrivals);
+        }
+    }
+
+    fn destinations(&mut self, source: usize) -> Vec<usize> {
+        let count = self.islands.len();
+        match self.topology {
+            Topology::Ring => vec![(source + 1) % count],
+            Topology::FullyConnected => (0..count).filter(|&d| d != source).collect(),
+            Topology::Random => {
+                // draw from all islands but the source
+                let destination = self.rng.random_range(0..count - 1);
+                vec![destination + usize::from(destination >= source)]
+            }
+        }
+    }
+
+    /// Splits an index counting through all islands into the island and the
+    /// index within it.
+    fn locate(&self, mut index: usize) -> (usize, usize) {
+        for (island, population) in self.islands.iter().enumerate() {
+            if index < population.phenotypes.len() {
+                return (island, index);
+            }
+            index -= population.phenotypes.len();
+        }
+        panic!("index out of bounds of the archipelago");
+    }
+
+    /// The phenotype of the fittest individual of the generation evaluated
+    /// last on any island, ranked by the fitness the islands select by.
+    ///
+    /// Taken from the statistics, which are recorded before the migration:
+    /// the evaluators of immigrants are recreated, so only the evaluator of
+    /// the original describes the individual.
+    pub fn fittest_phenotype(&self) -> Option<&E> {
+        let (island, index) = self.locate(self.history.last()?.elite_index);
+        self.islands[island].phenotypes.get(index)
+    }
+
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The combined statistics of every evaluated generation, oldest first.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The combined statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
+    pub fn islands(&self) -> &[Population<E, R>] {
+        &self.islands
+    }
+
+    pub fn islands_mut(&mut self) -> &mut [Population<E, R>] {
+        &mut self.islands
+    }
+
+    /// Calls [`Population::for_each_phenotype_mut`] on every island.
+    pub fn for_each_phenotype_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut E, &[Gene<E>]),
+    {
+        for island in self.islands.iter_mut() {
+            island.for_each_phenotype_mut(&mut func);
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<E, R> Archipelago<E, R>
+where
+    E: Evaluator + Send,
+    Gene<E>: Send + Sync,
+    E::FitnessParam: Sync,
+    R: Rng + SeedableRng + Send,
+{
+    /// Same as [`Archipelago::evolve`], but every island evaluates and breeds
+    /// on the rayon thread pool, see [`Population::par_evolve`].
+    pub fn par_evolve(&mut self, param: &E::FitnessParam) {
+        for island in self.islands.iter_mut() {
+            island.par_evaluate_generation(param);
+        }
+        self.record_statistics();
+        self.migrate_if_due();
+        for island in self.islands.iter_mut() {
+            island.par_breed_generation();
+        }
+    }
+
+    /// Calls [`Population::par_for_each_phenotype_mut`] on every island.
+    pub fn par_for_each_phenotype_mut<F>(&mut self, func: F)
+    where
+        F: Fn(&mut E, &[Gene<E>]) + Sync + Send,
+    {
+        for island in self.islands.iter_mut() {
+            island.par_for_each_phenotype_mut(&func);
+        }
+    }
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// Copies of the `count` fittest individuals of the evaluated generation
+    /// together with their fitness.
+    fn emigrants(&self, count: usize) -> Vec<(f64, Vec<Gene<E>>)> {
+        let mut order: Vec<usize> = (0..self.fitness.len()).collect();
+        order.sort_by(|&a, &b| fitness::compare(self.fitness[b], self.fitness[a]));
+
+        order
+            .into_iter()
+            .take(count)
+            .map(|index| {
+                let start = index * self.genotype_size;
+                let genotype = self.genotype_arena[start..start + self.genotype_size].to_vec();
+                (self.fitness[index], genotype)
+            })
+            .collect()
+    }
+
+    /// Replaces the least fit individuals of the evaluated generation with
+    /// the arrivals, so that they take part in the next breeding. If there
+    /// are more arrivals than individuals, the fittest arrivals are kept.
+    ///
+    /// The evaluators of the replaced individuals are recreated with
+    /// [`Evaluator::new`], as their state describes the replaced genotypes.
+    fn immigrate(&mut self, mut arrivals: Vec<(f64, Vec<Gene<E>>)>) {
+        arrivals.sort_by(|a, b| fitness::compare(b.0, a.0));
+
+        let mut order: Vec<usize> = (0..self.fitness.len()).collect();
+        order.sort_by(|&a, &b| fitness::compare(self.fitness[a], self.fitness[b]));
+
+        let genotype_size = self.genotype_size;
+        for (&slot, (fitness, genotype)) in order.iter().zip(&arrivals) {
+            self.fitness[slot] = *fitness;
+            let start = slot * genotype_size;
+            self.genotype_arena[start..start + genotype_size].copy_from_slice(genotype);
+            self.phenotypes[slot] = E::new(self.individuals[slot]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Individual, benchmarks::Bits};
+
+    /// Counts the set bits and remembers the genotype it evaluated.
+    struct Recorder {
+        fitness: f64,
+        genotype: Option<Vec<bool>>,
+    }
+
+    impl Evaluator for Recorder {
+        type Genome = Bits;
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            Recorder {
+                fitness: 0.0,
+                genotype: None,
+            }
+        }
+
+        fn evaluate(&mut self, genotype: &[bool], _: &()) {
+            self.fitness = genotype.iter().filter(|&&bit| bit).count() as f64;
+            self.genotype = Some(genotype.to_vec());
+        }
+
+        fn fitness(&self) -> f64 {
+            self.fitness
+        }
+    }
+
+    fn archipelago(topology: Topology) -> Archipelago<Recorder> {
+        let islands = (0..4)
+            .map(|seed| Population::with_seed(10, 32, seed))
+            .collect();
+        Archipelago::with_seed(islands, 9)
+            .with_topology(topology)
+            .with_migration_interval(1)
+    }
+
+    /// Evaluates every island and migrates, without breeding.
+    fn evaluate_and_migrate(archipelago: &mut Archipelago<Recorder>) {
+        for island in archipelago.islands.iter_mut() {
+            island.evaluate_generation(&());
+        }
+        archipelago.record_statistics();
+        archipelago.migrate_if_due();
+    }
+
+    fn genotypes(island: &Population<Recorder>) -> Vec<&[bool]> {
+        island.genotype_arena.chunks(island.genotype_size).collect()
+    }
+
+    #[test]
+    fn ring_migration_passes_the_best_on_to_the_next_island() {
+        let mut archipelago = archipelago(Topology::Ring);
+        for island in archipelago.islands.iter_mut() {
+            island.evaluate_generation(&());
+        }
+        let best: Vec<_> = archipelago
+            .islands
+            .iter()
+            .map(|island| island.emigrants(1).remove(0))
+            .collect();
+        archipelago.record_statistics();
+        archipelago.migrate_if_due();
+
+        for (source, (fitness, genotype)) in best.iter().enumerate() {
+            let island = &archipelago.islands[(source + 1) % 4];
+            let slot = genotypes(island)
+                .iter()
+                .position(|g| *g == genotype.as_slice())
+                .expect("the emigrant arrived");
+            assert_eq!(island.fitness[slot], *fitness);
+        }
+    }
+
+    #[test]
+    fn replaced_individuals_get_new_evaluators() {
+        let mut archipelago = archipelago(Topology::FullyConnected).with_migrants(2);
+        evaluate_and_migrate(&mut archipelago);
+        for island in &archipelago.islands {
+            let fresh = island
+                .phenotypes
+                .iter()
+                .zip(genotypes(island))
+                .filter(|(p, genotype)| match &p.genotype {
+                    None => true,
+                    Some(evaluated) => {
+                        assert_eq!(evaluated, genotype, "a stale evaluator was kept");
+                        false
+                    }
+                })
+                .count();
+            // two migrants from each of the three other islands
+            assert_eq!(fresh, 6);
+        }
+    }
+
+    #[test]
+    fn random_migration_never_sends_to_the_source() {
+        let mut archipelago = archipelago(Topology::Random);
+        for _ in 0..100 {
+            for source in 0..4 {
+                let destinations = archipelago.destinations(source);
+                assert_eq!(destinations.len(), 1);
+                assert!(destinations[0] < 4 && destinations[0] != source);
+            }
+        }
+    }
+}
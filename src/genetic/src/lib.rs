@@ -5,75 +5,407 @@
 //! structure to manage the evolutionary process.
 //!
 
-use rand::{distr::Uniform, prelude::*};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+pub mod benchmarks;
+mod cache;
+#[cfg(feature = "serde")]
+mod checkpoint;
+pub mod cma_es;
+pub mod constraint;
+pub mod crossover;
+pub mod differential_evolution;
+pub mod fitness;
+pub mod gp;
+mod individual;
+pub mod initializer;
+pub mod island;
+pub mod multi_objective;
+pub mod mutation;
+pub mod neat;
+pub mod niching;
+pub mod novelty;
+#[cfg(feature = "parallel")]
+mod parallel;
+pub mod replacement;
+pub mod selection;
+pub mod statistics;
+pub mod termination;
+pub mod tuning;
+pub mod variable;
+
+use cache::{FitnessCache, Lookup};
+use constraint::ConstraintState;
+use mutation::{Feedback, MutationSchedule};
+use niching::{Niches, Niching};
+use novelty::{HallOfFame, NoveltyState};
+use replacement::Replacement;
+use selection::{Selection, Tournament};
+use statistics::{Observer, Observers, Statistics};
+use termination::{RunReport, Termination};
+
+/// The genetic operators of a genotype representation.
+///
+/// Implemented on its own type, a set of operators can be shared by every
+/// [`Evaluator`] of that representation.
+pub trait Genome {
+    /// The type of a single gene (e.g., `u8`, `f64`).
+    type Gene: Copy + Default + PartialEq + std::fmt::Debug + GenotypeInitializer;
+
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [Self::Gene], rng: &mut R);
+
+    /// Mutates with the rate chosen by the population's [`MutationSchedule`].
+    ///
+    /// Only called if the population has a schedule. The default ignores the
+    /// rate and calls [`Genome::mutate`].
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [Self::Gene], rate: f64, rng: &mut R) {
+        let _ = rate;
+        Self::mutate(genotype, rng);
+    }
+
+    fn crossover<R: Rng + ?Sized>(
+        parent1: &[Self::Gene],
+        parent2: &[Self::Gene],
+        child1: &mut [Self::Gene],
+        child2: &mut [Self::Gene],
+        size: usize,
+        rng: &mut R,
+    );
+
+    /// Distance between two genotypes, used by the [`Niching`] strategies.
+    ///
+    /// Defaults to the fraction of genes in which they differ.
+    fn distance(a: &[Self::Gene], b: &[Self::Gene]) -> f64 {
+        if a.is_empty() {
+            return 0.0;
+        }
+        a.iter().zip(b).filter(|(x, y)| x != y).count() as f64 / a.len() as f64
+    }
+}
+
+/// The gene type of an [`Evaluator`].
+pub type Gene<E> = <<E as Evaluator>::Genome as Genome>::Gene;
+
+/// Evaluates the genotype of one individual of a population.
+///
+/// The population creates one evaluator per individual and keeps it for as
+/// long as the individual is part of the population. The evaluator may hold
+/// any state needed to compute the fitness, e.g. the agent of a simulation.
+pub trait Evaluator {
+    /// The operators of the genotypes.
+    type Genome: Genome;
+    /// The type of the parameter needed for fitness calculation.
+    type FitnessParam;
+
+    fn new(individual: Individual) -> Self;
+
+    /// Calculates the fitness of `genotype`, to be returned by
+    /// [`Evaluator::fitness`].
+    fn evaluate(&mut self, genotype: &[Gene<Self>], param: &Self::FitnessParam);
+
+    fn fitness(&self) -> f64;
+}
 
+/// A handle to an individual of a [`Population`]: a place in the population
+/// with its own [`Evaluator`], genotype and fitness.
+///
+/// Breeding replaces the genotypes of the individuals, but the individuals
+/// and their evaluators stay. A handle stays valid until its individual is
+/// removed, no matter how many others are added or removed, see
+/// [`Population::genotype`] and [`Population::resize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Individual(usize);
+
+impl Individual {
+    /// A number unique among the individuals a population has ever had, in
+    /// the order they were added.
+    pub fn id(self) -> usize {
+        self.0
+    }
+}
+
+/// The original single-trait interface, combining the operators of a
+/// [`Genome`] with an [`Evaluator`].
+///
+/// Every `Phenotype` is an [`Evaluator`] whose operators are adapted by
+/// [`PhenotypeGenome`], so it works with every population unchanged.
+/// `new` is passed the id of the individual, which is its initial slot.
+/// Once individuals are removed, `index` no longer matches the slot, so
+/// look genotypes up with [`Population::get_genotype`] instead of
+/// computing slices from it.
 pub trait Phenotype {
     /// The type of a single gene (e.g., `u8`, `f64`).
-    type Gene: Copy + Default + GenotypeInitializer;
+    type Gene: Copy + Default + PartialEq + std::fmt::Debug + GenotypeInitializer;
     /// The type of the parameter needed for fitness calculation.
     type FitnessParam;
 
     fn new(index: usize) -> Self;
 
     fn fitness(&mut self, genotype: &[Self::Gene], param: &Self::FitnessParam);
-    fn mutate(genotype: &mut [Self::Gene], rng: &mut ThreadRng);
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [Self::Gene], rng: &mut R);
 
-    fn crossover(
+    /// See [`Genome::mutate_with_rate`].
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [Self::Gene], rate: f64, rng: &mut R) {
+        let _ = rate;
+        Self::mutate(genotype, rng);
+    }
+
+    fn crossover<R: Rng + ?Sized>(
         parent1: &[Self::Gene],
         parent2: &[Self::Gene],
         child1: &mut [Self::Gene],
         child2: &mut [Self::Gene],
         size: usize,
-        rng: &mut ThreadRng,
+        rng: &mut R,
     );
 
     fn get_fitness(&self) -> f64;
 
+    /// See [`Genome::distance`].
+    fn distance(a: &[Self::Gene], b: &[Self::Gene]) -> f64 {
+        if a.is_empty() {
+            return 0.0;
+        }
+        a.iter().zip(b).filter(|(x, y)| x != y).count() as f64 / a.len() as f64
+    }
+
     /// Returns the phenotype's index within the population.
     fn index(&self) -> usize;
     fn reset(&mut self);
 }
 
+/// Adapts the operators of a [`Phenotype`] to a [`Genome`].
+pub struct PhenotypeGenome<P>(std::marker::PhantomData<P>);
+
+impl<P: Phenotype> Genome for PhenotypeGenome<P> {
+    type Gene = P::Gene;
+
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [P::Gene], rng: &mut R) {
+        P::mutate(genotype, rng);
+    }
+
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [P::Gene], rate: f64, rng: &mut R) {
+        P::mutate_with_rate(genotype, rate, rng);
+    }
+
+    fn crossover<R: Rng + ?Sized>(
+        parent1: &[P::Gene],
+        parent2: &[P::Gene],
+        child1: &mut [P::Gene],
+        child2: &mut [P::Gene],
+        size: usize,
+        rng: &mut R,
+    ) {
+        P::crossover(parent1, parent2, child1, child2, size, rng);
+    }
+
+    fn distance(a: &[P::Gene], b: &[P::Gene]) -> f64 {
+        P::distance(a, b)
+    }
+}
+
+impl<P: Phenotype> Evaluator for P {
+    type Genome = PhenotypeGenome<P>;
+    type FitnessParam = P::FitnessParam;
+
+    fn new(individual: Individual) -> Self {
+        <P as Phenotype>::new(individual.id())
+    }
+
+    fn evaluate(&mut self, genotype: &[P::Gene], param: &P::FitnessParam) {
+        Phenotype::fitness(self, genotype, param);
+    }
+
+    fn fitness(&self) -> f64 {
+        self.get_fitness()
+    }
+}
+
+/// The slot of `phenotype` in `phenotypes`, found by its address.
+///
+/// # Panics
+/// Panics if `phenotype` is not an element of `phenotypes`.
+pub(crate) fn slot_of<E>(phenotypes: &[E], phenotype: &E) -> usize {
+    phenotypes
+        .iter()
+        .position(|p| std::ptr::eq(p, phenotype))
+        .expect("the phenotype must belong to this population")
+}
+
+/// A population of phenotypes and their genotypes.
+///
+/// `R` is the random number generator driving initialisation, selection,
+/// crossover and mutation. The default `ChaCha8Rng` produces the same stream
+/// on every platform, so a population created with [`Population::with_seed`]
+/// replays the exact same run.
 #[derive(Debug)]
-pub struct Population<E: Phenotype> {
-    genotype_arena: Vec<E::Gene>,
-    next_gen_arena: Vec<E::Gene>,
+pub struct Population<E: Evaluator, R: Rng = ChaCha8Rng> {
+    genotype_arena: Vec<Gene<E>>,
+    next_gen_arena: Vec<Gene<E>>,
     phenotypes: Vec<E>,
+    /// The individual in every slot, in ascending order.
+    individuals: Vec<Individual>,
+    /// The id of the next individual to be added.
+    next_individual: usize,
     // phenotype_size: usize,
     genotype_size: usize,
     // weights: Vec<f64>,
-    rng: ThreadRng,
+    rng: R,
+    selection: Box<dyn Selection>,
+    fitness: Vec<f64>,
+    parents: Vec<usize>,
+    elitism: usize,
+    mutation_schedule: Option<Box<dyn MutationSchedule>>,
+    crossover: Option<crossover::Operator<Gene<E>>>,
+    /// Fitness of the better parent of every child in the current generation,
+    /// NaN for elites.
+    parent_fitness: Vec<f64>,
+    /// The fitness already known of every slot of the current generation:
+    /// that of a survivor of the last evaluated generation, NaN for children
+    /// and added individuals.
+    inherited_fitness: Vec<f64>,
+    niching: Option<Niching>,
+    niches: Niches<Gene<E>>,
+    replacement: Replacement,
+    fitness_cache: Option<FitnessCache<Gene<E>>>,
+    novelty: Option<NoveltyState<E>>,
+    hall_of_fame: Option<HallOfFame<Gene<E>>>,
+    constraints: Option<ConstraintState<E>>,
+    /// Calls to `Evaluator::evaluate` so far.
+    evaluations: usize,
+    generation: usize,
+    history: Vec<Statistics>,
+    observers: Observers,
 
     _phantom: std::marker::PhantomData<E>,
 }
 
-impl<E: Phenotype> Population<E> {
+impl<E: Evaluator, R: Rng + SeedableRng> Population<E, R> {
+    /// Creates a population seeded from the thread-local entropy source.
     pub fn new(phenotype_size: usize, genotype_size: usize) -> Self {
-        let mut rng = rand::rng();
+        Self::with_rng(phenotype_size, genotype_size, R::from_rng(&mut rand::rng()))
+    }
+
+    /// Creates a population whose whole run is determined by `seed`.
+    pub fn with_seed(phenotype_size: usize, genotype_size: usize, seed: u64) -> Self {
+        Self::with_rng(phenotype_size, genotype_size, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// Creates a population driven by the given random number generator.
+    pub fn with_rng(phenotype_size: usize, genotype_size: usize, mut rng: R) -> Self {
         let arena_size = phenotype_size * genotype_size;
 
-        let mut genotype_arena = vec![E::Gene::default(); arena_size];
+        let mut genotype_arena = vec![Gene::<E>::default(); arena_size];
 
         for genotype_slice in genotype_arena.chunks_mut(genotype_size) {
-            E::Gene::initial_genotypes(genotype_slice, &mut rng);
+            Gene::<E>::initial_genotypes(genotype_slice, &mut rng);
         }
 
+        Self::from_arena(genotype_arena, phenotype_size, genotype_size, rng)
+    }
+
+    /// Creates a population around existing genotypes.
+    ///
+    /// `genotype_arena` must hold `phenotype_size * genotype_size` genes.
+    fn from_arena(
+        genotype_arena: Vec<Gene<E>>,
+        phenotype_size: usize,
+        genotype_size: usize,
+        rng: R,
+    ) -> Self {
+        debug_assert_eq!(genotype_arena.len(), phenotype_size * genotype_size);
+        let arena_size = genotype_arena.len();
+
         Self {
             genotype_arena,
-            next_gen_arena: vec![E::Gene::default(); arena_size],
-            phenotypes: (0..phenotype_size).map(|i| E::new(i)).collect(),
+            next_gen_arena: vec![Gene::<E>::default(); arena_size],
+            phenotypes: (0..phenotype_size).map(|i| E::new(Individual(i))).collect(),
+            individuals: (0..phenotype_size).map(Individual).collect(),
+            next_individual: phenotype_size,
             // phenotype_size,
             genotype_size,
             // weights: vec![0.0; phenotype_size],
             rng,
+            selection: Box::new(Tournament::default()),
+            fitness: vec![0.0; phenotype_size],
+            parents: Vec::new(),
+            elitism: 1.min(phenotype_size),
+            mutation_schedule: None,
+            crossover: None,
+            parent_fitness: Vec::new(),
+            inherited_fitness: vec![f64::NAN; phenotype_size],
+            niching: None,
+            niches: Niches::default(),
+            replacement: Replacement::default(),
+            fitness_cache: None,
+            novelty: None,
+            hall_of_fame: None,
+            constraints: None,
+            evaluations: 0,
+            generation: 0,
+            history: Vec::new(),
+            observers: Observers::default(),
             _phantom: std::marker::PhantomData,
         }
     }
 
+    /// Replaces the parent selection strategy. Defaults to a tournament of size 3.
+    pub fn with_selection(mut self, selection: impl Selection + 'static) -> Self {
+        self.selection = Box::new(selection);
+        self
+    }
+
+    /// Sets the number of the fittest individuals that are copied unchanged
+    /// into the next generation. Defaults to 1, capped at the population size.
+    pub fn with_elitism(mut self, count: usize) -> Self {
+        self.elitism = count.min(self.phenotypes.len());
+        self
+    }
+
+    /// Adapts the mutation rate during the run. The rate is passed to
+    /// [`Genome::mutate_with_rate`] instead of calling [`Genome::mutate`].
+    pub fn with_mutation_schedule(mut self, schedule: impl MutationSchedule + 'static) -> Self {
+        self.mutation_schedule = Some(Box::new(schedule));
+        self
+    }
+
+    /// Replaces [`Genome::crossover`] by `operator`, e.g. to compare
+    /// operators without defining a genome for each of them.
+    pub fn with_crossover(mut self, operator: crossover::Operator<Gene<E>>) -> Self {
+        self.crossover = Some(operator);
+        self
+    }
+
+    /// The current rate of the mutation schedule, if there is one.
+    pub fn mutation_rate(&self) -> Option<f64> {
+        self.mutation_schedule.as_ref().map(|s| s.rate())
+    }
+
+    /// Registers an observer that is notified after every evaluated generation.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
     pub fn evolve(&mut self, param: &E::FitnessParam) {
+        self.evaluate_generation(param);
+        self.breed_generation();
+    }
+
+    /// First half of `evolve`, split off so that an `Archipelago` can migrate
+    /// individuals between evaluation and breeding.
+    fn evaluate_generation(&mut self, param: &E::FitnessParam) {
         // 1. Calculate fitness for the current generation.
         self.calculate_fitness(param);
+        self.record_statistics();
+        self.update_mutation_schedule();
+    }
 
+    /// Second half of `evolve`.
+    fn breed_generation(&mut self) {
+        self.align_next_generation();
         // 2. Select parents and create the next generation via crossover and mutation.
         self.create_next_generation();
 
@@ -81,117 +413,302 @@ impl<E: Phenotype> Population<E> {
         std::mem::swap(&mut self.genotype_arena, &mut self.next_gen_arena);
     }
 
+    /// Evolves the population until `termination` is satisfied.
+    ///
+    /// Suited for phenotypes whose fitness only depends on the genotype. If
+    /// the phenotypes need to be simulated before their fitness can be
+    /// calculated, drive `evolve` yourself and check the termination against
+    /// [`Population::history`].
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<Gene<E>> {
+        termination::drive(
+            self,
+            |population| population.evolve(param),
+            |population| &population.history,
+            |population, index| population.evaluated_genotype(index),
+            termination,
+        )
+    }
+
+    /// Genotype at `index` of the generation evaluated by the last call to `evolve`.
+    fn evaluated_genotype(&self, index: usize) -> &[Gene<E>] {
+        // After `evolve` the arenas have been swapped, so the evaluated
+        // generation lives in `next_gen_arena` until it is overwritten.
+        let start = index * self.genotype_size;
+        &self.next_gen_arena[start..start + self.genotype_size]
+    }
+
     fn calculate_fitness(&mut self, param: &E::FitnessParam) {
-        for p in self.phenotypes.iter_mut() {
-            let start = p.index() * self.genotype_size;
-            let end = start + self.genotype_size;
-            let genotype = &self.genotype_arena[start..end];
-            p.fitness(genotype, param);
+        self.repair_genotypes(param);
+        self.lookup_cached_fitness();
+        let known = cache::known(&self.fitness_cache);
+        for (slot, (p, genotype)) in self
+            .phenotypes
+            .iter_mut()
+            .zip(self.genotype_arena.chunks(self.genotype_size))
+            .enumerate()
+        {
+            if Lookup::needs_evaluation(known, slot) {
+                p.evaluate(genotype, param);
+            }
         }
+        self.collect_fitness();
     }
 
-    fn select_parent_by_tournament(
-        phenotypes: &[E],
-        rng: &mut ThreadRng,
-        tournament_size: usize,
-    ) -> usize {
-        let population_size = phenotypes.len();
-        if population_size == 0 {
-            // Handle empty population case to avoid panic on range
-            return 0;
-        }
+    fn collect_fitness(&mut self) {
+        self.fitness.clear();
+        self.fitness
+            .extend(self.phenotypes.iter().map(|p| p.fitness()));
+        self.collect_violations();
+        self.merge_cached_fitness();
+        self.apply_constraints();
+        fitness::sanitize(&mut self.fitness);
+        self.replace_by_rivals();
+        self.update_archives();
+    }
 
-        // Select the first contender as the initial best
-        let mut best_idx = rng.random_range(0..population_size);
-        let mut best_fitness = phenotypes[best_idx].get_fitness();
+    /// Summarises the evaluated generation, appends it to the history and
+    /// notifies the observers.
+    fn record_statistics(&mut self) {
+        let mut statistics = Statistics::from_fitness(self.generation, &self.fitness);
+        statistics.diversity = statistics::diversity(
+            &self.genotype_arena,
+            self.genotype_size,
+            statistics.elite_index,
+        );
+        statistics.evaluations = self.evaluations;
+        self.observers.notify(&statistics);
+        self.history.push(statistics);
+        self.generation += 1;
+    }
 
-        // Run the rest of the tournament (starting from the second contender)
-        for _ in 1..tournament_size {
-            let contender_idx = rng.random_range(0..population_size);
-            let contender_fitness = phenotypes[contender_idx].get_fitness();
+    /// Feeds the evaluated generation back into the mutation schedule.
+    fn update_mutation_schedule(&mut self) {
+        let Some(schedule) = self.mutation_schedule.as_mut() else {
+            return;
+        };
 
-            if contender_fitness > best_fitness {
-                best_fitness = contender_fitness;
-                best_idx = contender_idx;
-            }
-        }
-        best_idx
+        let (children, successes) = self
+            .parent_fitness
+            .iter()
+            .zip(&self.fitness)
+            .filter(|(parent, _)| !parent.is_nan())
+            .fold((0, 0), |(children, successes), (parent, child)| {
+                (children + 1, successes + usize::from(child > parent))
+            });
+
+        schedule.update(&Feedback {
+            statistics: &self.history[self.history.len() - 1],
+            success_ratio: (children > 0).then(|| successes as f64 / children as f64),
+        });
     }
+
     fn create_next_generation(&mut self) {
-        // Elitism part is unchanged and correct
-        let fittest_idx = self
-            .phenotypes
-            .iter()
-            .enumerate()
-            .max_by(|(_, a), (_, b)| a.get_fitness().partial_cmp(&b.get_fitness()).unwrap())
-            .map(|(index, _)| index)
-            .unwrap_or(0);
+        if let Replacement::SteadyState { count, replace } = self.replacement {
+            self.replace_steady_state(count, replace);
+            return;
+        }
+
+        self.copy_elite();
+        self.select_parents();
+
+        let genotype_size = self.genotype_size;
+        let survivors = self.survivors();
+        let genotype_arena = &self.genotype_arena;
+        let rate = self.mutation_rate();
+        let crossover = self.crossover;
+        for (children, pair) in self.next_gen_arena[survivors * genotype_size..]
+            .chunks_mut(2 * genotype_size)
+            .zip(self.parents.chunks_exact(2))
+        {
+            Self::breed(
+                genotype_arena,
+                genotype_size,
+                pair,
+                children,
+                rate,
+                crossover,
+                &mut self.rng,
+            );
+        }
+        self.assign_rivals();
+    }
 
-        let elite_start = fittest_idx * self.genotype_size;
-        let elite_genotype = &self.genotype_arena[elite_start..elite_start + self.genotype_size];
-        self.next_gen_arena[0..self.genotype_size].copy_from_slice(elite_genotype);
+    /// Copies the fittest genotypes of the current generation that survive
+    /// (see [`Population::with_elitism`] and [`Replacement`]) into the first
+    /// slots of the next one, the fittest first.
+    fn copy_elite(&mut self) {
+        let survivors = self.survivors();
+        self.inherited_fitness.clear();
+        self.inherited_fitness
+            .resize(self.phenotypes.len(), f64::NAN);
+        if survivors == 0 {
+            return;
+        }
 
-        // This creates the first mutable borrow. It's fine.
-        let mut child_chunks =
-            self.next_gen_arena[self.genotype_size..].chunks_mut(self.genotype_size);
+        let mut order: Vec<usize> = (0..self.fitness.len()).collect();
+        order.sort_by(|&a, &b| fitness::compare(self.fitness[b], self.fitness[a]));
+        for (inherited, &elite_idx) in self.inherited_fitness[..survivors].iter_mut().zip(&order) {
+            *inherited = self.fitness[elite_idx];
+        }
 
-        while let (Some(child1_geno), Some(child2_geno)) =
-            (child_chunks.next(), child_chunks.next())
+        let genotype_size = self.genotype_size;
+        for (slot, &elite_idx) in self.next_gen_arena[..survivors * genotype_size]
+            .chunks_mut(genotype_size)
+            .zip(&order)
         {
-            const TOURNAMENT_SIZE: usize = 3;
+            let elite_start = elite_idx * genotype_size;
+            slot.copy_from_slice(&self.genotype_arena[elite_start..elite_start + genotype_size]);
+        }
+    }
+
+    /// Selects all parents for this generation up front, two for every pair of
+    /// children. An odd number of children is rounded up to a full pair.
+    fn select_parents(&mut self) {
+        let survivors = self.survivors();
+        let children = self.phenotypes.len() - survivors;
+        self.select_pairs(children);
+
+        self.parent_fitness.clear();
+        self.parent_fitness.resize(survivors, f64::NAN);
+        for child in 0..children {
+            let pair = &self.parents[child / 2 * 2..child / 2 * 2 + 2];
+            let better = self.fitness[pair[0]].max(self.fitness[pair[1]]);
+            self.parent_fitness.push(better);
+        }
+    }
 
-            // We pass immutable &self.phenotypes and mutable &mut self.rng.
-            // This is allowed because they are different fields from self.next_gen_arena.
-            let parent1_idx =
-                Self::select_parent_by_tournament(&self.phenotypes, &mut self.rng, TOURNAMENT_SIZE);
-            let parent2_idx =
-                Self::select_parent_by_tournament(&self.phenotypes, &mut self.rng, TOURNAMENT_SIZE);
+    /// Fills `self.parents` with enough pairs for `children` children.
+    fn select_pairs(&mut self, children: usize) {
+        self.parents.resize(children.div_ceil(2) * 2, 0);
+        if !self.select_novel_parents() && !self.select_niche_parents() {
+            self.selection
+                .select(&self.fitness, &mut self.parents, &mut self.rng);
+        }
+    }
 
-            // This part remains the same
-            let parent1_start = parent1_idx * self.genotype_size;
-            let parent2_start = parent2_idx * self.genotype_size;
+    /// Creates two children from the parents in `pair` via crossover and mutation.
+    ///
+    /// `children` holds both child genotypes back to back. If it only has room
+    /// for a single genotype, the second child is discarded.
+    fn breed<G: Rng>(
+        genotype_arena: &[Gene<E>],
+        genotype_size: usize,
+        pair: &[usize],
+        children: &mut [Gene<E>],
+        rate: Option<f64>,
+        crossover: Option<crossover::Operator<Gene<E>>>,
+        rng: &mut G,
+    ) {
+        let parent1_start = pair[0] * genotype_size;
+        let parent2_start = pair[1] * genotype_size;
 
-            let parent1_geno =
-                &self.genotype_arena[parent1_start..parent1_start + self.genotype_size];
-            let parent2_geno =
-                &self.genotype_arena[parent2_start..parent2_start + self.genotype_size];
+        let parent1_geno = &genotype_arena[parent1_start..parent1_start + genotype_size];
+        let parent2_geno = &genotype_arena[parent2_start..parent2_start + genotype_size];
 
-            E::crossover(
+        if children.len() < 2 * genotype_size {
+            let mut discarded = vec![Gene::<E>::default(); genotype_size];
+            Self::cross(
+                crossover,
                 parent1_geno,
                 parent2_geno,
-                child1_geno,
-                child2_geno,
-                self.genotype_size,
-                &mut self.rng,
+                children,
+                &mut discarded,
+                genotype_size,
+                rng,
             );
+            Self::mutate(children, rate, rng);
+            return;
+        }
+
+        let (child1_geno, child2_geno) = children.split_at_mut(genotype_size);
+
+        Self::cross(
+            crossover,
+            parent1_geno,
+            parent2_geno,
+            child1_geno,
+            child2_geno,
+            genotype_size,
+            rng,
+        );
 
-            E::mutate(child1_geno, &mut self.rng);
-            E::mutate(child2_geno, &mut self.rng);
+        Self::mutate(child1_geno, rate, rng);
+        Self::mutate(child2_geno, rate, rng);
+    }
+
+    /// Crosses over with the operator set by [`Population::with_crossover`],
+    /// or [`Genome::crossover`] without one.
+    fn cross<G: Rng>(
+        crossover: Option<crossover::Operator<Gene<E>>>,
+        parent1: &[Gene<E>],
+        parent2: &[Gene<E>],
+        child1: &mut [Gene<E>],
+        child2: &mut [Gene<E>],
+        size: usize,
+        rng: &mut G,
+    ) {
+        match crossover {
+            Some(crossover) => crossover(parent1, parent2, child1, child2, size, rng),
+            None => E::Genome::crossover(parent1, parent2, child1, child2, size, rng),
         }
     }
 
-    pub fn fittest(&self) -> (f64, Vec<E::Gene>) {
-        let fittest_phenotype = self
-            .phenotypes
-            .iter()
-            .max_by(|a, b| a.get_fitness().partial_cmp(&b.get_fitness()).unwrap())
-            .unwrap(); // Should not panic in a non-empty population
+    fn mutate<G: Rng + ?Sized>(genotype: &mut [Gene<E>], rate: Option<f64>, rng: &mut G) {
+        match rate {
+            Some(rate) => E::Genome::mutate_with_rate(genotype, rate, rng),
+            None => E::Genome::mutate(genotype, rng),
+        }
+    }
 
-        let start = fittest_phenotype.index() * self.genotype_size;
-        let end = start + self.genotype_size;
+    /// Returns the fitness of the fittest individual of the generation
+    /// evaluated by the last call to `evolve` together with a copy of its
+    /// genotype.
+    ///
+    /// # Panics
+    /// Panics if the population is empty.
+    pub fn fittest(&self) -> (f64, Vec<Gene<E>>) {
+        let fittest_idx = fitness::argmax(self.fitness.iter().copied())
+            .expect("the population must not be empty");
 
         (
-            fittest_phenotype.get_fitness(),
-            self.genotype_arena[start..end].to_vec(),
+            self.fitness[fittest_idx],
+            self.evaluated_genotype(fittest_idx).to_vec(),
         )
     }
+
+    /// The phenotype of the fittest individual of the generation evaluated
+    /// last, ranked by the fitness the population selects by, e.g. after
+    /// constraint handling.
+    pub fn fittest_phenotype(&self) -> Option<&E> {
+        fitness::argmax(self.fitness.iter().copied()).map(|index| &self.phenotypes[index])
+    }
+
     pub fn max_fitness(&self) -> f64 {
-        self.phenotypes
+        self.fitness
             .iter()
-            .map(|p| p.get_fitness())
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .copied()
+            .max_by(|a, b| fitness::compare(*a, *b))
             .unwrap_or(0.0)
     }
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The statistics of every evaluated generation, oldest first.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
     pub fn get_phenotypes(&self) -> &[E] {
         &self.phenotypes
     }
@@ -201,15 +718,14 @@ impl<E: Phenotype> Population<E> {
 
     /// Retrieves an immutable slice of the genotype for a given phenotype.
     ///
+    /// The phenotype is found by its address, so it must be borrowed from
+    /// this population, e.g. from [`Population::get_phenotypes`].
+    ///
     /// # Panics
-    /// Panics if the phenotype's index is out of bounds for the current population size.
-    pub fn get_genotype(&self, phenotype: &E) -> &[E::Gene] {
-        let start = phenotype.index() * self.genotype_size;
-        let end = start + self.genotype_size;
-        // This slice access is safe because `Phenotype::index()` is designed to be
-        // an index into `Population::phenotypes`, and `genotype_arena` is sized
-        // based on `phenotype_size * genotype_size`.
-        &self.genotype_arena[start..end]
+    /// Panics if the phenotype does not belong to this population.
+    pub fn get_genotype(&self, phenotype: &E) -> &[Gene<E>] {
+        let start = slot_of(&self.phenotypes, phenotype) * self.genotype_size;
+        &self.genotype_arena[start..start + self.genotype_size]
     }
 
     /// Iterates over each phenotype mutably, providing its corresponding genotype slice.
@@ -218,7 +734,7 @@ impl<E: Phenotype> Population<E> {
     /// a closure, avoiding the conflicting borrow issues of a standard iterator.
     pub fn for_each_phenotype_mut<F>(&mut self, mut func: F)
     where
-        F: FnMut(&mut E, &[E::Gene]),
+        F: FnMut(&mut E, &[Gene<E>]),
     {
         // Here, we can manually iterate and use split borrows on the struct fields.
         // The borrow checker allows us to mutably borrow `self.phenotypes` while
@@ -239,83 +755,185 @@ impl<E: Phenotype> Population<E> {
 
 /// A trait for types that can initialize a vector of their own type, typically
 /// used for creating initial genotypes.
+///
+/// See the [`initializer`] module for the built-in implementations.
 pub trait GenotypeInitializer {
-    fn initial_genotypes(genotype: &mut [Self], rng: &mut ThreadRng)
+    fn initial_genotypes<R: Rng + ?Sized>(genotype: &mut [Self], rng: &mut R)
     where
         Self: Sized;
 }
 
-impl GenotypeInitializer for u8 {
-    fn initial_genotypes(genotype: &mut [u8], rng: &mut ThreadRng) {
-        let char_range = Uniform::new_inclusive(32u8, 126u8).unwrap();
-        for gene in genotype.iter_mut() {
-            *gene = rng.sample(char_range);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Never produced by `u8::initial_genotypes`, which only yields printable ASCII.
+    const STALE: u8 = 0;
+
+    /// Sums up its genes. Crossover copies the parents, mutation is a no-op,
+    /// so every child is an exact copy of a genotype of the last generation.
+    struct SumEvolver {
+        index: usize,
+        calc_fitness: f64,
     }
-}
 
-pub mod crossover {
-    use rand::prelude::*;
+    impl Phenotype for SumEvolver {
+        type Gene = u8;
+        type FitnessParam = ();
 
-    pub fn single_split<T: Copy>(
-        parent1: &[T],
-        parent2: &[T],
-        child1: &mut [T],
-        child2: &mut [T],
-        size: usize,
-        rng: &mut ThreadRng,
-    ) {
-        let crossover_point = rng.random_range(1..size);
-        let (p1_head, p1_tail) = parent1.split_at(crossover_point);
-        let (p2_head, p2_tail) = parent2.split_at(crossover_point);
+        fn new(index: usize) -> Self {
+            Self {
+                index,
+                calc_fitness: 0.0,
+            }
+        }
 
-        // Create child 1 by combining head of parent 1 and tail of parent 2
-        child1[..crossover_point].copy_from_slice(p1_head);
-        child1[crossover_point..].copy_from_slice(p2_tail);
+        fn fitness(&mut self, genotype: &[u8], _: &()) {
+            self.calc_fitness = genotype.iter().map(|&g| g as f64).sum();
+        }
 
-        // Create child 2 by combining head of parent 2 and tail of parent 1
-        child2[..crossover_point].copy_from_slice(p2_head);
-        child2[crossover_point..].copy_from_slice(p1_tail);
-    }
+        fn mutate<R: Rng + ?Sized>(_: &mut [u8], _: &mut R) {}
 
-    pub fn double_split<T: Copy>(
-        parent1: &[T],
-        parent2: &[T],
-        child1: &mut [T],
-        child2: &mut [T],
-        size: usize,
-        rng: &mut ThreadRng,
-    ) {
-        // Edge Case: If the genotype has fewer than 3 elements,
-        // it's impossible to pick two distinct internal points.
-        // Falling back to no crossover prevents panics or infinite loops.
-        if size < 3 {
+        fn crossover<R: Rng + ?Sized>(
+            parent1: &[u8],
+            parent2: &[u8],
+            child1: &mut [u8],
+            child2: &mut [u8],
+            _: usize,
+            _: &mut R,
+        ) {
             child1.copy_from_slice(parent1);
             child2.copy_from_slice(parent2);
-            return;
         }
 
-        // 1. Generate two DISTINCT crossover points.
-        let mut point1 = rng.random_range(1..size);
-        let mut point2 = rng.random_range(1..size);
-        while point1 == point2 {
-            point2 = rng.random_range(1..size);
+        fn get_fitness(&self) -> f64 {
+            self.calc_fitness
         }
 
-        // 2. Ensure point1 < point2 to make slicing logic simple.
-        if point1 > point2 {
-            std::mem::swap(&mut point1, &mut point2);
+        fn index(&self) -> usize {
+            self.index
         }
 
-        // 3. Assemble the children by swapping the middle segment.
-        // Child 1 = P1_head + P2_middle + P1_tail
-        child1[..point1].copy_from_slice(&parent1[..point1]);
-        child1[point1..point2].copy_from_slice(&parent2[point1..point2]);
-        child1[point2..].copy_from_slice(&parent1[point2..]);
+        fn reset(&mut self) {}
+    }
 
-        // Child 2 = P2_head + P1_middle + P2_tail
-        child2[..point1].copy_from_slice(&parent2[..point1]);
-        child2[point1..point2].copy_from_slice(&parent1[point1..point2]);
-        child2[point2..].copy_from_slice(&parent2[point2..]);
+    fn assert_every_slot_rewritten(
+        evolve: impl Fn(&mut Population<SumEvolver>),
+        population_size: usize,
+        elitism: usize,
+    ) {
+        let genotype_size = 4;
+        let mut population = Population::<SumEvolver>::with_seed(population_size, genotype_size, 7)
+            .with_elitism(elitism);
+
+        for _ in 0..3 {
+            population.next_gen_arena.fill(STALE);
+            evolve(&mut population);
+            for (slot, genotype) in population.genotype_arena.chunks(genotype_size).enumerate() {
+                assert!(
+                    !genotype.contains(&STALE),
+                    "slot {slot} not rewritten (population {population_size}, elitism {elitism})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_slot_is_rewritten() {
+        for population_size in 1..=9 {
+            for elitism in 0..=population_size + 1 {
+                assert_every_slot_rewritten(|p| p.evolve(&()), population_size, elitism);
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn every_slot_is_rewritten_in_parallel() {
+        for population_size in [1, 2, 3, 32, 33, 34, 35, 100, 101] {
+            for elitism in [0, 1, 2, 3] {
+                assert_every_slot_rewritten(|p| p.par_evolve(&()), population_size, elitism);
+            }
+        }
+    }
+
+    #[test]
+    fn elites_are_copied_unchanged() {
+        let genotype_size = 4;
+        let mut population =
+            Population::<SumEvolver>::with_seed(10, genotype_size, 3).with_elitism(3);
+        let before = population.genotype_arena.clone();
+        population.evolve(&());
+
+        let mut sums: Vec<(f64, &[u8])> = before
+            .chunks(genotype_size)
+            .map(|g| (g.iter().map(|&x| x as f64).sum(), g))
+            .collect();
+        sums.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+        for (slot, (_, elite)) in population
+            .genotype_arena
+            .chunks(genotype_size)
+            .zip(&sums)
+            .take(3)
+        {
+            assert_eq!(slot, *elite);
+        }
+    }
+
+    #[test]
+    fn fittest_is_taken_from_the_evaluated_generation() {
+        let genotype_size = 4;
+        let mut population = Population::<SumEvolver>::with_seed(10, genotype_size, 5);
+        population.evolve(&());
+
+        let (fitness, genotype) = population.fittest();
+        let sum: f64 = genotype.iter().map(|&x| x as f64).sum();
+        assert_eq!(fitness, sum);
+    }
+
+    #[test]
+    fn best_genotype_survives_resize() {
+        let genotype_size = 4;
+        for seed in 0..20 {
+            let mut population =
+                Population::<SumEvolver>::with_seed(10, genotype_size, seed).with_elitism(2);
+            population.evolve(&());
+            let (best, genotype) = population.fittest();
+
+            population.resize(3);
+            let elite = population.individuals()[0];
+            assert_eq!(population.genotype(elite), Some(genotype.as_slice()));
+            assert_eq!(population.fitness_of(elite), Some(best));
+            assert!(
+                population
+                    .fitness_of(population.individuals()[2])
+                    .unwrap()
+                    .is_nan()
+            );
+        }
+    }
+
+    #[test]
+    fn fittest_matches_its_genotype_after_resize_and_remove() {
+        let genotype_size = 4;
+        let sum = |genotype: &[u8]| genotype.iter().map(|&g| g as f64).sum::<f64>();
+        for seed in 0..20 {
+            let mut population =
+                Population::<SumEvolver>::with_seed(10, genotype_size, seed).with_elitism(2);
+            population.evolve(&());
+            population.resize(6);
+            let (best, genotype) = population.fittest();
+            assert_eq!(best, sum(&genotype));
+
+            population.remove(population.individuals()[0]);
+            let (best, genotype) = population.fittest();
+            assert_eq!(best, sum(&genotype));
+
+            population.insert(&[b'~'; 4]);
+            let (best, genotype) = population.fittest();
+            assert_eq!(best, sum(&genotype));
+            population.evolve(&());
+        }
     }
 }
@@ -0,0 +1,528 @@
+//! # Multi-Objective Optimisation
+//!
+//! A [`MultiObjective`] phenotype reports a vector of objectives instead of
+//! folding them into a single weighted fitness. [`Nsga2`] evolves such
+//! phenotypes with the NSGA-II algorithm (Deb et al., 2002):
+//!
+//! 1. Parents and offspring are sorted into fronts of mutually
+//!    non-dominated individuals, see [`non_dominated_sort`].
+//! 2. The next parents are taken front by front. The front that does not
+//!    fit completely is cut by [`crowding_distance`], preferring individuals
+//!    in sparsely populated regions of the objective space.
+//! 3. Offspring are bred from parents picked by binary tournaments on
+//!    (front, crowding distance).
+//!
+//! All objectives are maximised. NaN is the worst value of an objective and
+//! non-finite values are clamped like fitness values, see
+//! [`fitness`](crate::fitness).
+
+use std::cmp::Ordering;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    Evaluator, Gene, Genome, GenotypeInitializer, Individual, fitness,
+    statistics::{self, Observer, Observers, Statistics},
+    termination::{self, RunReport, Termination},
+};
+
+/// A phenotype with several objectives.
+///
+/// `Evaluator::evaluate` computes the objectives, `objectives` returns them.
+/// `Evaluator::fitness` is not used for selection, only for the
+/// statistics and termination criteria, so it can return whatever scalar is
+/// worth monitoring, e.g. the most important objective.
+pub trait MultiObjective: Evaluator {
+    /// The objectives computed by the last call to `Evaluator::evaluate`.
+    ///
+    /// All phenotypes of a population must return the same number of
+    /// objectives.
+    fn objectives(&self) -> &[f64];
+}
+
+/// Whether `a` is at least as good as `b` in every objective and better in
+/// at least one.
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    let mut better = false;
+    for (&a, &b) in a.iter().zip(b) {
+        match fitness::compare(a, b) {
+            Ordering::Less => return false,
+            Ordering::Greater => better = true,
+            Ordering::Equal => {}
+        }
+    }
+    better
+}
+
+/// Sorts the individuals into Pareto fronts.
+///
+/// The first front holds the indices of all individuals that no other
+/// individual dominates, the second those only dominated by the first front,
+/// and so on.
+pub fn non_dominated_sort<V: AsRef<[f64]>>(objectives: &[V]) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut domination_count = vec![0usize; n];
+    let mut dominated: Vec<Vec<usize>> = vec![Vec::new(); n];
+
+    for a in 0..n {
+        for b in a + 1..n {
+            if dominates(objectives[a].as_ref(), objectives[b].as_ref()) {
+                dominated[a].push(b);
+                domination_count[b] += 1;
+            } else if dominates(objectives[b].as_ref(), objectives[a].as_ref()) {
+                dominated[b].push(a);
+                domination_count[a] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..n).filter(|&i| domination_count[i] == 0).collect();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for &a in &front {
+            for &b in &dominated[a] {
+                domination_count[b] -= 1;
+                if domination_count[b] == 0 {
+                    next.push(b);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next;
+    }
+    fronts
+}
+
+/// The crowding distance of every member of `front`, in the order of `front`.
+///
+/// The distance is the sum over all objectives of the normalised gap between
+/// the two neighbours of an individual. The extremes of every objective get
+/// an infinite distance, so that they are always kept.
+pub fn crowding_distance<V: AsRef<[f64]>>(objectives: &[V], front: &[usize]) -> Vec<f64> {
+    let mut distance = vec![0.0; front.len()];
+    if front.len() <= 2 {
+        distance.fill(f64::INFINITY);
+        return distance;
+    }
+
+    let objective = |member: usize, k: usize| objectives[front[member]].as_ref()[k];
+    let mut order: Vec<usize> = (0..front.len()).collect();
+    let last = front.len() - 1;
+
+    for k in 0..objectives[front[0]].as_ref().len() {
+        order.sort_by(|&a, &b| fitness::compare(objective(a, k), objective(b, k)));
+        distance[order[0]] = f64::INFINITY;
+        distance[order[last]] = f64::INFINITY;
+
+        let range = objective(order[last], k) - objective(order[0], k);
+        if range > 0.0 {
+            for w in 1..last {
+                distance[order[w]] +=
+                    (objective(order[w + 1], k) - objective(order[w - 1], k)) / range;
+            }
+        }
+    }
+    distance
+}
+
+/// An NSGA-II population.
+///
+/// Holds the parents and their offspring side by side, twice the population
+/// size in total. Every individual keeps its slot for as long as it survives,
+/// so the phenotypes of the Pareto front stay valid between generations.
+/// Only the offspring are evaluated by [`Nsga2::evolve`].
+#[derive(Debug)]
+pub struct Nsga2<E: MultiObjective, R: Rng = ChaCha8Rng> {
+    genotype_arena: Vec<Gene<E>>,
+    phenotypes: Vec<E>,
+    genotype_size: usize,
+    population_size: usize,
+    rng: R,
+    objectives: Vec<Vec<f64>>,
+    /// Slots holding genotypes that have not been evaluated yet.
+    fresh: Vec<bool>,
+    rank: Vec<usize>,
+    crowding: Vec<f64>,
+    survivors: Vec<usize>,
+    evaluations: usize,
+    generation: usize,
+    history: Vec<Statistics>,
+    observers: Observers,
+}
+
+impl<E: MultiObjective, R: Rng + SeedableRng> Nsga2<E, R> {
+    /// Creates a population seeded from the thread-local entropy source.
+    pub fn new(population_size: usize, genotype_size: usize) -> Self {
+        Self::with_rng(
+            population_size,
+            genotype_size,
+            R::from_rng(&mut rand::rng()),
+        )
+    }
+
+    /// Creates a population whose whole run is determined by `seed`.
+    pub fn with_seed(population_size: usize, genotype_size: usize, seed: u64) -> Self {
+        Self::with_rng(population_size, genotype_size, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: MultiObjective, R: Rng> Nsga2<E, R> {
+    /// Creates a population driven by the given random number generator.
+    ///
+    /// Twice `population_size` random genotypes are created, so the first
+    /// generation already selects its parents from a full set of candidates.
+    pub fn with_rng(population_size: usize, genotype_size: usize, mut rng: R) -> Self {
+        let slots = 2 * population_size;
+        let mut genotype_arena = vec![Gene::<E>::default(); slots * genotype_size];
+        for genotype in genotype_arena.chunks_mut(genotype_size) {
+            Gene::<E>::initial_genotypes(genotype, &mut rng);
+        }
+
+        Self {
+            genotype_arena,
+            phenotypes: (0..slots).map(|i| E::new(Individual(i))).collect(),
+            genotype_size,
+            population_size,
+            rng,
+            objectives: vec![Vec::new(); slots],
+            fresh: vec![true; slots],
+            rank: vec![0; slots],
+            crowding: vec![0.0; slots],
+            survivors: Vec::new(),
+            evaluations: 0,
+            generation: 0,
+            history: Vec::new(),
+            observers: Observers::default(),
+        }
+    }
+
+    /// Registers an observer that is notified after every evaluated generation.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    /// Evaluates the offspring, selects the next parents and breeds new
+    /// offspring from them.
+    pub fn evolve(&mut self, param: &E::FitnessParam) {
+        self.calculate_objectives(param);
+        self.select_survivors();
+        self.record_statistics();
+        self.create_offspring();
+    }
+
+    /// Evolves the population until `termination` is satisfied.
+    ///
+    /// The termination is checked against the statistics of
+    /// `Evaluator::fitness` of the parents, see [`MultiObjective`].
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<Gene<E>> {
+        termination::drive(
+            self,
+            |nsga2| nsga2.evolve(param),
+            |nsga2| &nsga2.history,
+            |nsga2, index| nsga2.genotype(index),
+            termination,
+        )
+    }
+
+    fn calculate_objectives(&mut self, param: &E::FitnessParam) {
+        for (slot, (p, genotype)) in self
+            .phenotypes
+            .iter_mut()
+            .zip(self.genotype_arena.chunks(self.genotype_size))
+            .enumerate()
+        {
+            if self.fresh[slot] {
+                p.evaluate(genotype, param);
+                self.objectives[slot].clear();
+                self.objectives[slot].extend_from_slice(p.objectives());
+                self.fresh[slot] = false;
+                self.evaluations += 1;
+            }
+        }
+
+        // Clamp every objective into its finite range, as for fitness values.
+        let count = self.objectives.first().map_or(0, Vec::len);
+        let mut column = Vec::with_capacity(self.objectives.len());
+        for k in 0..count {
+            column.clear();
+            column.extend(self.objectives.iter().map(|o| o[k]));
+            fitness::sanitize(&mut column);
+            for (objectives, value) in self.objectives.iter_mut().zip(&column) {
+                objectives[k] = *value;
+            }
+        }
+    }
+
+    /// Keeps the best `population_size` individuals of parents and offspring.
+    fn select_survivors(&mut self) {
+        self.survivors.clear();
+        for (rank, front) in non_dominated_sort(&self.objectives).iter().enumerate() {
+            let distance = crowding_distance(&self.objectives, front);
+            for (&slot, &distance) in front.iter().zip(&distance) {
+                self.rank[slot] = rank;
+                self.crowding[slot] = distance;
+            }
+
+            let room = self.population_size - self.survivors.len();
+            if front.len() <= room {
+                self.survivors.extend_from_slice(front);
+            } else {
+                let mut front = front.clone();
+                front.sort_by(|&a, &b| fitness::compare(self.crowding[b], self.crowding[a]));
+                self.survivors.extend_from_slice(&front[..room]);
+            }
+            if self.survivors.len() == self.population_size {
+                break;
+            }
+        }
+        self.survivors.sort_unstable();
+    }
+
+    /// Summarises the `get_fitness` of the parents. `elite_index` is the slot
+    /// of the fittest parent.
+    fn record_statistics(&mut self) {
+        let fitness: Vec<f64> = self
+            .survivors
+            .iter()
+            .map(|&slot| self.phenotypes[slot].fitness())
+            .collect();
+        let mut statistics = Statistics::from_fitness(self.generation, &fitness);
+
+        let parents: Vec<Gene<E>> = self
+            .survivors
+            .iter()
+            .flat_map(|&slot| self.genotype(slot).iter().copied())
+            .collect();
+        statistics.diversity =
+            statistics::diversity(&parents, self.genotype_size, statistics.elite_index);
+        statistics.elite_index = self
+            .survivors
+            .get(statistics.elite_index)
+            .copied()
+            .unwrap_or(0);
+        statistics.evaluations = self.evaluations;
+
+        self.observers.notify(&statistics);
+        self.history.push(statistics);
+        self.generation += 1;
+    }
+
+    /// Overwrites every slot that did not survive with a child of two parents.
+    fn create_offspring(&mut self) {
+        if self.survivors.is_empty() {
+            return;
+        }
+
+        let genotype_size = self.genotype_size;
+        let targets: Vec<usize> = (0..self.phenotypes.len())
+            .filter(|slot| self.survivors.binary_search(slot).is_err())
+            .collect();
+        let mut children = vec![Gene::<E>::default(); 2 * genotype_size];
+
+        for targets in targets.chunks(2) {
+            let parent1 = self.tournament();
+            let parent2 = self.tournament();
+            let (child1, child2) = children.split_at_mut(genotype_size);
+            let start1 = parent1 * genotype_size;
+            let start2 = parent2 * genotype_size;
+            E::Genome::crossover(
+                &self.genotype_arena[start1..start1 + genotype_size],
+                &self.genotype_arena[start2..start2 + genotype_size],
+                child1,
+                child2,
+                genotype_size,
+                &mut self.rng,
+            );
+
+            for (&slot, child) in targets.iter().zip(children.chunks_mut(genotype_size)) {
+                E::Genome::mutate(child, &mut self.rng);
+                let start = slot * genotype_size;
+                self.genotype_arena[start..start + genotype_size].copy_from_slice(child);
+                self.fresh[slot] = true;
+            }
+        }
+    }
+
+    /// Binary tournament on the crowded comparison: the lower front wins, on
+    /// the same front the larger crowding distance.
+    fn tournament(&mut self) -> usize {
+        let a = self.survivors[self.rng.random_range(0..self.survivors.len())];
+        let b = self.survivors[self.rng.random_range(0..self.survivors.len())];
+        let a_wins = self.rank[a]
+            .cmp(&self.rank[b])
+            .then_with(|| fitness::compare(self.crowding[b], self.crowding[a]))
+            .is_le();
+        if a_wins { a } else { b }
+    }
+
+    fn genotype(&self, slot: usize) -> &[Gene<E>] {
+        let start = slot * self.genotype_size;
+        &self.genotype_arena[start..start + self.genotype_size]
+    }
+
+    /// The non-dominated parents of the last evaluated generation.
+    pub fn pareto_front(&self) -> impl Iterator<Item = &E> {
+        self.survivors
+            .iter()
+            .filter(|&&slot| self.rank[slot] == 0)
+            .map(|&slot| &self.phenotypes[slot])
+    }
+
+    /// The parents of the last evaluated generation.
+    pub fn parents(&self) -> impl Iterator<Item = &E> {
+        self.survivors.iter().map(|&slot| &self.phenotypes[slot])
+    }
+
+    /// The Pareto front a phenotype belongs to, starting at 0, or `None` if
+    /// it is not a parent of the last evaluated generation.
+    pub fn rank(&self, phenotype: &E) -> Option<usize> {
+        let slot = crate::slot_of(&self.phenotypes, phenotype);
+        self.survivors
+            .binary_search(&slot)
+            .ok()
+            .map(|_| self.rank[slot])
+    }
+
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The statistics of every evaluated generation, oldest first.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
+    /// All phenotypes, parents and offspring.
+    pub fn get_phenotypes(&self) -> &[E] {
+        &self.phenotypes
+    }
+
+    /// Retrieves the genotype of a phenotype.
+    ///
+    /// # Panics
+    /// Panics if the phenotype's index is out of bounds.
+    pub fn get_genotype(&self, phenotype: &E) -> &[Gene<E>] {
+        self.genotype(crate::slot_of(&self.phenotypes, phenotype))
+    }
+
+    /// Iterates over each phenotype mutably, providing its genotype slice.
+    ///
+    /// Only the offspring are evaluated by the next `evolve`, but the
+    /// closure is called for every phenotype.
+    pub fn for_each_phenotype_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut E, &[Gene<E>]),
+    {
+        for (p, genotype) in self
+            .phenotypes
+            .iter_mut()
+            .zip(self.genotype_arena.chunks(self.genotype_size))
+        {
+            func(p, genotype);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::{Real, Unit};
+
+    #[test]
+    fn points_are_sorted_into_fronts() {
+        let points = [
+            [1.0, 5.0],
+            [2.0, 4.0],
+            [3.0, 3.0],
+            [1.0, 4.0],
+            [2.0, 2.0],
+            [0.0, 0.0],
+            [3.0, 3.0],
+        ];
+        let mut fronts = non_dominated_sort(&points);
+        fronts.iter_mut().for_each(|front| front.sort_unstable());
+        // equal points do not dominate each other
+        assert_eq!(fronts, vec![vec![0, 1, 2, 6], vec![3, 4], vec![5]]);
+        assert!(non_dominated_sort::<[f64; 2]>(&[]).is_empty());
+    }
+
+    #[test]
+    fn boundaries_are_infinitely_far_from_the_crowd() {
+        let points = [[1.0, 4.0], [0.0, 5.0], [4.0, 0.0], [2.0, 2.0]];
+        let distance = crowding_distance(&points, &[0, 1, 2, 3]);
+        assert_eq!(distance[1], f64::INFINITY);
+        assert_eq!(distance[2], f64::INFINITY);
+        assert!((distance[0] - (2.0 / 4.0 + 3.0 / 5.0)).abs() < 1e-12);
+        assert!((distance[3] - (3.0 / 4.0 + 4.0 / 5.0)).abs() < 1e-12);
+
+        assert_eq!(crowding_distance(&points, &[0, 3]), vec![f64::INFINITY; 2]);
+    }
+
+    /// Maximises `x` and `1 - x - y`, the Pareto front is `y = 0`.
+    struct TradeOff {
+        objectives: [f64; 2],
+    }
+
+    impl Evaluator for TradeOff {
+        type Genome = Real;
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            TradeOff {
+                objectives: [0.0; 2],
+            }
+        }
+
+        fn evaluate(&mut self, genotype: &[Unit], _: &()) {
+            let (x, y) = (genotype[0].0, genotype[1].0);
+            self.objectives = [x, 1.0 - x - y];
+        }
+
+        fn fitness(&self) -> f64 {
+            self.objectives[1]
+        }
+    }
+
+    impl MultiObjective for TradeOff {
+        fn objectives(&self) -> &[f64] {
+            &self.objectives
+        }
+    }
+
+    #[test]
+    fn nsga2_keeps_the_first_front() {
+        let population_size = 20;
+        let mut nsga2: Nsga2<TradeOff> = Nsga2::with_seed(population_size, 2, 1);
+        for _ in 0..50 {
+            nsga2.evolve(&());
+            // `objectives` still holds every slot evaluated by this generation
+            let first = &non_dominated_sort(&nsga2.objectives)[0];
+            if first.len() <= population_size {
+                assert!(first.iter().all(|slot| nsga2.survivors.contains(slot)));
+                assert_eq!(nsga2.pareto_front().count(), first.len());
+            } else {
+                assert_eq!(nsga2.pareto_front().count(), population_size);
+            }
+            for phenotype in nsga2.pareto_front() {
+                assert_eq!(nsga2.rank(phenotype), Some(0));
+            }
+        }
+        assert!(
+            nsga2
+                .pareto_front()
+                .all(|p| p.objectives[0] + p.objectives[1] > 0.9)
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! # Mutation Operators
+//!
+//! Ready-made building blocks for `Genome::mutate` and
+//! `Genome::mutate_with_rate`.
+//!
+//! The per-gene operators ([`bit_flip`], [`random_reset`], [`gaussian`],
+//! [`creep`]) visit every gene and change it with probability `rate`. The
+//! permutation operators ([`swap`], [`inversion`], [`scramble`]) keep every
+//! gene in the genotype and are applied at most once per call, with
+//! probability `rate`. The length-changing operators ([`insertion`],
+//! [`deletion`]) work on the genomes of a `VariablePopulation` and are
+//! applied at most once per call as well. Rates outside `0..=1` are clamped,
+//! a NaN rate mutates nothing.
+//!
+//! A [`MutationSchedule`] set on the `Population` adapts the rate during a
+//! run and passes it to `Genome::mutate_with_rate`.
+
+use rand::prelude::*;
+
+use crate::{GenotypeInitializer, crossover::RealGene, statistics::Statistics};
+
+/// Flips every bit with probability `rate`.
+pub fn bit_flip<R: Rng + ?Sized>(genotype: &mut [bool], rate: f64, rng: &mut R) {
+    let rate = probability(rate);
+    for gene in genotype.iter_mut() {
+        if rng.random_bool(rate) {
+            *gene = !*gene;
+        }
+    }
+}
+
+/// Replaces every gene with probability `rate` by a new gene drawn from the
+/// gene's [`GenotypeInitializer`].
+pub fn random_reset<T: GenotypeInitializer, R: Rng + ?Sized>(
+    genotype: &mut [T],
+    rate: f64,
+    rng: &mut R,
+) {
+    let rate = probability(rate);
+    for gene in genotype.iter_mut() {
+        if rng.random_bool(rate) {
+            T::initial_genotypes(std::slice::from_mut(gene), rng);
+        }
+    }
+}
+
+/// Replaces every gene with probability `rate` by the result of `sample`.
+pub fn random_reset_with<T, R: Rng + ?Sized>(
+    genotype: &mut [T],
+    rate: f64,
+    rng: &mut R,
+    mut sample: impl FnMut(&mut R) -> T,
+) {
+    let rate = probability(rate);
+    for gene in genotype.iter_mut() {
+        if rng.random_bool(rate) {
+            *gene = sample(rng);
+        }
+    }
+}
+
+/// Adds normally distributed noise with standard deviation `sigma` to every
+/// gene with probability `rate`.
+pub fn gaussian<T: RealGene, R: Rng + ?Sized>(
+    genotype: &mut [T],
+    rate: f64,
+    sigma: f64,
+    rng: &mut R,
+) {
+    let rate = probability(rate);
+    for gene in genotype.iter_mut() {
+        if rng.random_bool(rate) {
+            *gene = T::from_f64(gene.to_f64() + sigma * standard_normal(rng));
+        }
+    }
+}
+
+/// Adds a uniformly distributed value from `-step..=step` to every gene with
+/// probability `rate`.
+///
+/// # Panics
+/// Panics if `step` is negative or NaN.
+pub fn creep<T: RealGene, R: Rng + ?Sized>(genotype: &mut [T], rate: f64, step: f64, rng: &mut R) {
+    assert!(
+        step >= 0.0,
+        "the creep step must not be negative, got {step}"
+    );
+    let rate = probability(rate);
+    for gene in genotype.iter_mut() {
+        if rng.random_bool(rate) {
+            *gene = T::from_f64(gene.to_f64() + rng.random_range(-step..=step));
+        }
+    }
+}
+
+/// Swaps two distinct random genes with probability `rate`.
+pub fn swap<T, R: Rng + ?Sized>(genotype: &mut [T], rate: f64, rng: &mut R) {
+    let rate = probability(rate);
+    if genotype.len() < 2 || !rng.random_bool(rate) {
+        return;
+    }
+    let a = rng.random_range(0..genotype.len());
+    let mut b = rng.random_range(0..genotype.len() - 1);
+    if b >= a {
+        b += 1;
+    }
+    genotype.swap(a, b);
+}
+
+/// Reverses a random segment with probability `rate`.
+pub fn inversion<T, R: Rng + ?Sized>(genotype: &mut [T], rate: f64, rng: &mut R) {
+    let rate = probability(rate);
+    if genotype.len() < 2 || !rng.random_bool(rate) {
+        return;
+    }
+    let (start, end) = segment(genotype.len(), rng);
+    genotype[start..end].reverse();
+}
+
+/// Shuffles a random segment with probability `rate`.
+pub fn scramble<T, R: Rng + ?Sized>(genotype: &mut [T], rate: f64, rng: &mut R) {
+    let rate = probability(rate);
+    if genotype.len() < 2 || !rng.random_bool(rate) {
+        return;
+    }
+    let (start, end) = segment(genotype.len(), rng);
+    genotype[start..end].shuffle(rng);
+}
+
+/// Inserts a new gene drawn from the gene's [`GenotypeInitializer`] at a
+/// random position with probability `rate`, unless the genotype already
+/// holds `max_len` genes.
+pub fn insertion<T: GenotypeInitializer + Default, R: Rng + ?Sized>(
+    genotype: &mut Vec<T>,
+    rate: f64,
+    max_len: usize,
+    rng: &mut R,
+) {
+    let rate = probability(rate);
+    if genotype.len() >= max_len || !rng.random_bool(rate) {
+        return;
+    }
+    let mut gene = T::default();
+    T::initial_genotypes(std::slice::from_mut(&mut gene), rng);
+    let position = rng.random_range(0..=genotype.len());
+    genotype.insert(position, gene);
+}
+
+/// Removes a random gene with probability `rate`, unless the genotype holds
+/// only `min_len` genes.
+pub fn deletion<T, R: Rng + ?Sized>(genotype: &mut Vec<T>, rate: f64, min_len: usize, rng: &mut R) {
+    let rate = probability(rate);
+    if genotype.len() <= min_len || !rng.random_bool(rate) {
+        return;
+    }
+    let position = rng.random_range(0..genotype.len());
+    genotype.remove(position);
+}
+
+/// A rate as a probability for `random_bool`, which panics on NaN.
+pub(crate) fn probability(rate: f64) -> f64 {
+    if rate.is_nan() {
+        0.0
+    } else {
+        rate.clamp(0.0, 1.0)
+    }
+}
+
+/// A random segment `start..end` of at least two genes.
+fn segment<R: Rng + ?Sized>(size: usize, rng: &mut R) -> (usize, usize) {
+    let start = rng.random_range(0..size - 1);
+    let end = rng.random_range(start + 2..=size);
+    (start, end)
+}
+
+/// Samples the standard normal distribution with the Box-Muller transform.
+pub(crate) fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    // 1 - u lies in (0, 1], so the logarithm is finite
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+/// What a [`MutationSchedule`] learns about the last evaluated generation.
+#[derive(Debug, Clone, Copy)]
+pub struct Feedback<'a> {
+    pub statistics: &'a Statistics,
+    /// Fraction of the bred children that are fitter than the better of their
+    /// parents. `None` if the generation contains no bred children yet.
+    pub success_ratio: Option<f64>,
+}
+
+/// Adapts the mutation rate during a run.
+pub trait MutationSchedule: std::fmt::Debug {
+    /// The mutation rate for the next generation.
+    fn rate(&self) -> f64;
+    /// Called after every evaluated generation, before the next one is bred.
+    fn update(&mut self, feedback: &Feedback);
+}
+
+/// A fixed mutation rate.
+#[derive(Debug, Clone, Copy)]
+pub struct Constant(pub f64);
+
+impl MutationSchedule for Constant {
+    fn rate(&self) -> f64 {
+        self.0
+    }
+    fn update(&mut self, _: &Feedback) {}
+}
+
+/// Moves the rate linearly from `start` to `end` over `generations`
+/// generations and keeps it at `end` afterwards.
+#[derive(Debug, Clone, Copy)]
+pub struct LinearDecay {
+    pub start: f64,
+    pub end: f64,
+    pub generations: usize,
+    rate: f64,
+}
+
+impl LinearDecay {
+    pub fn new(start: f64, end: f64, generations: usize) -> Self {
+        Self {
+            start,
+            end,
+            generations,
+            rate: start,
+        }
+    }
+}
+
+impl MutationSchedule for LinearDecay {
+    fn rate(&self) -> f64 {
+        self.rate
+    }
+    fn update(&mut self, feedback: &Feedback) {
+        let progress = if self.generations == 0 {
+            1.0
+        } else {
+            ((feedback.statistics.generation + 1) as f64 / self.generations as f64).min(1.0)
+        };
+        // exactly `end` once the decay is over
+        self.rate = self.start * (1.0 - progress) + self.end * progress;
+    }
+}
+
+/// Rechenberg's 1/5 success rule.
+///
+/// If more than a fifth of the children improve on their parents, the search
+/// is too timid and the rate is increased; if fewer do, the rate is decreased.
+/// `factor` (e.g. `0.85`) is the multiplicative step, the rate stays within
+/// `min..=max`.
+#[derive(Debug, Clone, Copy)]
+pub struct OneFifthRule {
+    pub factor: f64,
+    pub min: f64,
+    pub max: f64,
+    rate: f64,
+}
+
+impl OneFifthRule {
+    /// # Panics
+    /// Panics if `min` is greater than `max` or either of them is NaN.
+    pub fn new(rate: f64, factor: f64, min: f64, max: f64) -> Self {
+        assert!(
+            min <= max,
+            "the rate bounds must satisfy min <= max, got {min} and {max}"
+        );
+        Self {
+            factor,
+            min,
+            max,
+            rate: rate.clamp(min, max),
+        }
+    }
+}
+
+impl MutationSchedule for OneFifthRule {
+    fn rate(&self) -> f64 {
+        self.rate
+    }
+    fn update(&mut self, feedback: &Feedback) {
+        let Some(success_ratio) = feedback.success_ratio else {
+            return;
+        };
+        if success_ratio > 0.2 {
+            self.rate /= self.factor;
+        } else if success_ratio < 0.2 {
+            self.rate *= self.factor;
+        }
+        // unlike `clamp`, fine with bounds changed by hand
+        self.rate = self.rate.max(self.min).min(self.max);
+    }
+}
+
+/// Switches to the `hyper` rate while the population diversity (see
+/// `Statistics::diversity`) is below `threshold`, and back to `base` once it
+/// has recovered.
+#[derive(Debug, Clone, Copy)]
+pub struct Hypermutation {
+    pub base: f64,
+    pub hyper: f64,
+    pub threshold: f64,
+    rate: f64,
+}
+
+impl Hypermutation {
+    pub fn new(base: f64, hyper: f64, threshold: f64) -> Self {
+        Self {
+            base,
+            hyper,
+            threshold,
+            rate: base,
+        }
+    }
+}
+
+impl MutationSchedule for Hypermutation {
+    fn rate(&self) -> f64 {
+        self.rate
+    }
+    fn update(&mut self, feedback: &Feedback) {
+        self.rate = if feedback.statistics.diversity < self.threshold {
+            self.hyper
+        } else {
+            self.base
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    fn rng() -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(1)
+    }
+
+    #[test]
+    fn bit_flip_follows_the_rate() {
+        let mut rng = rng();
+        let mut genotype = [false; 1000];
+        for rate in [0.0, -1.0, f64::NAN] {
+            bit_flip(&mut genotype, rate, &mut rng);
+            assert!(genotype.iter().all(|&bit| !bit));
+        }
+        bit_flip(&mut genotype, 2.0, &mut rng);
+        assert!(genotype.iter().all(|&bit| bit));
+        bit_flip(&mut genotype, 0.1, &mut rng);
+        let flipped = genotype.iter().filter(|&&bit| !bit).count();
+        assert!((70..130).contains(&flipped), "{flipped}");
+    }
+
+    #[test]
+    fn gaussian_adds_noise_of_the_given_deviation() {
+        let mut rng = rng();
+        let mut genotype = [5.0; 10_000];
+        gaussian(&mut genotype, 1.0, 0.0, &mut rng);
+        assert!(genotype.iter().all(|&gene| gene == 5.0));
+        gaussian(&mut genotype, 1.0, 2.0, &mut rng);
+        let n = genotype.len() as f64;
+        let mean = genotype.iter().sum::<f64>() / n;
+        let deviation = (genotype.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / n).sqrt();
+        assert!((mean - 5.0).abs() < 0.1, "{mean}");
+        assert!((deviation - 2.0).abs() < 0.1, "{deviation}");
+    }
+
+    #[test]
+    fn creep_stays_within_the_step() {
+        let mut rng = rng();
+        let mut genotype = [0.0f64; 1000];
+        creep(&mut genotype, 1.0, 0.5, &mut rng);
+        assert!(genotype.iter().all(|gene| gene.abs() <= 0.5));
+        assert!(genotype.iter().any(|&gene| gene != 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "must not be negative")]
+    fn creep_rejects_a_negative_step() {
+        creep(&mut [0.0], 1.0, -1.0, &mut rng());
+    }
+
+    #[test]
+    fn swap_always_exchanges_two_distinct_genes() {
+        let mut rng = rng();
+        for len in 2..10 {
+            for _ in 0..100 {
+                let mut genotype: Vec<usize> = (0..len).collect();
+                swap(&mut genotype, 1.0, &mut rng);
+                let moved: Vec<usize> = (0..len).filter(|&i| genotype[i] != i).collect();
+                assert_eq!(moved.len(), 2);
+                assert_eq!(genotype[moved[0]], moved[1]);
+            }
+        }
+        let mut single = [7];
+        swap(&mut single, 1.0, &mut rng);
+        assert_eq!(single, [7]);
+    }
+
+    #[test]
+    fn inversion_reverses_one_segment() {
+        let mut rng = rng();
+        for _ in 0..100 {
+            let mut genotype: Vec<usize> = (0..10).collect();
+            inversion(&mut genotype, 1.0, &mut rng);
+            let start = (0..10).find(|&i| genotype[i] != i).unwrap();
+            let end = (0..10).rfind(|&i| genotype[i] != i).unwrap();
+            let reversed: Vec<usize> = (start..=end).rev().collect();
+            assert_eq!(genotype[start..=end], reversed);
+        }
+    }
+
+    #[test]
+    fn schedules_adapt_the_rate() {
+        let mut statistics = Statistics::from_fitness(0, &[1.0]);
+        let update = |schedule: &mut dyn MutationSchedule, statistics: &Statistics, ratio| {
+            schedule.update(&Feedback {
+                statistics,
+                success_ratio: ratio,
+            });
+            schedule.rate()
+        };
+
+        let mut decay = LinearDecay::new(0.5, 0.1, 4);
+        assert_eq!(decay.rate(), 0.5);
+        statistics.generation = 1;
+        assert!((update(&mut decay, &statistics, None) - 0.3).abs() < 1e-12);
+        statistics.generation = 10;
+        assert_eq!(update(&mut decay, &statistics, None), 0.1);
+
+        let mut one_fifth = OneFifthRule::new(0.5, 0.5, 0.1, 0.8);
+        assert_eq!(update(&mut one_fifth, &statistics, Some(0.5)), 0.8);
+        assert_eq!(update(&mut one_fifth, &statistics, Some(0.2)), 0.8);
+        assert_eq!(update(&mut one_fifth, &statistics, None), 0.8);
+        assert_eq!(update(&mut one_fifth, &statistics, Some(0.0)), 0.4);
+        assert_eq!(update(&mut one_fifth, &statistics, Some(0.0)), 0.2);
+        assert_eq!(update(&mut one_fifth, &statistics, Some(0.0)), 0.1);
+
+        let mut hyper = Hypermutation::new(0.01, 0.3, 0.1);
+        statistics.diversity = 0.05;
+        assert_eq!(update(&mut hyper, &statistics, None), 0.3);
+        statistics.diversity = 0.5;
+        assert_eq!(update(&mut hyper, &statistics, None), 0.01);
+
+        assert_eq!(update(&mut Constant(0.2), &statistics, Some(1.0)), 0.2);
+    }
+
+    #[test]
+    #[should_panic(expected = "min <= max")]
+    fn one_fifth_rule_rejects_inverted_bounds() {
+        OneFifthRule::new(0.5, 0.85, 0.8, 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "min <= max")]
+    fn one_fifth_rule_rejects_nan_bounds() {
+        OneFifthRule::new(0.5, 0.85, f64::NAN, 0.1);
+    }
+}
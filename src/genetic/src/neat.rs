@@ -0,0 +1,1028 @@
+//! # NeuroEvolution of Augmenting Topologies
+//!
+//! [`Neat`] (Stanley & Miikkulainen, 2002) evolves the weights and the
+//! topology of feed-forward neural networks that serve as controllers: every
+//! [`NetworkEvaluator`] drives its [`Network`] with sensor inputs and acts on
+//! the outputs, and its fitness is what the controller achieves.
+//!
+//! - Evolution starts from minimal networks, every input and the bias
+//!   connected to every output. Structural mutations add connections and
+//!   split connections with new hidden nodes.
+//! - Every structural innovation is numbered. The same connection gets the
+//!   same number in every genome, so genomes of different topologies line up
+//!   for crossover and for the compatibility distance.
+//! - Genomes are divided into species by their compatibility distance. The
+//!   fitness is shared within a species, and every species gets offspring in
+//!   proportion to its shared fitness. New structure can therefore mature
+//!   before it has to compete with the whole population. Species that stop
+//!   improving are dropped.
+//!
+//! Connections never form a cycle, so a network is evaluated in a single
+//! pass and keeps no state between activations.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    Individual, fitness,
+    mutation::{self, standard_normal},
+    statistics::{Observer, Observers, Statistics},
+    termination::{self, RunReport, Termination},
+};
+
+/// A connection between two nodes of a [`NetworkGenome`].
+///
+/// The inputs are the nodes `0..inputs`, followed by the bias and the
+/// outputs. Hidden nodes get higher ids as they are created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionGene {
+    /// The innovation number, the same for the same connection in every genome.
+    pub innovation: usize,
+    pub from: usize,
+    pub to: usize,
+    pub weight: f64,
+    /// Disabled connections are kept for crossover but carry no signal.
+    pub enabled: bool,
+}
+
+/// The innovation numbers and hidden nodes created so far.
+#[derive(Debug, Clone, Default)]
+struct Innovations {
+    connections: HashMap<(usize, usize), usize>,
+    /// The node created by splitting a connection, by its innovation.
+    splits: HashMap<usize, usize>,
+    next_node: usize,
+}
+
+impl Innovations {
+    fn connection(&mut self, from: usize, to: usize) -> usize {
+        let next = self.connections.len();
+        *self.connections.entry((from, to)).or_insert(next)
+    }
+
+    /// The node splitting a connection. A genome that has split it before,
+    /// and since re-enabled the connection, gets a new node.
+    fn split(&mut self, innovation: usize, hidden: &[usize]) -> usize {
+        match self.splits.get(&innovation) {
+            Some(&node) if !hidden.contains(&node) => node,
+            _ => {
+                let node = self.next_node;
+                self.next_node += 1;
+                self.splits.insert(innovation, node);
+                node
+            }
+        }
+    }
+}
+
+/// The encoding of a network: its hidden nodes and its connections, sorted
+/// by innovation number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkGenome {
+    inputs: usize,
+    outputs: usize,
+    hidden: Vec<usize>,
+    connections: Vec<ConnectionGene>,
+}
+
+impl NetworkGenome {
+    /// Every input and the bias connected to every output, with random weights.
+    fn minimal<R: Rng + ?Sized>(
+        inputs: usize,
+        outputs: usize,
+        innovations: &mut Innovations,
+        rng: &mut R,
+    ) -> Self {
+        let mut connections = Vec::with_capacity((inputs + 1) * outputs);
+        for from in 0..=inputs {
+            for to in inputs + 1..inputs + 1 + outputs {
+                connections.push(ConnectionGene {
+                    innovation: innovations.connection(from, to),
+                    from,
+                    to,
+                    weight: rng.random_range(-1.0..=1.0),
+                    enabled: true,
+                });
+            }
+        }
+        connections.sort_by_key(|c| c.innovation);
+        Self {
+            inputs,
+            outputs,
+            hidden: Vec::new(),
+            connections,
+        }
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    /// The ids of the hidden nodes.
+    pub fn hidden(&self) -> &[usize] {
+        &self.hidden
+    }
+
+    /// The connections, sorted by innovation number.
+    pub fn connections(&self) -> &[ConnectionGene] {
+        &self.connections
+    }
+
+    pub fn network(&self) -> Network {
+        Network::new(self.inputs, self.outputs, &self.connections)
+    }
+
+    /// The compatibility distance `c1 * E / N + c2 * D / N + c3 * W`: `E`
+    /// and `D` count the excess and disjoint connections, `W` is the mean
+    /// weight difference of the matching ones and `N` the number of
+    /// connections of the larger genome, or 1 for genomes below 20.
+    pub fn distance(&self, other: &NetworkGenome, coefficients: [f64; 3]) -> f64 {
+        let (a, b) = (&self.connections, &other.connections);
+        let (mut i, mut j) = (0, 0);
+        let (mut disjoint, mut matching, mut weight_difference) = (0, 0, 0.0);
+        while i < a.len() && j < b.len() {
+            match a[i].innovation.cmp(&b[j].innovation) {
+                std::cmp::Ordering::Equal => {
+                    weight_difference += (a[i].weight - b[j].weight).abs();
+                    matching += 1;
+                    i += 1;
+                    j += 1;
+                }
+                std::cmp::Ordering::Less => {
+                    disjoint += 1;
+                    i += 1;
+                }
+                std::cmp::Ordering::Greater => {
+                    disjoint += 1;
+                    j += 1;
+                }
+            }
+        }
+        let excess = (a.len() - i) + (b.len() - j);
+        let n = match a.len().max(b.len()) {
+            n if n < 20 => 1.0,
+            n => n as f64,
+        };
+        let mean_difference = if matching > 0 {
+            weight_difference / matching as f64
+        } else {
+            0.0
+        };
+        let [c1, c2, c3] = coefficients;
+        c1 * excess as f64 / n + c2 * disjoint as f64 / n + c3 * mean_difference
+    }
+
+    /// Crossover: matching connections are inherited from a random parent,
+    /// the disjoint and excess ones from `fitter`. A connection disabled in
+    /// either parent stays disabled with probability 0.75.
+    fn crossover<R: Rng + ?Sized>(
+        fitter: &NetworkGenome,
+        other: &NetworkGenome,
+        rng: &mut R,
+    ) -> NetworkGenome {
+        let mut child = fitter.clone();
+        let mut j = 0;
+        for gene in child.connections.iter_mut() {
+            while j < other.connections.len() && other.connections[j].innovation < gene.innovation {
+                j += 1;
+            }
+            let Some(matching) = other
+                .connections
+                .get(j)
+                .filter(|m| m.innovation == gene.innovation)
+            else {
+                continue;
+            };
+            let disabled = !gene.enabled || !matching.enabled;
+            if rng.random_bool(0.5) {
+                gene.weight = matching.weight;
+            }
+            gene.enabled = !(disabled && rng.random_bool(0.75));
+        }
+        child
+    }
+
+    /// Perturbs every weight by a normal step of deviation `power`, or with
+    /// probability 0.1 replaces it by a new random weight.
+    fn mutate_weights<R: Rng + ?Sized>(&mut self, power: f64, rng: &mut R) {
+        for gene in self.connections.iter_mut() {
+            if rng.random_bool(0.1) {
+                gene.weight = rng.random_range(-1.0..=1.0);
+            } else {
+                gene.weight += power * standard_normal(rng);
+            }
+        }
+    }
+
+    /// Connects two unconnected nodes, unless the connection would close a
+    /// cycle. Gives up after a few attempts in a densely connected genome.
+    fn add_connection<R: Rng + ?Sized>(&mut self, innovations: &mut Innovations, rng: &mut R) {
+        let fixed = self.inputs + 1 + self.outputs;
+        let sources = fixed + self.hidden.len();
+        let targets = self.outputs + self.hidden.len();
+        let node = |index: usize| {
+            if index < fixed {
+                index
+            } else {
+                self.hidden[index - fixed]
+            }
+        };
+
+        for _ in 0..20 {
+            let from = node(rng.random_range(0..sources));
+            let to = node(self.inputs + 1 + rng.random_range(0..targets));
+            let exists = self
+                .connections
+                .iter()
+                .any(|c| c.from == from && c.to == to);
+            if from == to || exists || self.reaches(to, from) {
+                continue;
+            }
+            let gene = ConnectionGene {
+                innovation: innovations.connection(from, to),
+                from,
+                to,
+                weight: rng.random_range(-1.0..=1.0),
+                enabled: true,
+            };
+            self.insert(gene);
+            return;
+        }
+    }
+
+    /// Splits a random enabled connection: it is disabled and replaced by a
+    /// new hidden node, connected with weight 1 on the way in and the old
+    /// weight on the way out. The signal only passes the activation of the
+    /// new node on its way, so with a linear activation the outputs stay
+    /// the same.
+    fn add_node<R: Rng + ?Sized>(&mut self, innovations: &mut Innovations, rng: &mut R) {
+        let enabled: Vec<usize> = (0..self.connections.len())
+            .filter(|&i| self.connections[i].enabled)
+            .collect();
+        let Some(&split) = enabled.choose(rng) else {
+            return;
+        };
+        self.connections[split].enabled = false;
+        let ConnectionGene {
+            innovation,
+            from,
+            to,
+            weight,
+            ..
+        } = self.connections[split];
+
+        let node = innovations.split(innovation, &self.hidden);
+        self.hidden.push(node);
+        for (from, to, weight) in [(from, node, 1.0), (node, to, weight)] {
+            let gene = ConnectionGene {
+                innovation: innovations.connection(from, to),
+                from,
+                to,
+                weight,
+                enabled: true,
+            };
+            self.insert(gene);
+        }
+    }
+
+    fn insert(&mut self, gene: ConnectionGene) {
+        let at = self
+            .connections
+            .partition_point(|c| c.innovation < gene.innovation);
+        self.connections.insert(at, gene);
+    }
+
+    /// Whether a path of connections leads from `from` to `to`.
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![from];
+        while let Some(node) = pending.pop() {
+            if node == to {
+                return true;
+            }
+            if visited.insert(node) {
+                pending.extend(
+                    self.connections
+                        .iter()
+                        .filter(|c| c.from == node)
+                        .map(|c| c.to),
+                );
+            }
+        }
+        false
+    }
+}
+
+/// The steepened sigmoid of the original NEAT, `1 / (1 + e^(-4.9 x))`.
+pub fn steepened_sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-4.9 * x).exp())
+}
+
+/// A feed-forward network decoded from a [`NetworkGenome`].
+#[derive(Debug, Clone)]
+pub struct Network {
+    inputs: usize,
+    outputs: usize,
+    /// The nodes to compute, in topological order, with their incoming
+    /// connections as (node index, weight).
+    order: Vec<(usize, Vec<(usize, f64)>)>,
+    values: Vec<f64>,
+    output: Vec<f64>,
+    activation: fn(f64) -> f64,
+}
+
+impl Network {
+    /// Builds the network of the enabled `connections`, e.g. the
+    /// `best_genotype` of a [`RunReport`].
+    pub fn new(inputs: usize, outputs: usize, connections: &[ConnectionGene]) -> Self {
+        let fixed = inputs + 1 + outputs;
+        let mut index: HashMap<usize, usize> = (0..fixed).map(|id| (id, id)).collect();
+        let enabled: Vec<&ConnectionGene> = connections.iter().filter(|c| c.enabled).collect();
+        for c in &enabled {
+            for id in [c.from, c.to] {
+                let next = index.len();
+                index.entry(id).or_insert(next);
+            }
+        }
+
+        let nodes = index.len();
+        let mut incoming: Vec<Vec<(usize, f64)>> = vec![Vec::new(); nodes];
+        let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); nodes];
+        let mut missing = vec![0; nodes];
+        for c in &enabled {
+            let (from, to) = (index[&c.from], index[&c.to]);
+            incoming[to].push((from, c.weight));
+            outgoing[from].push(to);
+            missing[to] += 1;
+        }
+
+        // Kahn's algorithm, starting from every node without inputs.
+        let mut ready: VecDeque<usize> = (0..nodes).filter(|&n| missing[n] == 0).collect();
+        let mut order = Vec::with_capacity(nodes);
+        while let Some(node) = ready.pop_front() {
+            if node > inputs {
+                order.push((node, std::mem::take(&mut incoming[node])));
+            }
+            for &next in &outgoing[node] {
+                missing[next] -= 1;
+                if missing[next] == 0 {
+                    ready.push_back(next);
+                }
+            }
+        }
+
+        Self {
+            inputs,
+            outputs,
+            order,
+            values: vec![0.0; nodes],
+            output: vec![0.0; outputs],
+            activation: steepened_sigmoid,
+        }
+    }
+
+    /// Replaces the activation function of the hidden and output nodes.
+    /// Defaults to [`steepened_sigmoid`].
+    pub fn with_activation(mut self, activation: fn(f64) -> f64) -> Self {
+        self.activation = activation;
+        self
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    /// Feeds `inputs` through the network and returns the outputs.
+    ///
+    /// # Panics
+    /// Panics if the number of inputs does not match the network.
+    pub fn activate(&mut self, inputs: &[f64]) -> &[f64] {
+        assert_eq!(inputs.len(), self.inputs, "wrong number of inputs");
+        self.values[..self.inputs].copy_from_slice(inputs);
+        self.values[self.inputs] = 1.0;
+        for (node, incoming) in &self.order {
+            let sum: f64 = incoming
+                .iter()
+                .map(|&(from, weight)| self.values[from] * weight)
+                .sum();
+            self.values[*node] = (self.activation)(sum);
+        }
+        let start = self.inputs + 1;
+        self.output
+            .copy_from_slice(&self.values[start..start + self.outputs]);
+        &self.output
+    }
+}
+
+/// Evaluates the network of one individual of a [`Neat`] population, the
+/// counterpart of [`Evaluator`](crate::Evaluator) for networks.
+pub trait NetworkEvaluator {
+    /// The type of the parameter needed for fitness calculation.
+    type FitnessParam;
+
+    fn new(individual: Individual) -> Self;
+
+    /// Evaluates the controller, e.g. by activating the network with the
+    /// sensor readings of every step of a simulation. The fitness is
+    /// returned by [`NetworkEvaluator::fitness`].
+    fn evaluate(&mut self, network: &mut Network, param: &Self::FitnessParam);
+
+    fn fitness(&self) -> f64;
+}
+
+/// A group of genomes with similar topologies.
+#[derive(Debug, Clone)]
+pub struct Species {
+    id: usize,
+    /// The genome new genomes are compared with.
+    representative: NetworkGenome,
+    members: Vec<usize>,
+    best_fitness: f64,
+    stagnation: usize,
+}
+
+impl Species {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The indices of the members in the most recently evaluated generation.
+    pub fn members(&self) -> &[usize] {
+        &self.members
+    }
+
+    /// The highest fitness any member has ever reached.
+    pub fn best_fitness(&self) -> f64 {
+        self.best_fitness
+    }
+
+    /// The number of generations since the best fitness last improved.
+    pub fn stagnation(&self) -> usize {
+        self.stagnation
+    }
+}
+
+/// A NEAT population.
+#[derive(Debug)]
+pub struct Neat<E: NetworkEvaluator, R: Rng = ChaCha8Rng> {
+    genomes: Vec<NetworkGenome>,
+    next_genomes: Vec<NetworkGenome>,
+    phenotypes: Vec<E>,
+    innovations: Innovations,
+    species: Vec<Species>,
+    next_species_id: usize,
+    rng: R,
+    coefficients: [f64; 3],
+    compatibility_threshold: f64,
+    max_stagnation: usize,
+    survival_rate: f64,
+    weight_mutation_rate: f64,
+    weight_mutation_power: f64,
+    add_connection_rate: f64,
+    add_node_rate: f64,
+    interspecies_rate: f64,
+    fitness: Vec<f64>,
+    evaluations: usize,
+    generation: usize,
+    history: Vec<Statistics>,
+    observers: Observers,
+}
+
+impl<E: NetworkEvaluator, R: Rng + SeedableRng> Neat<E, R> {
+    /// Creates a population seeded from the thread-local entropy source.
+    pub fn new(population_size: usize, inputs: usize, outputs: usize) -> Self {
+        Self::with_rng(
+            population_size,
+            inputs,
+            outputs,
+            R::from_rng(&mut rand::rng()),
+        )
+    }
+
+    /// Creates a population whose whole run is determined by `seed`.
+    pub fn with_seed(population_size: usize, inputs: usize, outputs: usize, seed: u64) -> Self {
+        Self::with_rng(population_size, inputs, outputs, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: NetworkEvaluator, R: Rng> Neat<E, R> {
+    /// Creates a population of minimal networks with `inputs` sensors and
+    /// `outputs` actions, driven by the given random number generator.
+    pub fn with_rng(population_size: usize, inputs: usize, outputs: usize, mut rng: R) -> Self {
+        let mut innovations = Innovations {
+            next_node: inputs + 1 + outputs,
+            ..Innovations::default()
+        };
+        let genomes: Vec<NetworkGenome> = (0..population_size)
+            .map(|_| NetworkGenome::minimal(inputs, outputs, &mut innovations, &mut rng))
+            .collect();
+
+        Self {
+            next_genomes: genomes.clone(),
+            genomes,
+            phenotypes: (0..population_size)
+                .map(|i| E::new(Individual(i)))
+                .collect(),
+            innovations,
+            species: Vec::new(),
+            next_species_id: 0,
+            rng,
+            coefficients: [1.0, 1.0, 0.4],
+            compatibility_threshold: 3.0,
+            max_stagnation: 15,
+            survival_rate: 0.2,
+            weight_mutation_rate: 0.8,
+            weight_mutation_power: 0.5,
+            add_connection_rate: 0.05,
+            add_node_rate: 0.03,
+            interspecies_rate: 0.001,
+            fitness: vec![0.0; population_size],
+            evaluations: 0,
+            generation: 0,
+            history: Vec::new(),
+            observers: Observers::default(),
+        }
+    }
+
+    /// Sets the coefficients of the excess connections, the disjoint
+    /// connections and the weight differences in the compatibility distance,
+    /// see [`NetworkGenome::distance`]. Defaults to 1, 1 and 0.4.
+    pub fn with_compatibility_coefficients(
+        mut self,
+        excess: f64,
+        disjoint: f64,
+        weight: f64,
+    ) -> Self {
+        self.coefficients = [excess, disjoint, weight];
+        self
+    }
+
+    /// Sets the largest compatibility distance of a genome to the
+    /// representative of its species. Defaults to 3.
+    pub fn with_compatibility_threshold(mut self, threshold: f64) -> Self {
+        self.compatibility_threshold = threshold;
+        self
+    }
+
+    /// Sets the number of generations without improvement after which a
+    /// species is dropped. The species of the fittest genome is always kept.
+    /// Defaults to 15.
+    pub fn with_max_stagnation(mut self, generations: usize) -> Self {
+        self.max_stagnation = generations;
+        self
+    }
+
+    /// Sets the fraction of the fittest members of every species that may
+    /// become parents. Defaults to 0.2.
+    pub fn with_survival_rate(mut self, rate: f64) -> Self {
+        self.survival_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the probability of a child having its weights perturbed by
+    /// normal steps of deviation `power`. Defaults to 0.8 and 0.5.
+    pub fn with_weight_mutation(mut self, rate: f64, power: f64) -> Self {
+        self.weight_mutation_rate = mutation::probability(rate);
+        self.weight_mutation_power = power;
+        self
+    }
+
+    /// Sets the probability of a child gaining a connection. Defaults to 0.05.
+    pub fn with_add_connection_rate(mut self, rate: f64) -> Self {
+        self.add_connection_rate = mutation::probability(rate);
+        self
+    }
+
+    /// Sets the probability of a child gaining a hidden node. Defaults to 0.03.
+    pub fn with_add_node_rate(mut self, rate: f64) -> Self {
+        self.add_node_rate = mutation::probability(rate);
+        self
+    }
+
+    /// Sets the probability of a parent being mated with a member of another
+    /// species. Defaults to 0.001.
+    pub fn with_interspecies_rate(mut self, rate: f64) -> Self {
+        self.interspecies_rate = mutation::probability(rate);
+        self
+    }
+
+    /// Registers an observer that is notified after every evaluated generation.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    pub fn evolve(&mut self, param: &E::FitnessParam) {
+        // 1. Divide the current generation into species and evaluate it.
+        self.speciate();
+        self.calculate_fitness(param);
+        self.record_statistics();
+
+        // 2. Breed the next generation within the species.
+        self.reproduce();
+
+        // 3. Swap. The new generation is now the current one.
+        std::mem::swap(&mut self.genomes, &mut self.next_genomes);
+    }
+
+    /// Evolves the population until `termination` is satisfied.
+    ///
+    /// The `best_genotype` of the report holds the connections of the best
+    /// genome, see [`Network::new`].
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<ConnectionGene> {
+        termination::drive(
+            self,
+            |neat| neat.evolve(param),
+            |neat| &neat.history,
+            // after `evolve` the evaluated generation lives in `next_genomes`
+            |neat, index| neat.next_genomes[index].connections(),
+            termination,
+        )
+    }
+
+    /// Assigns every genome to the first species whose representative is
+    /// compatible, or founds a new species. Empty species are removed.
+    fn speciate(&mut self) {
+        for species in self.species.iter_mut() {
+            species.members.clear();
+        }
+        for (index, genome) in self.genomes.iter().enumerate() {
+            let compatible = self.species.iter_mut().find(|s| {
+                s.representative.distance(genome, self.coefficients) <= self.compatibility_threshold
+            });
+            match compatible {
+                Some(species) => species.members.push(index),
+                None => {
+                    self.species.push(Species {
+                        id: self.next_species_id,
+                        representative: genome.clone(),
+                        members: vec![index],
+                        best_fitness: f64::NEG_INFINITY,
+                        stagnation: 0,
+                    });
+                    self.next_species_id += 1;
+                }
+            }
+        }
+        self.species.retain(|s| !s.members.is_empty());
+    }
+
+    fn calculate_fitness(&mut self, param: &E::FitnessParam) {
+        for (p, genome) in self.phenotypes.iter_mut().zip(&self.genomes) {
+            p.evaluate(&mut genome.network(), param);
+        }
+        self.evaluations += self.phenotypes.len();
+        self.fitness.clear();
+        self.fitness
+            .extend(self.phenotypes.iter().map(|p| p.fitness()));
+        fitness::sanitize(&mut self.fitness);
+
+        for species in self.species.iter_mut() {
+            let best = species
+                .members
+                .iter()
+                .map(|&m| self.fitness[m])
+                .fold(f64::NEG_INFINITY, f64::max);
+            if best > species.best_fitness {
+                species.best_fitness = best;
+                species.stagnation = 0;
+            } else {
+                species.stagnation += 1;
+            }
+        }
+    }
+
+    /// Summarises the evaluated generation. The diversity is the fraction of
+    /// genomes outside the species of the elite.
+    fn record_statistics(&mut self) {
+        let mut statistics = Statistics::from_fitness(self.generation, &self.fitness);
+        if let Some(species) = self
+            .species
+            .iter()
+            .find(|s| s.members.contains(&statistics.elite_index))
+        {
+            let outside = self.genomes.len() - species.members.len();
+            statistics.diversity = outside as f64 / self.genomes.len() as f64;
+        }
+        statistics.evaluations = self.evaluations;
+        self.observers.notify(&statistics);
+        self.history.push(statistics);
+        self.generation += 1;
+    }
+
+    /// Drops stagnant species, shares out the offspring and breeds them.
+    fn reproduce(&mut self) {
+        let population_size = self.genomes.len();
+        if population_size == 0 {
+            return;
+        }
+        let Some(elite) = fitness::argmax(self.fitness.iter().copied()) else {
+            return;
+        };
+        self.species
+            .retain(|s| s.stagnation < self.max_stagnation || s.members.contains(&elite));
+
+        for species in self.species.iter_mut() {
+            let fitness = &self.fitness;
+            species
+                .members
+                .sort_by(|&a, &b| fitness::compare(fitness[b], fitness[a]));
+            // the next generation is compared with a random member
+            let representative = species.members[self.rng.random_range(0..species.members.len())];
+            species.representative = self.genomes[representative].clone();
+        }
+
+        let offspring = self.share_offspring(population_size);
+        self.next_genomes.clear();
+        for (s, &count) in offspring.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let (size, champion) = (self.species[s].members.len(), self.species[s].members[0]);
+            // the champion of a species of at least 5 survives unchanged
+            let mut remaining = count;
+            if size >= 5 {
+                self.next_genomes.push(self.genomes[champion].clone());
+                remaining -= 1;
+            }
+            let parents = ((size as f64 * self.survival_rate).ceil() as usize).clamp(1, size);
+            for _ in 0..remaining {
+                let child = self.breed(s, parents);
+                self.next_genomes.push(child);
+            }
+        }
+    }
+
+    /// The number of offspring of every species, in proportion to the sum of
+    /// its shared fitness, rounded by largest remainder so that they add up
+    /// to `total`. The fitness is shifted to be positive before sharing.
+    fn share_offspring(&self, total: usize) -> Vec<usize> {
+        let min = self.fitness.iter().copied().fold(f64::INFINITY, f64::min);
+        let shared: Vec<f64> = self
+            .species
+            .iter()
+            .map(|s| {
+                let sum: f64 = s.members.iter().map(|&m| self.fitness[m] - min).sum();
+                sum / s.members.len() as f64
+            })
+            .collect();
+        let sum: f64 = shared.iter().sum();
+        let quotas: Vec<f64> = if sum > 0.0 {
+            shared.iter().map(|s| s / sum * total as f64).collect()
+        } else {
+            // all equally fit: keep the sizes of the species
+            let members: usize = self.species.iter().map(|s| s.members.len()).sum();
+            self.species
+                .iter()
+                .map(|s| s.members.len() as f64 / members as f64 * total as f64)
+                .collect()
+        };
+
+        let mut offspring: Vec<usize> = quotas.iter().map(|q| q.floor() as usize).collect();
+        let mut by_remainder: Vec<usize> = (0..quotas.len()).collect();
+        by_remainder.sort_by(|&a, &b| {
+            fitness::compare(quotas[b] - quotas[b].floor(), quotas[a] - quotas[a].floor())
+        });
+        let missing = total - offspring.iter().sum::<usize>();
+        for &s in by_remainder.iter().cycle().take(missing) {
+            offspring[s] += 1;
+        }
+        offspring
+    }
+
+    /// A child of the species `s`, bred from its `parents` fittest members.
+    fn breed(&mut self, s: usize, parents: usize) -> NetworkGenome {
+        let members = &self.species[s].members;
+        let first = members[self.rng.random_range(0..parents)];
+        let mut child = if self.rng.random_bool(0.25) {
+            self.genomes[first].clone()
+        } else {
+            let second = if self.species.len() > 1 && self.rng.random_bool(self.interspecies_rate) {
+                let other = &self.species[self.rng.random_range(0..self.species.len())];
+                other.members[0]
+            } else {
+                members[self.rng.random_range(0..parents)]
+            };
+            let (fitter, other) =
+                if fitness::compare(self.fitness[second], self.fitness[first]).is_gt() {
+                    (second, first)
+                } else {
+                    (first, second)
+                };
+            NetworkGenome::crossover(&self.genomes[fitter], &self.genomes[other], &mut self.rng)
+        };
+
+        if self.rng.random_bool(self.add_node_rate) {
+            child.add_node(&mut self.innovations, &mut self.rng);
+        }
+        if self.rng.random_bool(self.add_connection_rate) {
+            child.add_connection(&mut self.innovations, &mut self.rng);
+        }
+        if self.rng.random_bool(self.weight_mutation_rate) {
+            child.mutate_weights(self.weight_mutation_power, &mut self.rng);
+        }
+        child
+    }
+
+    /// The phenotype with the highest fitness according to [`fitness::compare`].
+    pub fn fittest_phenotype(&self) -> Option<&E> {
+        fitness::argmax(self.phenotypes.iter().map(|p| p.fitness()))
+            .map(|index| &self.phenotypes[index])
+    }
+
+    /// The species of the most recently evaluated generation.
+    pub fn species(&self) -> &[Species] {
+        &self.species
+    }
+
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The statistics of every evaluated generation, oldest first.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
+    /// The genomes of the current generation.
+    pub fn genomes(&self) -> &[NetworkGenome] {
+        &self.genomes
+    }
+
+    pub fn get_phenotypes(&self) -> &[E] {
+        &self.phenotypes
+    }
+    pub fn get_phenotypes_mut(&mut self) -> &mut [E] {
+        &mut self.phenotypes
+    }
+
+    /// The genome of the given phenotype.
+    ///
+    /// The phenotype is found by its address, so it must be borrowed from
+    /// this population, e.g. from [`Neat::get_phenotypes`].
+    ///
+    /// # Panics
+    /// Panics if the phenotype does not belong to this population.
+    pub fn get_genome(&self, phenotype: &E) -> &NetworkGenome {
+        &self.genomes[crate::slot_of(&self.phenotypes, phenotype)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::termination::{MaxGenerations, TargetFitness};
+
+    /// A genome of the given connections, as (innovation, weight).
+    fn genome(connections: &[(usize, f64)]) -> NetworkGenome {
+        NetworkGenome {
+            inputs: 1,
+            outputs: 1,
+            hidden: Vec::new(),
+            connections: connections
+                .iter()
+                .map(|&(innovation, weight)| ConnectionGene {
+                    innovation,
+                    from: 0,
+                    to: 2,
+                    weight,
+                    enabled: true,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn distance_aligns_connections_by_innovation() {
+        let a = genome(&[(1, 0.5), (2, 1.0), (3, 0.0), (5, 0.0)]);
+        let b = genome(&[(1, 0.0), (2, 2.0), (4, 0.0), (6, 0.0), (7, 0.0)]);
+        // 1 and 2 match, 3, 4 and 5 are disjoint, 6 and 7 are excess
+        assert_eq!(a.distance(&b, [1.0, 0.0, 0.0]), 2.0);
+        assert_eq!(a.distance(&b, [0.0, 1.0, 0.0]), 3.0);
+        assert_eq!(a.distance(&b, [0.0, 0.0, 1.0]), 0.75);
+        assert_eq!(b.distance(&a, [1.0, 10.0, 100.0]), 107.0);
+        assert_eq!(a.distance(&a, [1.0, 1.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn add_node_keeps_the_outputs_of_a_linear_network() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let mut innovations = Innovations {
+            next_node: 3 + 1 + 2,
+            ..Innovations::default()
+        };
+        let mut genome = NetworkGenome::minimal(3, 2, &mut innovations, &mut rng);
+        let inputs = [0.3, -1.2, 2.0];
+        let before = genome
+            .network()
+            .with_activation(|x| x)
+            .activate(&inputs)
+            .to_vec();
+        for _ in 0..5 {
+            genome.add_node(&mut innovations, &mut rng);
+            let after = genome
+                .network()
+                .with_activation(|x| x)
+                .activate(&inputs)
+                .to_vec();
+            for (b, a) in before.iter().zip(&after) {
+                assert!((b - a).abs() < 1e-12, "{before:?} became {after:?}");
+            }
+        }
+        assert_eq!(genome.hidden().len(), 5);
+    }
+
+    struct Xor {
+        fitness: f64,
+    }
+
+    impl NetworkEvaluator for Xor {
+        type FitnessParam = ();
+
+        fn new(_: Individual) -> Self {
+            Xor { fitness: 0.0 }
+        }
+
+        fn evaluate(&mut self, network: &mut Network, _: &()) {
+            let error: f64 = [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0)]
+                .iter()
+                .map(|&(a, b)| (network.activate(&[a, b])[0] - xor(a, b)).abs())
+                .sum();
+            self.fitness = (4.0 - error).powi(2);
+        }
+
+        fn fitness(&self) -> f64 {
+            self.fitness
+        }
+    }
+
+    fn xor(a: f64, b: f64) -> f64 {
+        if a != b { 1.0 } else { 0.0 }
+    }
+
+    #[test]
+    fn offspring_add_up_to_the_population_size() {
+        let mut neat: Neat<Xor> = Neat::with_seed(0, 2, 1, 0);
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        for total in [1, 7, 100, 150] {
+            for _ in 0..50 {
+                let size = rng.random_range(1..40);
+                neat.fitness = (0..size)
+                    .map(|_| match rng.random_range(0..3) {
+                        // ties, including an evenly fit generation
+                        0 => 1.0,
+                        _ => rng.random_range(-10.0..10.0),
+                    })
+                    .collect();
+                let mut members: Vec<usize> = (0..size).collect();
+                members.shuffle(&mut rng);
+                neat.species = members
+                    .chunks(rng.random_range(1..=size))
+                    .enumerate()
+                    .map(|(id, members)| Species {
+                        id,
+                        representative: genome(&[]),
+                        members: members.to_vec(),
+                        best_fitness: f64::NEG_INFINITY,
+                        stagnation: 0,
+                    })
+                    .collect();
+                let offspring = neat.share_offspring(total);
+                assert_eq!(offspring.len(), neat.species.len());
+                assert_eq!(offspring.iter().sum::<usize>(), total, "{:?}", neat.fitness);
+            }
+        }
+    }
+
+    #[test]
+    fn solves_xor() {
+        let mut neat: Neat<Xor> = Neat::with_seed(150, 2, 1, 14);
+        let report = neat.run(&(), MaxGenerations::new(300).or(TargetFitness(15.5)));
+        let mut network = Network::new(2, 1, &report.best_genotype);
+        for (a, b) in [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0), (1.0, 1.0)] {
+            let output = network.activate(&[a, b])[0];
+            assert_eq!(output > 0.5, xor(a, b) > 0.5, "{a} xor {b} gave {output}");
+        }
+        assert!(neat.genomes().iter().any(|g| !g.hidden().is_empty()));
+    }
+}
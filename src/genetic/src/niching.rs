@@ -0,0 +1,365 @@
+//! # Niching
+//!
+//! Without countermeasures a population tends to collapse into clones of its
+//! elite. A [`Niching`] strategy set with `Population::with_niching` keeps
+//! several niches of the search space populated instead, without raising
+//! the mutation rate. All strategies measure how similar two individuals are
+//! with `Genome::distance`.
+
+use rand::prelude::*;
+
+use crate::{Evaluator, Gene, Genome, Population, fitness};
+
+/// A diversity preserving strategy, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Niching {
+    /// Fitness sharing (Goldberg & Richardson).
+    ///
+    /// For parent selection, the fitness of every individual is divided by
+    /// its niche count `Σ sh(d)` over all individuals, where
+    /// `sh(d) = 1 - (d / radius)^alpha` for distances below `radius` and 0
+    /// otherwise. Crowded niches therefore get fewer parents. Negative
+    /// fitness values are shifted so that the worst individual has 0. Elites
+    /// are still chosen by their unshared fitness.
+    Sharing { radius: f64, alpha: f64 },
+    /// Deterministic crowding (Mahfoud).
+    ///
+    /// Parents are paired at random instead of being selected. Every child
+    /// competes with the more similar of its two parents and only replaces
+    /// it if it is at least as fit. As the fitness of a child is known only
+    /// after the next evaluation, the losing children are swapped back for
+    /// their parents right after it. The phenotypes of such slots still
+    /// describe the child, while the fitness and genotype of the slot are the
+    /// parent's.
+    DeterministicCrowding,
+    /// Speciation with offspring quotas.
+    ///
+    /// Every individual joins the first species whose representative is
+    /// closer than `threshold`, or founds a new one. Each species gets a
+    /// share of the offspring proportional to its mean fitness, and the
+    /// parents are selected within the species. The fittest member of a
+    /// species represents it in the next generation.
+    Speciation { threshold: f64 },
+}
+
+/// The state kept by the niching strategies between generations.
+#[derive(Debug)]
+pub(crate) struct Niches<G> {
+    /// For every slot of the current generation the slot of the previous
+    /// generation its genotype competes with.
+    pub(crate) rivals: Vec<Option<usize>>,
+    /// The fitness of the previous generation.
+    pub(crate) previous_fitness: Vec<f64>,
+    /// The genotype representing every species.
+    representatives: Vec<Vec<G>>,
+    /// The members of every species of the last evaluated generation.
+    species: Vec<Vec<usize>>,
+}
+
+impl<G> Default for Niches<G> {
+    fn default() -> Self {
+        Self {
+            rivals: Vec::new(),
+            previous_fitness: Vec::new(),
+            representatives: Vec::new(),
+            species: Vec::new(),
+        }
+    }
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// Sets the diversity preserving strategy. Defaults to none.
+    pub fn with_niching(mut self, niching: Niching) -> Self {
+        self.niching = Some(niching);
+        self
+    }
+
+    /// The members of every species of the generation evaluated last, empty
+    /// unless [`Niching::Speciation`] is used.
+    pub fn species(&self) -> &[Vec<usize>] {
+        &self.niches.species
+    }
+
+    fn genotype_at<'a>(&self, arena: &'a [Gene<E>], index: usize) -> &'a [Gene<E>] {
+        let start = index * self.genotype_size;
+        &arena[start..start + self.genotype_size]
+    }
+
+    /// Puts back the parents that beat the child competing with them, for
+    /// deterministic crowding and steady-state replacement of parents.
+    /// Called right after the fitness of a generation has been collected.
+    pub(crate) fn replace_by_rivals(&mut self) {
+        let genotype_size = self.genotype_size;
+        for (slot, rival) in self.niches.rivals.iter().enumerate() {
+            let Some(rival) = *rival else {
+                continue;
+            };
+            let rival_fitness = self.niches.previous_fitness[rival];
+            if fitness::compare(rival_fitness, self.fitness[slot]).is_gt() {
+                // the previous generation is still kept in `next_gen_arena`
+                let (start, rival_start) = (slot * genotype_size, rival * genotype_size);
+                self.genotype_arena[start..start + genotype_size].copy_from_slice(
+                    &self.next_gen_arena[rival_start..rival_start + genotype_size],
+                );
+                self.fitness[slot] = rival_fitness;
+            }
+        }
+        self.niches.rivals.clear();
+    }
+
+    /// Fills `self.parents` according to the niching strategy. Returns
+    /// `false` if the strategy leaves parent selection to `self.selection`.
+    pub(crate) fn select_niche_parents(&mut self) -> bool {
+        match self.niching {
+            None => false,
+            Some(Niching::Sharing { radius, alpha }) => {
+                let shared = self.shared_fitness(radius, alpha);
+                self.selection
+                    .select(&shared, &mut self.parents, &mut self.rng);
+                true
+            }
+            Some(Niching::DeterministicCrowding) => {
+                let n = self.phenotypes.len();
+                let mut order: Vec<usize> = (0..n).collect();
+                for parents in self.parents.chunks_mut(n.max(1)) {
+                    order.shuffle(&mut self.rng);
+                    parents.copy_from_slice(&order[..parents.len()]);
+                }
+                true
+            }
+            Some(Niching::Speciation { threshold }) => {
+                self.speciate(threshold);
+                self.select_within_species();
+                true
+            }
+        }
+    }
+
+    fn shared_fitness(&self, radius: f64, alpha: f64) -> Vec<f64> {
+        let min = self
+            .fitness
+            .iter()
+            .copied()
+            .fold(f64::INFINITY, f64::min)
+            .min(0.0);
+
+        (0..self.phenotypes.len())
+            .map(|i| {
+                let genotype = self.genotype_at(&self.genotype_arena, i);
+                let niche_count: f64 = (0..self.phenotypes.len())
+                    .map(|j| {
+                        let distance = E::Genome::distance(
+                            genotype,
+                            self.genotype_at(&self.genotype_arena, j),
+                        );
+                        if distance < radius {
+                            1.0 - (distance / radius).powf(alpha)
+                        } else {
+                            0.0
+                        }
+                    })
+                    .sum();
+                // an individual is at distance 0 from itself, so the niche
+                // count is at least 1 unless the radius is 0
+                (self.fitness[i] - min) / niche_count.max(1.0)
+            })
+            .collect()
+    }
+
+    fn speciate(&mut self, threshold: f64) {
+        let mut species: Vec<Vec<usize>> = vec![Vec::new(); self.niches.representatives.len()];
+        for i in 0..self.phenotypes.len() {
+            let genotype = self.genotype_at(&self.genotype_arena, i);
+            match self
+                .niches
+                .representatives
+                .iter()
+                .position(|r| E::Genome::distance(r, genotype) < threshold)
+            {
+                Some(s) => species[s].push(i),
+                None => {
+                    self.niches.representatives.push(genotype.to_vec());
+                    species.push(vec![i]);
+                }
+            }
+        }
+        species.retain(|members| !members.is_empty());
+
+        self.niches.representatives = species
+            .iter()
+            .map(|members| {
+                let best = members[fitness::argmax(members.iter().map(|&i| self.fitness[i]))
+                    .expect("species are not empty")];
+                self.genotype_at(&self.genotype_arena, best).to_vec()
+            })
+            .collect();
+        self.niches.species = species;
+    }
+
+    /// Hands every species a number of parent pairs proportional to its mean
+    /// fitness, and selects them among its members.
+    fn select_within_species(&mut self) {
+        let pairs = self.parents.len() / 2;
+        let species = &self.niches.species;
+        if species.is_empty() || pairs == 0 {
+            return;
+        }
+
+        let min = self.fitness.iter().copied().fold(f64::INFINITY, f64::min);
+        let mut weights: Vec<f64> = species
+            .iter()
+            .map(|members| {
+                members.iter().map(|&i| self.fitness[i] - min).sum::<f64>() / members.len() as f64
+            })
+            .collect();
+        if weights.iter().sum::<f64>() <= 0.0 {
+            weights.fill(1.0);
+        }
+        let quotas = apportion(&weights, pairs);
+
+        let mut offset = 0;
+        let mut species_fitness = Vec::new();
+        for (members, quota) in species.iter().zip(quotas) {
+            species_fitness.clear();
+            species_fitness.extend(members.iter().map(|&i| self.fitness[i]));
+
+            let parents = &mut self.parents[offset..offset + 2 * quota];
+            self.selection
+                .select(&species_fitness, parents, &mut self.rng);
+            for parent in parents.iter_mut() {
+                *parent = members[*parent];
+            }
+            offset += 2 * quota;
+        }
+    }
+
+    /// Deterministic crowding: matches every child just bred with the more
+    /// similar of its parents.
+    pub(crate) fn assign_rivals(&mut self) {
+        if self.niching != Some(Niching::DeterministicCrowding) {
+            return;
+        }
+
+        self.niches.previous_fitness.clone_from(&self.fitness);
+        self.niches.rivals.clear();
+        let survivors = self.survivors();
+        self.niches.rivals.resize(survivors, None);
+
+        let n = self.phenotypes.len();
+        for (c, pair) in self.parents.chunks_exact(2).enumerate() {
+            let first = survivors + 2 * c;
+            if first + 1 >= n {
+                self.niches
+                    .rivals
+                    .push(Some(self.closer_parent(first, pair)));
+                continue;
+            }
+
+            let distance = |child, parent| {
+                E::Genome::distance(
+                    self.genotype_at(&self.next_gen_arena, child),
+                    self.genotype_at(&self.genotype_arena, parent),
+                )
+            };
+            let straight = distance(first, pair[0]) + distance(first + 1, pair[1]);
+            let crossed = distance(first, pair[1]) + distance(first + 1, pair[0]);
+            if straight <= crossed {
+                self.niches.rivals.extend([Some(pair[0]), Some(pair[1])]);
+            } else {
+                self.niches.rivals.extend([Some(pair[1]), Some(pair[0])]);
+            }
+        }
+    }
+
+    fn closer_parent(&self, child: usize, pair: &[usize]) -> usize {
+        let child = self.genotype_at(&self.next_gen_arena, child);
+        let d0 = E::Genome::distance(child, self.genotype_at(&self.genotype_arena, pair[0]));
+        let d1 = E::Genome::distance(child, self.genotype_at(&self.genotype_arena, pair[1]));
+        if d0 <= d1 { pair[0] } else { pair[1] }
+    }
+}
+
+/// Splits `total` into integer shares proportional to `weights` with the
+/// largest remainder method.
+fn apportion(weights: &[f64], total: usize) -> Vec<usize> {
+    let sum: f64 = weights.iter().sum();
+    let exact: Vec<f64> = weights.iter().map(|w| w / sum * total as f64).collect();
+    let mut shares: Vec<usize> = exact.iter().map(|e| e.floor() as usize).collect();
+
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by(|&a, &b| fitness::compare(exact[b].fract(), exact[a].fract()));
+    let missing = total - shares.iter().sum::<usize>();
+    for &i in order.iter().cycle().take(missing) {
+        shares[i] += 1;
+    }
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::OneMax;
+
+    /// A population of `n` individuals, the first `ones` of which have all
+    /// genes set and the others none.
+    fn two_clusters(n: usize, ones: usize) -> Population<OneMax> {
+        let mut population = Population::<OneMax>::with_seed(n, 4, 3);
+        for (i, genotype) in population.genotype_arena.chunks_mut(4).enumerate() {
+            genotype.fill(i < ones);
+        }
+        population
+    }
+
+    #[test]
+    fn sharing_divides_the_fitness_among_the_niche() {
+        let mut population = two_clusters(4, 3);
+        population.fitness = vec![3.0, 3.0, 3.0, 2.0];
+        // three clones share their fitness, the loner keeps its own
+        assert_eq!(population.shared_fitness(0.5, 1.0), [1.0, 1.0, 1.0, 2.0]);
+
+        // negative fitness is shifted up to 0 first
+        population.fitness = vec![1.0, 1.0, 1.0, -1.0];
+        let shared = population.shared_fitness(0.5, 1.0);
+        assert!((shared[0] - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(shared[3], 0.0);
+    }
+
+    #[test]
+    fn deterministic_crowding_never_loses_fitness() {
+        let mut population = Population::<OneMax>::with_seed(20, 16, 4)
+            .with_elitism(0)
+            .with_niching(Niching::DeterministicCrowding);
+        for _ in 0..30 {
+            population.evolve(&());
+        }
+        // every individual is a parent once and is only replaced by a child
+        // that is at least as fit
+        for pair in population.history().windows(2) {
+            assert!(pair[1].mean_fitness >= pair[0].mean_fitness);
+            assert!(pair[1].max_fitness >= pair[0].max_fitness);
+        }
+    }
+
+    #[test]
+    fn speciation_breeds_the_fitter_species() {
+        let mut population =
+            two_clusters(6, 3).with_niching(Niching::Speciation { threshold: 0.5 });
+        population.fitness = vec![4.0, 4.0, 4.0, 0.0, 0.0, 0.0];
+        population.select_pairs(6);
+
+        assert_eq!(population.species(), [vec![0, 1, 2], vec![3, 4, 5]]);
+        assert_eq!(
+            population.niches.representatives,
+            [vec![true; 4], vec![false; 4]]
+        );
+        // the other species has a mean fitness of 0 and gets no offspring
+        assert!(population.parents.iter().all(|&parent| parent < 3));
+    }
+
+    #[test]
+    fn apportion_hands_out_the_whole_total() {
+        assert_eq!(apportion(&[1.0, 1.0, 2.0], 4), [1, 1, 2]);
+        assert_eq!(apportion(&[1.0, 1.0, 1.0], 4).iter().sum::<usize>(), 4);
+        assert_eq!(apportion(&[3.0, 0.0], 5), [5, 0]);
+    }
+}
@@ -0,0 +1,457 @@
+//! # Novelty Search and Hall of Fame
+//!
+//! In a deceptive fitness landscape the way to the goal leads away from it
+//! first: around a wall, or out of a dead end of a maze. Fitness such as the
+//! distance to the target then pulls the population into the trap.
+//! [`NoveltySearch`] (Lehman & Stanley, 2011) ignores the objective instead
+//! and rewards individuals for behaving differently from the others.
+//!
+//! - Every individual describes its behaviour with [`Novelty::behavior`],
+//!   a vector of numbers such as its final position, or one entry per cell
+//!   of a grid that is 1 for every visited cell.
+//! - Its novelty is the mean distance of its behaviour to the `k` nearest
+//!   behaviours of the current generation and the [`Archive`].
+//! - Individuals more novel than a threshold are added to the archive, so
+//!   that the search keeps moving on to unexplored behaviour. The threshold
+//!   adapts to the number of additions.
+//!
+//! Parents are selected by novelty, optionally blended with the fitness.
+//! Elites, statistics and the termination criteria still use the fitness,
+//! so a run stops once an individual stumbles upon the goal.
+//!
+//! Independent of novelty search, a [`HallOfFame`] keeps the best genotypes
+//! ever evaluated, which elitism alone loses once they are replaced by
+//! fitter or, in novelty search, more novel individuals.
+
+use rand::prelude::*;
+
+use crate::{Evaluator, Gene, Population, fitness};
+
+/// An evaluator that describes the behaviour of its individual.
+pub trait Novelty: Evaluator {
+    /// Describes the behaviour in the last evaluation. All descriptors of a
+    /// population must have the same length.
+    fn behavior(&self) -> Vec<f64>;
+}
+
+/// The Euclidean distance between two behaviours.
+pub fn euclidean(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// The Manhattan distance between two behaviours. For descriptors of
+/// visited cells, the number of cells visited by only one of them.
+pub fn manhattan(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+}
+
+/// A behaviour in the [`Archive`] with the fitness of its individual.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub behavior: Vec<f64>,
+    pub fitness: f64,
+}
+
+/// The behaviours found novel so far, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct Archive {
+    entries: Vec<Entry>,
+    capacity: Option<usize>,
+}
+
+impl Archive {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds an entry, dropping the oldest one if the archive is full.
+    pub fn push(&mut self, entry: Entry) {
+        if self
+            .capacity
+            .is_some_and(|capacity| self.entries.len() >= capacity)
+        {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry);
+    }
+}
+
+/// The configuration and archive of a novelty search, see
+/// [`Population::with_novelty_search`].
+#[derive(Debug, Clone)]
+pub struct NoveltySearch {
+    k: usize,
+    threshold: f64,
+    fitness_weight: f64,
+    distance: fn(&[f64], &[f64]) -> f64,
+    archive: Archive,
+    /// The selection score of every slot of the last evaluated generation.
+    scores: Vec<f64>,
+}
+
+impl NoveltySearch {
+    /// Scores individuals by the mean distance to the `k` nearest behaviours.
+    /// Lehman and Stanley use `k = 15`.
+    pub fn new(k: usize) -> Self {
+        Self {
+            k: k.max(1),
+            threshold: 1.0,
+            fitness_weight: 0.0,
+            distance: euclidean,
+            archive: Archive::default(),
+            scores: Vec::new(),
+        }
+    }
+
+    /// Sets the initial novelty an individual needs to enter the archive.
+    /// It is lowered by 5% after every generation without an addition and
+    /// raised by 20% after a generation with more than 4. Defaults to 1.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Limits the archive to the `capacity` most recent entries. Defaults to
+    /// no limit.
+    pub fn with_archive_capacity(mut self, capacity: usize) -> Self {
+        self.archive.capacity = Some(capacity.max(1));
+        self
+    }
+
+    /// Blends the fitness into the selection score: `weight` times the
+    /// fitness plus `1 - weight` times the novelty, both scaled to the range
+    /// of the generation. Defaults to 0, pure novelty search.
+    pub fn with_fitness_weight(mut self, weight: f64) -> Self {
+        self.fitness_weight = weight.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Replaces the distance between behaviours. Defaults to [`euclidean`].
+    pub fn with_distance(mut self, distance: fn(&[f64], &[f64]) -> f64) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+
+    /// The current novelty needed to enter the archive.
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// The mean distance of every behaviour to its `k` nearest neighbours
+    /// among the others and the archive.
+    fn novelty(&self, behaviors: &[Vec<f64>]) -> Vec<f64> {
+        let mut distances = Vec::new();
+        behaviors
+            .iter()
+            .enumerate()
+            .map(|(i, behavior)| {
+                distances.clear();
+                let others = behaviors
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, other)| other)
+                    .chain(self.archive.entries.iter().map(|e| &e.behavior));
+                distances.extend(others.map(|other| (self.distance)(behavior, other)));
+                let k = self.k.min(distances.len());
+                if k == 0 {
+                    return 0.0;
+                }
+                distances.select_nth_unstable_by(k - 1, |a, b| fitness::compare(*a, *b));
+                distances[..k].iter().sum::<f64>() / k as f64
+            })
+            .collect()
+    }
+
+    /// Scores a generation, archives its novel behaviours and adapts the
+    /// threshold.
+    fn update(&mut self, behaviors: Vec<Vec<f64>>, fitness: &[f64]) {
+        let mut novelty = self.novelty(&behaviors);
+        fitness::sanitize(&mut novelty);
+
+        let mut added = 0;
+        for ((behavior, &n), &f) in behaviors.into_iter().zip(&novelty).zip(fitness) {
+            if n > self.threshold {
+                self.archive.push(Entry {
+                    behavior,
+                    fitness: f,
+                });
+                added += 1;
+            }
+        }
+        match added {
+            0 => self.threshold *= 0.95,
+            5.. => self.threshold *= 1.2,
+            _ => {}
+        }
+
+        let (novelty, fitness) = (scaled(&novelty), scaled(fitness));
+        let w = self.fitness_weight;
+        self.scores.clear();
+        self.scores.extend(
+            novelty
+                .iter()
+                .zip(&fitness)
+                .map(|(n, f)| w * f + (1.0 - w) * n),
+        );
+    }
+}
+
+/// Scales values linearly to `[0, 1]`. All values become 0 if they are equal.
+fn scaled(values: &[f64]) -> Vec<f64> {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    values
+        .iter()
+        .map(|v| if range > 0.0 { (v - min) / range } else { 0.0 })
+        .collect()
+}
+
+/// A novelty search together with the way to describe an evaluator.
+#[derive(Debug)]
+pub(crate) struct NoveltyState<E> {
+    search: NoveltySearch,
+    behavior: fn(&E) -> Vec<f64>,
+}
+
+/// The best genotypes ever evaluated, the fittest first.
+#[derive(Debug, Clone)]
+pub struct HallOfFame<G> {
+    capacity: usize,
+    entries: Vec<(f64, Vec<G>)>,
+}
+
+impl<G: Clone + PartialEq> HallOfFame<G> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// The fitness and genotype of every member, the fittest first.
+    pub fn entries(&self) -> &[(f64, Vec<G>)] {
+        &self.entries
+    }
+
+    /// The fittest genotype ever evaluated.
+    pub fn best(&self) -> Option<(f64, &[G])> {
+        self.entries
+            .first()
+            .map(|(fitness, genotype)| (*fitness, genotype.as_slice()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a genotype if it is fitter than the least fit member, or the hall
+    /// is not full yet. Returns whether it was added. A genotype that is
+    /// already a member is not added again, and a hall of capacity 0 never
+    /// holds any.
+    pub fn insert(&mut self, fitness: f64, genotype: &[G]) -> bool {
+        if self.capacity == 0 {
+            return false;
+        }
+        let full = self.entries.len() >= self.capacity;
+        let fits = self
+            .entries
+            .last()
+            .is_none_or(|(worst, _)| fitness::compare(fitness, *worst).is_gt());
+        if (full && !fits) || self.entries.iter().any(|(_, g)| g.as_slice() == genotype) {
+            return false;
+        }
+        let at = self
+            .entries
+            .partition_point(|(f, _)| fitness::compare(*f, fitness).is_ge());
+        self.entries.insert(at, (fitness, genotype.to_vec()));
+        self.entries.truncate(self.capacity);
+        true
+    }
+}
+
+impl<E: Novelty, R: Rng> Population<E, R> {
+    /// Selects parents by novelty instead of fitness, see [`NoveltySearch`].
+    /// Takes the place of the parent selection of a [`Niching`](crate::niching::Niching)
+    /// strategy.
+    ///
+    /// The behaviour is read from the evaluators, so phenotypes skipped by
+    /// the fitness cache report the behaviour of an earlier genotype.
+    pub fn with_novelty_search(mut self, search: NoveltySearch) -> Self {
+        self.novelty = Some(NoveltyState {
+            search,
+            behavior: E::behavior,
+        });
+        self
+    }
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// Keeps the `capacity` best genotypes ever evaluated, see
+    /// [`Population::hall_of_fame`].
+    pub fn with_hall_of_fame(mut self, capacity: usize) -> Self {
+        self.hall_of_fame = Some(HallOfFame::new(capacity));
+        self
+    }
+
+    pub fn hall_of_fame(&self) -> Option<&HallOfFame<Gene<E>>> {
+        self.hall_of_fame.as_ref()
+    }
+
+    /// The novelty search, if there is one, with its archive.
+    pub fn novelty_search(&self) -> Option<&NoveltySearch> {
+        self.novelty.as_ref().map(|state| &state.search)
+    }
+
+    /// Scores the evaluated generation by novelty and enters it into the
+    /// hall of fame. Called once its fitness is known.
+    pub(crate) fn update_archives(&mut self) {
+        if let Some(state) = self.novelty.as_mut() {
+            let behaviors = self.phenotypes.iter().map(state.behavior).collect();
+            state.search.update(behaviors, &self.fitness);
+        }
+        if let Some(hall) = self.hall_of_fame.as_mut() {
+            for (&fitness, genotype) in self
+                .fitness
+                .iter()
+                .zip(self.genotype_arena.chunks(self.genotype_size))
+            {
+                hall.insert(fitness, genotype);
+            }
+        }
+    }
+
+    /// Fills `self.parents` by novelty. Returns `false` without a novelty
+    /// search.
+    pub(crate) fn select_novel_parents(&mut self) -> bool {
+        let Some(state) = self.novelty.as_ref() else {
+            return false;
+        };
+        self.selection
+            .select(&state.search.scores, &mut self.parents, &mut self.rng);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::OneMax;
+
+    fn behaviors(values: &[f64]) -> Vec<Vec<f64>> {
+        values.iter().map(|&v| vec![v]).collect()
+    }
+
+    #[test]
+    fn novelty_is_the_mean_distance_to_the_k_nearest() {
+        let mut search = NoveltySearch::new(2);
+        let generation = behaviors(&[0.0, 1.0, 3.0, 7.0]);
+        assert_eq!(search.novelty(&generation), [2.0, 1.5, 2.5, 5.0]);
+
+        search.archive.push(Entry {
+            behavior: vec![6.0],
+            fitness: 0.0,
+        });
+        assert_eq!(search.novelty(&generation), [2.0, 1.5, 2.5, 2.5]);
+        // fewer neighbours than k
+        assert_eq!(
+            NoveltySearch::new(5).novelty(&behaviors(&[0.0, 4.0])),
+            [4.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn the_archive_grows_with_novel_behaviours() {
+        let mut search = NoveltySearch::new(2).with_threshold(2.2);
+        search.update(behaviors(&[0.0, 1.0, 3.0, 7.0]), &[0.0, 1.0, 2.0, 3.0]);
+        let archived: Vec<&[f64]> = search
+            .archive()
+            .entries()
+            .iter()
+            .map(|e| e.behavior.as_slice())
+            .collect();
+        assert_eq!(archived, [[3.0], [7.0]]);
+        assert_eq!(search.archive().entries()[1].fitness, 3.0);
+        assert_eq!(search.threshold(), 2.2);
+
+        // nothing new: the threshold drops
+        search.update(behaviors(&[3.0, 3.0]), &[0.0, 0.0]);
+        assert_eq!(search.archive().len(), 2);
+        assert!((search.threshold() - 2.2 * 0.95).abs() < 1e-12);
+
+        // many novel behaviours: the threshold rises, the oldest entries go
+        let mut search = NoveltySearch::new(1)
+            .with_threshold(0.5)
+            .with_archive_capacity(3);
+        search.update(behaviors(&[0.0, 10.0, 20.0, 30.0, 40.0]), &[0.0; 5]);
+        assert!((search.threshold() - 0.6).abs() < 1e-12);
+        let archived: Vec<f64> = search
+            .archive()
+            .entries()
+            .iter()
+            .map(|e| e.behavior[0])
+            .collect();
+        assert_eq!(archived, [20.0, 30.0, 40.0]);
+    }
+
+    #[test]
+    fn the_hall_of_fame_keeps_the_fittest_in_order() {
+        let mut hall = HallOfFame::new(3);
+        assert!(hall.insert(2.0, &[2]));
+        assert!(hall.insert(5.0, &[5]));
+        assert!(hall.insert(1.0, &[1]));
+        assert!(!hall.insert(7.0, &[5]), "already a member");
+        // full: the least fit member is evicted, an unfit genotype stays out
+        assert!(hall.insert(3.0, &[3]));
+        assert!(!hall.insert(2.0, &[4]));
+        assert!(!hall.insert(f64::NAN, &[6]));
+        let entries: Vec<(f64, i32)> = hall.entries().iter().map(|(f, g)| (*f, g[0])).collect();
+        assert_eq!(entries, [(5.0, 5), (3.0, 3), (2.0, 2)]);
+        assert_eq!(hall.best(), Some((5.0, &[5][..])));
+
+        let mut empty = HallOfFame::new(0);
+        assert!(!empty.insert(1.0, &[1]));
+        assert!(empty.is_empty() && empty.best().is_none());
+    }
+
+    #[test]
+    fn the_hall_of_fame_holds_the_best_of_the_run() {
+        let mut population = Population::<OneMax>::with_seed(20, 32, 2)
+            .with_elitism(0)
+            .with_hall_of_fame(5);
+        for _ in 0..20 {
+            population.evolve(&());
+        }
+        let hall = population.hall_of_fame().unwrap();
+        let best = population
+            .history()
+            .iter()
+            .map(|s| s.max_fitness)
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert_eq!(hall.len(), 5);
+        assert_eq!(hall.best().unwrap().0, best);
+        let ones = hall.best().unwrap().1.iter().filter(|&&bit| bit).count();
+        assert_eq!(ones as f64, best);
+    }
+}
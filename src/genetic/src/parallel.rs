@@ -0,0 +1,114 @@
+//! Multi-threaded variants of the `Population` hot loops, enabled with the
+//! `parallel` cargo feature.
+//!
+//! The serial methods stay available (and are the only option on wasm). The
+//! parallel methods produce a reproducible run for a given seed as well: the
+//! offspring are bred in fixed blocks, and every block gets its own RNG stream
+//! derived from the population's RNG, independent of the number of threads.
+
+use rand::prelude::*;
+use rayon::prelude::*;
+
+use crate::{
+    Evaluator, Gene, Population,
+    cache::{self, Lookup},
+    replacement::Replacement,
+};
+
+/// Number of child pairs bred with the same RNG stream.
+const PAIRS_PER_STREAM: usize = 16;
+
+impl<E, R> Population<E, R>
+where
+    E: Evaluator + Send,
+    Gene<E>: Send + Sync,
+    E::FitnessParam: Sync,
+    R: Rng + SeedableRng + Send,
+{
+    /// Same as [`Population::evolve`], but evaluates the fitness and breeds the
+    /// next generation on the rayon thread pool.
+    pub fn par_evolve(&mut self, param: &E::FitnessParam) {
+        self.par_evaluate_generation(param);
+        self.par_breed_generation();
+    }
+
+    pub(crate) fn par_evaluate_generation(&mut self, param: &E::FitnessParam) {
+        self.par_calculate_fitness(param);
+        self.record_statistics();
+        self.update_mutation_schedule();
+    }
+
+    pub(crate) fn par_breed_generation(&mut self) {
+        self.align_next_generation();
+        self.par_create_next_generation();
+        std::mem::swap(&mut self.genotype_arena, &mut self.next_gen_arena);
+    }
+
+    fn par_calculate_fitness(&mut self, param: &E::FitnessParam) {
+        self.repair_genotypes(param);
+        self.lookup_cached_fitness();
+        let known = cache::known(&self.fitness_cache);
+        self.phenotypes
+            .par_iter_mut()
+            .zip(self.genotype_arena.par_chunks(self.genotype_size))
+            .enumerate()
+            .filter(|(slot, _)| Lookup::needs_evaluation(known, *slot))
+            .for_each(|(_, (p, genotype))| p.evaluate(genotype, param));
+        self.collect_fitness();
+    }
+
+    fn par_create_next_generation(&mut self) {
+        if let Replacement::SteadyState { count, replace } = self.replacement {
+            // only a few children per step, not worth spreading over threads
+            self.replace_steady_state(count, replace);
+            return;
+        }
+
+        self.copy_elite();
+        self.select_parents();
+
+        let genotype_size = self.genotype_size;
+        let genotype_arena = &self.genotype_arena;
+        let rate = self.mutation_rate();
+        let crossover = self.crossover;
+        let survivors = self.survivors();
+        let base_seed: u64 = self.rng.random();
+
+        self.next_gen_arena[survivors * genotype_size..]
+            .par_chunks_mut(2 * genotype_size * PAIRS_PER_STREAM)
+            .zip(self.parents.par_chunks(2 * PAIRS_PER_STREAM))
+            .enumerate()
+            .for_each(|(stream, (children, parents))| {
+                let mut rng = R::seed_from_u64(base_seed.wrapping_add(stream as u64));
+                for (children, pair) in children
+                    .chunks_mut(2 * genotype_size)
+                    .zip(parents.chunks_exact(2))
+                {
+                    Self::breed(
+                        genotype_arena,
+                        genotype_size,
+                        pair,
+                        children,
+                        rate,
+                        crossover,
+                        &mut rng,
+                    );
+                }
+            });
+        self.assign_rivals();
+    }
+
+    /// Parallel version of [`Population::for_each_phenotype_mut`].
+    ///
+    /// The closure is called concurrently for different phenotypes and therefore
+    /// has to be `Fn` and `Sync`.
+    pub fn par_for_each_phenotype_mut<F>(&mut self, func: F)
+    where
+        F: Fn(&mut E, &[Gene<E>]) + Sync + Send,
+    {
+        self.phenotypes
+            .par_iter_mut()
+            .zip(self.genotype_arena.par_chunks(self.genotype_size))
+            .for_each(|(p, genotype)| func(p, genotype));
+    }
+}
@@ -0,0 +1,238 @@
+//! # Replacement
+//!
+//! How the offspring take the place of the current generation. By default
+//! every call to `evolve` breeds a whole new generation, keeping only the
+//! elites. A [`Replacement`] set with `Population::with_replacement` keeps a
+//! larger part of the population instead, down to replacing only a handful
+//! of individuals per step. This makes for continuous, gradual improvement,
+//! e.g. one step per frame of an interactive visualisation.
+//!
+//! Every step still evaluates, records and counts as a full generation.
+
+use rand::prelude::*;
+
+use crate::{Evaluator, Gene, Population, fitness};
+
+/// How many individuals are replaced per generation, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Replacement {
+    /// All individuals but the elites are replaced.
+    #[default]
+    Generational,
+    /// Only `fraction` (between 0 and 1) of the population is replaced by
+    /// offspring, the fittest others survive unchanged. The elites survive
+    /// even if the fraction is larger.
+    GenerationalGap { fraction: f64 },
+    /// Only `count` children are bred per generation. They take the place of
+    /// the individuals picked by `replace`, all others survive unchanged.
+    SteadyState { count: usize, replace: Replace },
+}
+
+/// Which individuals the children of a steady-state step replace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Replace {
+    /// The least fit individuals, never the elites.
+    #[default]
+    Worst,
+    /// Random individuals, never the elites.
+    Random,
+    /// Every child competes with one of its parents and only takes its place
+    /// if it turns out to be at least as fit when it is evaluated in the next
+    /// generation. Otherwise the parent is put back right after that
+    /// evaluation; the phenotype of the slot still describes the child, while
+    /// the fitness and genotype of the slot are the parent's.
+    Parent,
+}
+
+impl<E: Evaluator, R: Rng> Population<E, R> {
+    /// Sets how the offspring replace the current generation. Defaults to
+    /// [`Replacement::Generational`].
+    pub fn with_replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Number of the fittest individuals that are copied unchanged into the
+    /// next generation by the generational modes.
+    pub(crate) fn survivors(&self) -> usize {
+        let n = self.phenotypes.len();
+        match self.replacement {
+            Replacement::GenerationalGap { fraction } => {
+                let replaced = (fraction.clamp(0.0, 1.0) * n as f64).round() as usize;
+                self.elitism.max(n - replaced)
+            }
+            _ => self.elitism,
+        }
+    }
+
+    /// Creates the next generation as a copy of the current one in which
+    /// `count` individuals are replaced by children.
+    pub(crate) fn replace_steady_state(&mut self, count: usize, replace: Replace) {
+        let n = self.phenotypes.len();
+        let genotype_size = self.genotype_size;
+        self.next_gen_arena.copy_from_slice(&self.genotype_arena);
+
+        // every slot but the elites, the least fit first
+        let mut targets: Vec<usize> = (0..n).collect();
+        targets.sort_by(|&a, &b| fitness::compare(self.fitness[a], self.fitness[b]));
+        targets.truncate(n - self.elitism);
+        match replace {
+            Replace::Worst => {}
+            Replace::Random => targets.shuffle(&mut self.rng),
+            Replace::Parent => targets.clear(),
+        }
+
+        let children = match replace {
+            Replace::Parent => count.min(n),
+            _ => count.min(targets.len()),
+        };
+        self.select_pairs(children);
+
+        self.parent_fitness.clear();
+        self.parent_fitness.resize(n, f64::NAN);
+        if replace == Replace::Parent {
+            self.niches.previous_fitness.clone_from(&self.fitness);
+            self.niches.rivals.clear();
+            self.niches.rivals.resize(n, None);
+        }
+
+        let rate = self.mutation_rate();
+        let crossover = self.crossover;
+        let mut offspring = vec![Gene::<E>::default(); 2 * genotype_size];
+        for (p, pair) in self.parents.chunks_exact(2).enumerate() {
+            let born = (children - 2 * p).min(2);
+            let offspring = &mut offspring[..born * genotype_size];
+            Self::breed(
+                &self.genotype_arena,
+                genotype_size,
+                pair,
+                offspring,
+                rate,
+                crossover,
+                &mut self.rng,
+            );
+
+            let slots = match replace {
+                Replace::Parent => {
+                    // a parent that already competes with another child, or
+                    // both parents being the same, leaves a child without a slot
+                    let free = |slot: usize| self.niches.rivals[slot].is_none();
+                    let first = free(pair[0]).then_some(pair[0]);
+                    let second = (pair[1] != pair[0] && free(pair[1])).then_some(pair[1]);
+                    [first, second]
+                }
+                _ => [Some(targets[2 * p]), targets.get(2 * p + 1).copied()],
+            };
+
+            let better_parent = self.fitness[pair[0]].max(self.fitness[pair[1]]);
+            for (slot, child) in slots.into_iter().zip(offspring.chunks(genotype_size)) {
+                let Some(slot) = slot else {
+                    continue;
+                };
+                let start = slot * genotype_size;
+                self.next_gen_arena[start..start + genotype_size].copy_from_slice(child);
+                self.parent_fitness[slot] = better_parent;
+                if replace == Replace::Parent {
+                    self.niches.rivals[slot] = Some(slot);
+                }
+            }
+        }
+
+        self.inherited_fitness.clone_from(&self.fitness);
+        for (inherited, parent) in self.inherited_fitness.iter_mut().zip(&self.parent_fitness) {
+            if !parent.is_nan() {
+                *inherited = f64::NAN;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::OneMax;
+
+    #[test]
+    fn generational_gap_keeps_the_fittest() {
+        let mut population = Population::<OneMax>::with_seed(20, 16, 5)
+            .with_elitism(2)
+            .with_replacement(Replacement::GenerationalGap { fraction: 0.25 });
+        assert_eq!(population.survivors(), 15);
+        population.evolve(&());
+
+        let mut order: Vec<usize> = (0..20).collect();
+        order.sort_by(|&a, &b| fitness::compare(population.fitness[b], population.fitness[a]));
+        for (slot, &fittest) in order[..15].iter().enumerate() {
+            assert_eq!(
+                &population.genotype_arena[slot * 16..(slot + 1) * 16],
+                population.evaluated_genotype(fittest)
+            );
+            assert_eq!(
+                population.inherited_fitness[slot],
+                population.fitness[fittest]
+            );
+        }
+        assert!(
+            population.inherited_fitness[15..]
+                .iter()
+                .all(|f| f.is_nan())
+        );
+
+        // the elites survive however large the gap
+        let population =
+            population.with_replacement(Replacement::GenerationalGap { fraction: 1.0 });
+        assert_eq!(population.survivors(), 2);
+    }
+
+    #[test]
+    fn steady_state_replaces_the_worst() {
+        let mut population = Population::<OneMax>::with_seed(20, 16, 6)
+            .with_elitism(1)
+            .with_replacement(Replacement::SteadyState {
+                count: 3,
+                replace: Replace::Worst,
+            });
+        population.evaluate_generation(&());
+        population.create_next_generation();
+
+        let replaced: Vec<usize> = (0..20)
+            .filter(|&slot| !population.parent_fitness[slot].is_nan())
+            .collect();
+        assert_eq!(replaced.len(), 3);
+        for slot in 0..20 {
+            let genotype = slot * 16..(slot + 1) * 16;
+            if replaced.contains(&slot) {
+                assert!(population.inherited_fitness[slot].is_nan());
+                assert!(
+                    (0..20)
+                        .filter(|other| !replaced.contains(other))
+                        .all(|other| population.fitness[slot] <= population.fitness[other])
+                );
+            } else {
+                assert_eq!(
+                    population.next_gen_arena[genotype.clone()],
+                    population.genotype_arena[genotype]
+                );
+                assert_eq!(population.inherited_fitness[slot], population.fitness[slot]);
+            }
+        }
+    }
+
+    #[test]
+    fn steady_state_children_only_replace_worse_parents() {
+        let mut population =
+            Population::<OneMax>::with_seed(20, 16, 7).with_replacement(Replacement::SteadyState {
+                count: 6,
+                replace: Replace::Parent,
+            });
+        population.evolve(&());
+        for _ in 0..20 {
+            let previous = population.fitness.clone();
+            population.evolve(&());
+            for (now, before) in population.fitness.iter().zip(&previous) {
+                assert!(now >= before);
+            }
+        }
+    }
+}
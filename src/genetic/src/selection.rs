@@ -0,0 +1,329 @@
+//! # Parent Selection Strategies
+//!
+//! A [`Selection`] strategy decides which individuals of the current generation
+//! become parents of the next one. The `Population` hands the strategy the
+//! fitness of every phenotype and a buffer of parent slots to fill; the
+//! strategy writes indices into the fitness slice.
+//!
+//! All strategies maximise fitness, i.e. a higher value is considered better.
+
+use rand::prelude::*;
+
+use crate::fitness;
+
+pub trait Selection: std::fmt::Debug {
+    /// Fills every entry of `parents` with the index of a selected individual.
+    ///
+    /// `fitness[i]` is the fitness of the phenotype with index `i`. Consecutive
+    /// entries of `parents` are paired up for crossover.
+    fn select(&mut self, fitness: &[f64], parents: &mut [usize], rng: &mut dyn RngCore);
+}
+
+/// Tournament selection: draw `size` random contenders and keep the fittest.
+///
+/// Larger tournaments increase the selection pressure.
+#[derive(Debug, Clone, Copy)]
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl Tournament {
+    pub fn new(size: usize) -> Self {
+        Self { size: size.max(1) }
+    }
+}
+
+impl Default for Tournament {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl Selection for Tournament {
+    fn select(&mut self, fitness: &[f64], parents: &mut [usize], rng: &mut dyn RngCore) {
+        let population_size = fitness.len();
+        if population_size == 0 {
+            parents.fill(0);
+            return;
+        }
+
+        for parent in parents.iter_mut() {
+            // Select the first contender as the initial best
+            let mut best_idx = rng.random_range(0..population_size);
+
+            // Run the rest of the tournament (starting from the second contender)
+            for _ in 1..self.size {
+                let contender_idx = rng.random_range(0..population_size);
+                if fitness[contender_idx] > fitness[best_idx] {
+                    best_idx = contender_idx;
+                }
+            }
+            *parent = best_idx;
+        }
+    }
+}
+
+/// Fitness proportionate (roulette wheel) selection.
+///
+/// Every parent is drawn with a probability proportional to its fitness.
+/// Negative fitness values are shifted so that the worst individual gets a
+/// zero-width slice of the wheel.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RouletteWheel;
+
+impl Selection for RouletteWheel {
+    fn select(&mut self, fitness: &[f64], parents: &mut [usize], rng: &mut dyn RngCore) {
+        let cumulative = cumulative_weights(fitness.iter().copied().map(shift(fitness)));
+        for parent in parents.iter_mut() {
+            *parent = spin(&cumulative, rng);
+        }
+    }
+}
+
+/// Stochastic universal sampling.
+///
+/// Like [`RouletteWheel`], but the wheel is spun only once and all parents are
+/// picked with evenly spaced pointers. This keeps the number of offspring of
+/// an individual close to its expected value and reduces selection noise.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StochasticUniversalSampling;
+
+impl Selection for StochasticUniversalSampling {
+    fn select(&mut self, fitness: &[f64], parents: &mut [usize], rng: &mut dyn RngCore) {
+        let cumulative = cumulative_weights(fitness.iter().copied().map(shift(fitness)));
+        let total = cumulative.last().copied().unwrap_or(0.0);
+        if parents.is_empty() {
+            return;
+        }
+        if total <= 0.0 || !total.is_finite() {
+            uniform(fitness.len(), parents, rng);
+            return;
+        }
+
+        let distance = total / parents.len() as f64;
+        let start = rng.random_range(0.0..distance);
+        let mut idx = 0;
+        for (i, parent) in parents.iter_mut().enumerate() {
+            let pointer = start + i as f64 * distance;
+            while idx < cumulative.len() - 1 && cumulative[idx] <= pointer {
+                idx += 1;
+            }
+            *parent = idx;
+        }
+
+        // The pointers visit the individuals in order; shuffle them so that
+        // neighbouring individuals are not always mated with each other.
+        parents.shuffle(rng);
+    }
+}
+
+/// Linear rank-based selection.
+///
+/// Individuals are sorted by fitness and selected with a probability that
+/// depends only on their rank. `pressure` is the expected number of offspring
+/// of the best individual and must lie in `1.0..=2.0`.
+#[derive(Debug, Clone, Copy)]
+pub struct Rank {
+    pub pressure: f64,
+}
+
+impl Rank {
+    pub fn new(pressure: f64) -> Self {
+        Self {
+            pressure: pressure.clamp(1.0, 2.0),
+        }
+    }
+}
+
+impl Default for Rank {
+    fn default() -> Self {
+        Self::new(1.5)
+    }
+}
+
+impl Selection for Rank {
+    fn select(&mut self, fitness: &[f64], parents: &mut [usize], rng: &mut dyn RngCore) {
+        let n = fitness.len();
+        if n < 2 {
+            parents.fill(0);
+            return;
+        }
+
+        let order = sorted_ascending(fitness);
+        let s = self.pressure;
+        let n_f = n as f64;
+        // weight of rank r (0 = worst) according to linear ranking
+        let cumulative = cumulative_weights(
+            (0..n).map(|r| (2.0 - s) / n_f + 2.0 * r as f64 * (s - 1.0) / (n_f * (n_f - 1.0))),
+        );
+
+        for parent in parents.iter_mut() {
+            *parent = order[spin(&cumulative, rng)];
+        }
+    }
+}
+
+/// Truncation selection: only the best `fraction` of the population may
+/// reproduce, each of them with equal probability.
+#[derive(Debug, Clone, Copy)]
+pub struct Truncation {
+    pub fraction: f64,
+}
+
+impl Truncation {
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction: fraction.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Default for Truncation {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl Selection for Truncation {
+    fn select(&mut self, fitness: &[f64], parents: &mut [usize], rng: &mut dyn RngCore) {
+        let n = fitness.len();
+        if n == 0 {
+            parents.fill(0);
+            return;
+        }
+
+        let order = sorted_ascending(fitness);
+        let keep = ((n as f64 * self.fraction).ceil() as usize).clamp(1, n);
+        let survivors = &order[n - keep..];
+        for parent in parents.iter_mut() {
+            *parent = survivors[rng.random_range(0..keep)];
+        }
+    }
+}
+
+/// Returns a closure that shifts the fitness values to be non-negative.
+fn shift(fitness: &[f64]) -> impl Fn(f64) -> f64 {
+    let min = fitness.iter().copied().fold(f64::INFINITY, f64::min);
+    let offset = if min < 0.0 { -min } else { 0.0 };
+    move |f| f + offset
+}
+
+fn cumulative_weights(weights: impl Iterator<Item = f64>) -> Vec<f64> {
+    let mut sum = 0.0;
+    weights
+        .map(|w| {
+            sum += w.max(0.0);
+            sum
+        })
+        .collect()
+}
+
+/// Picks an index with a probability proportional to its slice of the
+/// cumulative weights. Falls back to a uniform pick for a degenerate wheel.
+fn spin(cumulative: &[f64], rng: &mut dyn RngCore) -> usize {
+    let total = cumulative.last().copied().unwrap_or(0.0);
+    if cumulative.is_empty() {
+        return 0;
+    }
+    if total <= 0.0 || !total.is_finite() {
+        return rng.random_range(0..cumulative.len());
+    }
+    let pointer = rng.random_range(0.0..total);
+    cumulative
+        .partition_point(|&c| c <= pointer)
+        .min(cumulative.len() - 1)
+}
+
+fn uniform(population_size: usize, parents: &mut [usize], rng: &mut dyn RngCore) {
+    for parent in parents.iter_mut() {
+        *parent = rng.random_range(0..population_size.max(1));
+    }
+}
+
+/// Indices of `fitness` sorted from the worst to the best individual.
+fn sorted_ascending(fitness: &[f64]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..fitness.len()).collect();
+    order.sort_by(|&a, &b| fitness::compare(fitness[a], fitness[b]));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    /// How often every individual is picked out of 3000 parents.
+    fn counts(selection: &mut dyn Selection, fitness: &[f64]) -> Vec<usize> {
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let mut parents = vec![usize::MAX; 3000];
+        selection.select(fitness, &mut parents, &mut rng);
+        let mut counts = vec![0; fitness.len()];
+        for parent in parents {
+            counts[parent] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn tournament_of_zero_picks_at_random() {
+        let counts = counts(&mut Tournament { size: 0 }, &[1.0, 2.0, 3.0]);
+        assert!(counts.iter().all(|&count| (800..1200).contains(&count)));
+        assert_eq!(Tournament::new(0).size, 1);
+    }
+
+    #[test]
+    fn tournament_larger_than_the_population_picks_the_best() {
+        assert_eq!(
+            counts(&mut Tournament::new(100), &[1.0, 3.0, 2.0]),
+            [0, 3000, 0]
+        );
+    }
+
+    #[test]
+    fn tournament_favours_the_fitter() {
+        let counts = counts(&mut Tournament::default(), &[1.0, 2.0, 3.0]);
+        assert!(counts[0] < counts[1] && counts[1] < counts[2]);
+    }
+
+    #[test]
+    fn roulette_shifts_negative_fitness() {
+        // shifted to 0, 2 and 1
+        let counts = counts(&mut RouletteWheel, &[-3.0, -1.0, -2.0]);
+        assert_eq!(counts[0], 0);
+        assert!((1800..2200).contains(&counts[1]));
+        assert!((800..1200).contains(&counts[2]));
+    }
+
+    #[test]
+    fn roulette_with_equal_fitness_is_uniform() {
+        for value in [-1.0, 0.0, 5.0] {
+            let counts = counts(&mut RouletteWheel, &[value; 3]);
+            assert!(counts.iter().all(|&count| (800..1200).contains(&count)));
+        }
+    }
+
+    #[test]
+    fn rank_ignores_the_fitness_scale() {
+        let mut rank = Rank::new(2.0);
+        let counts_small = counts(&mut rank, &[0.1, 0.3, 0.2]);
+        assert_eq!(counts_small, counts(&mut rank, &[-100.0, 1e9, 5.0]));
+        // with the highest pressure the worst is never selected
+        assert_eq!(counts_small[0], 0);
+        assert!(counts_small[2] < counts_small[1]);
+    }
+
+    #[test]
+    fn selection_from_tiny_populations() {
+        let strategies: [&mut dyn Selection; 3] = [
+            &mut Tournament::default(),
+            &mut RouletteWheel,
+            &mut Rank::default(),
+        ];
+        for selection in strategies {
+            assert_eq!(counts(selection, &[4.0]), [3000]);
+            let mut parents = [usize::MAX; 2];
+            selection.select(&[], &mut parents, &mut ChaCha8Rng::seed_from_u64(0));
+            assert_eq!(parents, [0, 0]);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! # Generation Statistics
+//!
+//! After the fitness of a generation has been evaluated, the `Population`
+//! condenses it into a [`Statistics`] record, appends it to its history and
+//! hands it to every registered [`Observer`].
+
+use crate::fitness;
+
+/// Summary of a single evaluated generation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statistics {
+    /// The generation this record describes, starting at 0.
+    pub generation: usize,
+    pub min_fitness: f64,
+    pub mean_fitness: f64,
+    pub median_fitness: f64,
+    pub max_fitness: f64,
+    pub std_dev: f64,
+    /// Mean fraction of genes in which a genotype differs from the elite.
+    ///
+    /// `0.0` means the whole population consists of clones of the elite,
+    /// `1.0` means no individual shares a single gene with it.
+    pub diversity: f64,
+    /// Index of the fittest phenotype of the evaluated generation.
+    pub elite_index: usize,
+    /// Fitness evaluations performed so far, including this generation's.
+    /// Genotypes whose fitness was taken from a cache are not counted.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub evaluations: usize,
+}
+
+impl Statistics {
+    /// Computes the fitness statistics of a generation.
+    ///
+    /// `diversity` and `evaluations` are left at 0 and have to be filled in
+    /// by the caller, as they depend on the genotypes and the population.
+    pub fn from_fitness(generation: usize, fitness: &[f64]) -> Self {
+        if fitness.is_empty() {
+            return Self {
+                generation,
+                ..Default::default()
+            };
+        }
+
+        let n = fitness.len() as f64;
+        let mut sorted = fitness.to_vec();
+        sorted.sort_by(|a, b| fitness::compare(*a, *b));
+
+        let mean = fitness.iter().sum::<f64>() / n;
+        let variance = fitness.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / n;
+        let mid = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        };
+        let elite_index = fitness::argmax(fitness.iter().copied()).unwrap_or(0);
+
+        Self {
+            generation,
+            min_fitness: sorted[0],
+            mean_fitness: mean,
+            median_fitness: median,
+            max_fitness: sorted[sorted.len() - 1],
+            std_dev: variance.sqrt(),
+            diversity: 0.0,
+            elite_index,
+            evaluations: 0,
+        }
+    }
+}
+
+/// Mean normalised Hamming distance between every genotype in `genotype_arena`
+/// and the genotype at `elite_index`.
+pub fn diversity<G: PartialEq>(
+    genotype_arena: &[G],
+    genotype_size: usize,
+    elite_index: usize,
+) -> f64 {
+    if genotype_size == 0 || genotype_arena.len() < genotype_size {
+        return 0.0;
+    }
+    let elite_start = elite_index * genotype_size;
+    let elite = &genotype_arena[elite_start..elite_start + genotype_size];
+
+    let genotypes = genotype_arena.len() / genotype_size;
+    let differing: usize = genotype_arena
+        .chunks(genotype_size)
+        .map(|genotype| genotype.iter().zip(elite).filter(|(a, b)| a != b).count())
+        .sum();
+    differing as f64 / (genotypes * genotype_size) as f64
+}
+
+/// Gets notified every time a `Population` has evaluated a generation.
+///
+/// Any `FnMut(&Statistics)` closure is an observer.
+pub trait Observer {
+    fn on_generation(&mut self, statistics: &Statistics);
+}
+
+impl<F: FnMut(&Statistics)> Observer for F {
+    fn on_generation(&mut self, statistics: &Statistics) {
+        self(statistics)
+    }
+}
+
+/// The registered observers of a population.
+#[derive(Default)]
+pub(crate) struct Observers(pub(crate) Vec<Box<dyn Observer>>);
+
+impl Observers {
+    pub(crate) fn notify(&mut self, statistics: &Statistics) {
+        for observer in self.0.iter_mut() {
+            observer.on_generation(statistics);
+        }
+    }
+}
+
+impl std::fmt::Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Observers({})", self.0.len())
+    }
+}
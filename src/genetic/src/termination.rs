@@ -0,0 +1,334 @@
+//! # Termination Criteria
+//!
+//! A [`Termination`] decides when an evolutionary run is finished. It is
+//! checked after every evaluated generation with the statistics history of
+//! the run, see `Population::run`. Criteria can be combined with
+//! [`Termination::or`]:
+//!
+//! ```
+//! use genetic::termination::{MaxGenerations, Stagnation, TargetFitness, Termination};
+//!
+//! let termination = TargetFitness(67.0)
+//!     .or(MaxGenerations::new(10_000))
+//!     .or(Stagnation::new(500));
+//! ```
+
+use crate::statistics::Statistics;
+
+/// Why a run was stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopReason {
+    MaxGenerations,
+    TargetFitness,
+    Stagnation,
+    TimeBudget,
+    MaxEvaluations,
+    Custom,
+}
+
+pub trait Termination {
+    /// Returns the reason to stop, or `None` if the run should continue.
+    ///
+    /// `history` holds the statistics of every generation evaluated so far.
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason>;
+
+    /// Called by the `run` methods before the first generation of a run, so
+    /// that a criterion reused for another run starts counting afresh.
+    /// `history` holds the statistics of the generations evaluated before.
+    fn begin(&mut self, history: &[Statistics]) {
+        let _ = history;
+    }
+
+    /// Stops as soon as either `self` or `other` is satisfied.
+    fn or<T: Termination>(self, other: T) -> Or<Self, T>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+}
+
+/// Stops after the given number of generations since the start of the run,
+/// so that a run continuing an earlier one, e.g. from a checkpoint, gets the
+/// full number of generations. Checked by hand, without a `run` method, the
+/// count starts at the first check.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxGenerations {
+    pub generations: usize,
+    start: Option<usize>,
+}
+
+impl MaxGenerations {
+    pub fn new(generations: usize) -> Self {
+        Self {
+            generations,
+            start: None,
+        }
+    }
+}
+
+impl Termination for MaxGenerations {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        // the first check follows the first generation of the run
+        let start = *self.start.get_or_insert(history.len().saturating_sub(1));
+        (history.len().saturating_sub(start) >= self.generations)
+            .then_some(StopReason::MaxGenerations)
+    }
+
+    fn begin(&mut self, history: &[Statistics]) {
+        self.start = Some(history.len());
+    }
+}
+
+/// Stops once the best individual reaches the given fitness.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetFitness(pub f64);
+
+impl Termination for TargetFitness {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        history
+            .last()
+            .filter(|s| s.max_fitness >= self.0)
+            .map(|_| StopReason::TargetFitness)
+    }
+}
+
+/// Stops once the given number of fitness evaluations has been used, see
+/// [`Statistics::evaluations`]. The generation that exceeds the budget is
+/// completed, so a run may use slightly more evaluations.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxEvaluations(pub usize);
+
+impl Termination for MaxEvaluations {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        history
+            .last()
+            .filter(|s| s.evaluations >= self.0)
+            .map(|_| StopReason::MaxEvaluations)
+    }
+}
+
+/// Stops when the best fitness has not improved by more than `epsilon` for
+/// `generations` consecutive generations.
+#[derive(Debug, Clone, Copy)]
+pub struct Stagnation {
+    pub generations: usize,
+    pub epsilon: f64,
+    best: f64,
+    last_improvement: usize,
+}
+
+impl Stagnation {
+    pub fn new(generations: usize) -> Self {
+        Self::with_epsilon(generations, 0.0)
+    }
+
+    pub fn with_epsilon(generations: usize, epsilon: f64) -> Self {
+        Self {
+            generations,
+            epsilon,
+            best: f64::NEG_INFINITY,
+            last_improvement: 0,
+        }
+    }
+}
+
+impl Termination for Stagnation {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        let latest = history.last()?;
+        if latest.generation < self.last_improvement {
+            // reused for a run of another population
+            *self = Self::with_epsilon(self.generations, self.epsilon);
+        }
+        if latest.max_fitness > self.best + self.epsilon {
+            self.best = latest.max_fitness;
+            self.last_improvement = latest.generation;
+        }
+        (latest.generation - self.last_improvement >= self.generations)
+            .then_some(StopReason::Stagnation)
+    }
+
+    fn begin(&mut self, _: &[Statistics]) {
+        *self = Self::with_epsilon(self.generations, self.epsilon);
+    }
+}
+
+/// Stops once the given wall-clock time has passed since the first check.
+///
+/// Not available on wasm, where `std::time::Instant` is not supported.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+pub struct TimeBudget {
+    pub budget: std::time::Duration,
+    start: Option<std::time::Instant>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TimeBudget {
+    pub fn new(budget: std::time::Duration) -> Self {
+        Self {
+            budget,
+            start: None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Termination for TimeBudget {
+    fn check(&mut self, _: &[Statistics]) -> Option<StopReason> {
+        let start = *self.start.get_or_insert_with(std::time::Instant::now);
+        (start.elapsed() >= self.budget).then_some(StopReason::TimeBudget)
+    }
+
+    fn begin(&mut self, _: &[Statistics]) {
+        self.start = Some(std::time::Instant::now());
+    }
+}
+
+/// Stops when the predicate returns `true`.
+pub struct Predicate<F>(pub F);
+
+impl<F: FnMut(&[Statistics]) -> bool> Termination for Predicate<F> {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        (self.0)(history).then_some(StopReason::Custom)
+    }
+}
+
+/// Combination of two criteria, see [`Termination::or`].
+#[derive(Debug, Clone, Copy)]
+pub struct Or<A, B>(A, B);
+
+impl<A: Termination, B: Termination> Termination for Or<A, B> {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        // Check both sides so that stateful criteria see every generation.
+        let a = self.0.check(history);
+        let b = self.1.check(history);
+        a.or(b)
+    }
+
+    fn begin(&mut self, history: &[Statistics]) {
+        self.0.begin(history);
+        self.1.begin(history);
+    }
+}
+
+impl<T: Termination + ?Sized> Termination for Box<T> {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        (**self).check(history)
+    }
+
+    fn begin(&mut self, history: &[Statistics]) {
+        (**self).begin(history);
+    }
+}
+
+/// Lets a criterion be reused for several runs.
+impl<T: Termination + ?Sized> Termination for &mut T {
+    fn check(&mut self, history: &[Statistics]) -> Option<StopReason> {
+        (**self).check(history)
+    }
+
+    fn begin(&mut self, history: &[Statistics]) {
+        (**self).begin(history);
+    }
+}
+
+/// The outcome of the `run` methods, e.g. `Population::run`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunReport<G> {
+    pub reason: StopReason,
+    /// Number of generations evaluated during the run.
+    pub generations: usize,
+    /// Fitness of the best individual seen during the run.
+    pub best_fitness: f64,
+    /// Genotype of the best individual seen during the run.
+    pub best_genotype: Vec<G>,
+    /// Statistics of the last evaluated generation.
+    pub statistics: Statistics,
+}
+
+/// The loop behind the `run` methods.
+///
+/// Calls `step` until `termination` is satisfied and keeps track of the best
+/// individual seen. `history` returns the statistics of every evaluated
+/// generation, `genotype` the evaluated genotype at an `elite_index`.
+pub(crate) fn drive<S, G: Clone>(
+    state: &mut S,
+    mut step: impl FnMut(&mut S),
+    history: impl Fn(&S) -> &[Statistics],
+    genotype: impl Fn(&S, usize) -> &[G],
+    mut termination: impl Termination,
+) -> RunReport<G> {
+    let start_generation = history(state).len();
+    termination.begin(history(state));
+    let mut best_fitness = f64::NEG_INFINITY;
+    let mut best_genotype = Vec::new();
+
+    let reason = loop {
+        step(state);
+
+        let statistics = history(state)[history(state).len() - 1];
+        let first = history(state).len() == start_generation + 1;
+        if first || statistics.max_fitness > best_fitness {
+            best_fitness = statistics.max_fitness;
+            best_genotype = genotype(state, statistics.elite_index).to_vec();
+        }
+        if let Some(reason) = termination.check(history(state)) {
+            break reason;
+        }
+    };
+
+    let history = history(state);
+    RunReport {
+        reason,
+        generations: history.len() - start_generation,
+        best_fitness,
+        best_genotype,
+        statistics: history[history.len() - 1],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Population, benchmarks::OneMax};
+
+    #[test]
+    fn max_generations_counts_every_run_afresh() {
+        let mut termination = MaxGenerations::new(5);
+        let mut population = Population::<OneMax>::with_seed(20, 16, 1);
+        assert_eq!(population.run(&(), &mut termination).generations, 5);
+        // the same population continued, and a population with a shorter history
+        assert_eq!(population.run(&(), &mut termination).generations, 5);
+        let mut population = Population::<OneMax>::with_seed(20, 16, 2);
+        assert_eq!(population.run(&(), &mut termination).generations, 5);
+    }
+
+    #[test]
+    fn max_generations_checked_by_hand_counts_from_the_first_check() {
+        let history = vec![Statistics::from_fitness(0, &[1.0]); 10];
+        let mut termination = MaxGenerations::new(3);
+        assert_eq!(termination.check(&history[..8]), None);
+        assert_eq!(termination.check(&history[..9]), None);
+        assert_eq!(
+            termination.check(&history[..10]),
+            Some(StopReason::MaxGenerations)
+        );
+        // a shorter history must not underflow
+        assert_eq!(termination.check(&history[..2]), None);
+    }
+
+    #[test]
+    fn stagnation_restarts_with_every_run() {
+        let mut termination = Stagnation::new(3);
+        let mut population = Population::<OneMax>::with_seed(10, 8, 3);
+        // the first run converges, so the second one improves on nothing
+        for _ in 0..2 {
+            let report = population.run(&(), (&mut termination).or(MaxGenerations::new(100)));
+            assert_eq!(report.reason, StopReason::Stagnation);
+            assert!(report.generations > 3, "{}", report.generations);
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! # Hyperparameter Tuning
+//!
+//! A meta-GA that searches for good settings of a `Population`: population
+//! size, mutation rate, tournament size, elitism and crossover operator.
+//!
+//! The settings are the genotype of an outer population, one gene per
+//! setting that picks one of the candidate values of the [`Tuner`]. The
+//! fitness of a candidate is the mean best fitness of several runs of the
+//! inner population, each with another seed and the same budget of fitness
+//! evaluations, so that small and large populations are compared fairly.
+//! All candidates share the same seeds, and every candidate is run only once
+//! even if the outer population evaluates it again.
+//!
+//! ```no_run
+//! # use genetic::{crossover, tuning::Tuner, benchmarks::OneMax};
+//! let crossovers: [(&str, crossover::Operator<bool>); 2] = [
+//!     ("uniform", |a, b, c1, c2, n, rng| crossover::uniform(a, b, c1, c2, n, rng)),
+//!     ("single", |a, b, c1, c2, n, rng| crossover::single_split(a, b, c1, c2, n, rng)),
+//! ];
+//! let tuning = Tuner::<OneMax>::new((), 100)
+//!     .with_crossovers(&crossovers)
+//!     .with_runs(10)
+//!     .with_budget(5_000)
+//!     .tune(20, 30, 0);
+//! let best = tuning.best().expect("at least one candidate was run");
+//! println!("{:?}: {:.1} ± {:.1}", best.settings, best.mean, best.half_width);
+//! ```
+//!
+//! The mutation rate is handed to `Genome::mutate_with_rate` through a
+//! constant mutation schedule. Genomes that ignore the rate should leave the
+//! candidate rates at their default of a single value.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use rand::prelude::*;
+
+use crate::{
+    Evaluator, Gene, Genome, Individual, Population, crossover, fitness, mutation,
+    selection::Tournament, termination::MaxEvaluations,
+};
+
+/// One combination of settings.
+#[derive(Clone, Copy)]
+pub struct Settings<G> {
+    pub population_size: usize,
+    pub mutation_rate: f64,
+    pub tournament_size: usize,
+    pub elitism: usize,
+    /// The name of the crossover operator, `"genome"` for [`Genome::crossover`].
+    pub crossover: &'static str,
+    operator: Option<crossover::Operator<G>>,
+}
+
+// The operator is left out, its address says nothing.
+impl<G> std::fmt::Debug for Settings<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Settings")
+            .field("population_size", &self.population_size)
+            .field("mutation_rate", &self.mutation_rate)
+            .field("tournament_size", &self.tournament_size)
+            .field("elitism", &self.elitism)
+            .field("crossover", &self.crossover)
+            .finish()
+    }
+}
+
+impl<G> Settings<G> {
+    /// The crossover operator, `None` for [`Genome::crossover`].
+    pub fn operator(&self) -> Option<crossover::Operator<G>> {
+        self.operator
+    }
+}
+
+/// The runs of one candidate.
+#[derive(Debug, Clone)]
+pub struct Trial<G> {
+    pub settings: Settings<G>,
+    /// The best fitness of every run, in the order of the seeds.
+    pub runs: Vec<f64>,
+    pub mean: f64,
+    /// Half the width of the 95% confidence interval of the mean, from
+    /// Student's t-distribution. Infinite for a single run.
+    pub half_width: f64,
+}
+
+impl<G> Trial<G> {
+    fn new(settings: Settings<G>, runs: Vec<f64>) -> Self {
+        let n = runs.len() as f64;
+        let mean = runs.iter().sum::<f64>() / n;
+        let half_width = if runs.len() > 1 {
+            let variance = runs.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            t_quantile(runs.len() - 1) * (variance / n).sqrt()
+        } else {
+            f64::INFINITY
+        };
+        Self {
+            settings,
+            runs,
+            mean,
+            half_width,
+        }
+    }
+
+    /// The 95% confidence interval of the mean.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        (self.mean - self.half_width, self.mean + self.half_width)
+    }
+}
+
+/// The 97.5% quantile of Student's t-distribution with `df` degrees of
+/// freedom, approximated by the normal distribution above 30.
+fn t_quantile(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    TABLE.get(df.wrapping_sub(1)).copied().unwrap_or(1.960)
+}
+
+/// The candidate values of every setting and how candidates are compared.
+pub struct Tuner<E: Evaluator> {
+    param: E::FitnessParam,
+    genotype_size: usize,
+    population_sizes: Vec<usize>,
+    mutation_rates: Vec<f64>,
+    tournament_sizes: Vec<usize>,
+    elitism: Vec<usize>,
+    crossovers: Vec<(&'static str, Option<crossover::Operator<Gene<E>>>)>,
+    runs: u64,
+    budget: usize,
+    /// The runs of every candidate evaluated so far, by the index of each
+    /// of its settings.
+    trials: RefCell<HashMap<[usize; 5], Vec<f64>>>,
+}
+
+impl<E: Evaluator> Tuner<E> {
+    /// Tunes populations of `genotype_size` genes, evaluated with `param`.
+    pub fn new(param: E::FitnessParam, genotype_size: usize) -> Self {
+        Self {
+            param,
+            genotype_size,
+            population_sizes: vec![20, 50, 100, 200],
+            mutation_rates: vec![0.001, 0.01, 0.05, 0.1, 0.2],
+            tournament_sizes: vec![2, 3, 5, 7],
+            elitism: vec![0, 1, 2, 5],
+            crossovers: vec![("genome", None)],
+            runs: 10,
+            budget: 10_000,
+            trials: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Defaults to 20, 50, 100 and 200.
+    pub fn with_population_sizes(mut self, sizes: &[usize]) -> Self {
+        self.population_sizes = sizes.iter().map(|&size| size.max(2)).collect();
+        self
+    }
+
+    /// Defaults to 0.001, 0.01, 0.05, 0.1 and 0.2.
+    pub fn with_mutation_rates(mut self, rates: &[f64]) -> Self {
+        self.mutation_rates = rates.to_vec();
+        self
+    }
+
+    /// Defaults to 2, 3, 5 and 7.
+    pub fn with_tournament_sizes(mut self, sizes: &[usize]) -> Self {
+        self.tournament_sizes = sizes.to_vec();
+        self
+    }
+
+    /// Defaults to 0, 1, 2 and 5. Capped at the population size.
+    pub fn with_elitism(mut self, elitism: &[usize]) -> Self {
+        self.elitism = elitism.to_vec();
+        self
+    }
+
+    /// Named crossover operators to choose from. Defaults to
+    /// [`Genome::crossover`] only, named `"genome"`.
+    pub fn with_crossovers(
+        mut self,
+        crossovers: &[(&'static str, crossover::Operator<Gene<E>>)],
+    ) -> Self {
+        self.crossovers = crossovers
+            .iter()
+            .map(|&(name, operator)| (name, Some(operator)))
+            .collect();
+        self
+    }
+
+    /// The number of runs, with the seeds `0..runs`, per candidate. Defaults
+    /// to 10.
+    pub fn with_runs(mut self, runs: u64) -> Self {
+        self.runs = runs.max(1);
+        self
+    }
+
+    /// The fitness evaluations of every run. Defaults to 10000.
+    pub fn with_budget(mut self, evaluations: usize) -> Self {
+        self.budget = evaluations;
+        self
+    }
+
+    /// Evolves `size` candidates over `generations` generations, starting
+    /// from `seed`, and returns every candidate that was run.
+    ///
+    /// # Panics
+    /// Panics if the candidate values of a setting are empty.
+    pub fn tune(self, size: usize, generations: usize, seed: u64) -> Tuning<Gene<E>> {
+        assert!(
+            !(self.population_sizes.is_empty()
+                || self.mutation_rates.is_empty()
+                || self.tournament_sizes.is_empty()
+                || self.elitism.is_empty()
+                || self.crossovers.is_empty()),
+            "every setting needs at least one candidate value"
+        );
+
+        let mut meta = Population::<Candidate<E>>::with_seed(size, 5, seed).with_elitism(1);
+        for _ in 0..generations {
+            meta.evolve(&self);
+        }
+
+        let mut trials: Vec<Trial<Gene<E>>> = self
+            .trials
+            .take()
+            .into_iter()
+            .map(|(choice, runs)| Trial::new(self.settings(choice), runs))
+            .collect();
+        trials.sort_by(|a, b| fitness::compare(b.mean, a.mean));
+        Tuning { trials }
+    }
+
+    /// The candidate value of every setting picked by the genes.
+    fn choose(&self, genes: &[f64]) -> [usize; 5] {
+        let lengths = [
+            self.population_sizes.len(),
+            self.mutation_rates.len(),
+            self.tournament_sizes.len(),
+            self.elitism.len(),
+            self.crossovers.len(),
+        ];
+        std::array::from_fn(|i| {
+            ((genes[i].clamp(0.0, 1.0) * lengths[i] as f64) as usize).min(lengths[i] - 1)
+        })
+    }
+
+    fn settings(&self, choice: [usize; 5]) -> Settings<Gene<E>> {
+        let (crossover, operator) = self.crossovers[choice[4]];
+        let population_size = self.population_sizes[choice[0]];
+        Settings {
+            population_size,
+            mutation_rate: self.mutation_rates[choice[1]],
+            tournament_size: self.tournament_sizes[choice[2]],
+            elitism: self.elitism[choice[3]].min(population_size),
+            crossover,
+            operator,
+        }
+    }
+
+    /// The mean best fitness of the runs of a candidate.
+    fn score(&self, choice: [usize; 5]) -> f64 {
+        if let Some(runs) = self.trials.borrow().get(&choice) {
+            return runs.iter().sum::<f64>() / runs.len() as f64;
+        }
+
+        let settings = self.settings(choice);
+        let runs: Vec<f64> = (0..self.runs)
+            .map(|seed| self.run(&settings, seed))
+            .collect();
+        let mean = runs.iter().sum::<f64>() / runs.len() as f64;
+        self.trials.borrow_mut().insert(choice, runs);
+        mean
+    }
+
+    fn run(&self, settings: &Settings<Gene<E>>, seed: u64) -> f64 {
+        let mut population: Population<E> =
+            Population::with_seed(settings.population_size, self.genotype_size, seed)
+                .with_elitism(settings.elitism)
+                .with_selection(Tournament::new(settings.tournament_size))
+                .with_mutation_schedule(mutation::Constant(settings.mutation_rate));
+        if let Some(operator) = settings.operator {
+            population = population.with_crossover(operator);
+        }
+        population
+            .run(&self.param, MaxEvaluations(self.budget))
+            .best_fitness
+    }
+}
+
+/// The result of [`Tuner::tune`].
+#[derive(Debug, Clone)]
+pub struct Tuning<G> {
+    /// Every candidate that was run, the best mean first.
+    pub trials: Vec<Trial<G>>,
+}
+
+impl<G> Tuning<G> {
+    /// The candidate with the best mean, `None` if no candidate was run,
+    /// e.g. for zero generations.
+    pub fn best(&self) -> Option<&Trial<G>> {
+        self.trials.first()
+    }
+
+    /// The candidates whose confidence interval overlaps the one of the best
+    /// candidate, i.e. the ones the runs cannot tell apart from it, the best
+    /// mean first.
+    pub fn contenders(&self) -> Vec<&Trial<G>> {
+        let Some(best) = self.best() else {
+            return Vec::new();
+        };
+        let (low, high) = best.confidence_interval();
+        self.trials
+            .iter()
+            .filter(|trial| {
+                let (trial_low, trial_high) = trial.confidence_interval();
+                trial_high >= low && trial_low <= high
+            })
+            .collect()
+    }
+}
+
+/// The genes of a candidate, one per setting in `0..=1`.
+#[derive(Debug)]
+struct Choices;
+
+impl Genome for Choices {
+    type Gene = f64;
+
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [f64], rng: &mut R) {
+        mutation::gaussian(genotype, 0.3, 0.2, rng);
+        for gene in genotype.iter_mut() {
+            *gene = gene.clamp(0.0, 1.0);
+        }
+    }
+
+    fn crossover<R: Rng + ?Sized>(
+        parent1: &[f64],
+        parent2: &[f64],
+        child1: &mut [f64],
+        child2: &mut [f64],
+        size: usize,
+        rng: &mut R,
+    ) {
+        crossover::uniform(parent1, parent2, child1, child2, size, rng);
+    }
+}
+
+/// An individual of the outer population.
+struct Candidate<E> {
+    fitness: f64,
+    _phantom: PhantomData<fn() -> E>,
+}
+
+impl<E: Evaluator> Evaluator for Candidate<E> {
+    type Genome = Choices;
+    type FitnessParam = Tuner<E>;
+
+    fn new(_individual: Individual) -> Self {
+        Self {
+            fitness: 0.0,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn evaluate(&mut self, genotype: &[f64], tuner: &Tuner<E>) {
+        self.fitness = tuner.score(tuner.choose(genotype));
+    }
+
+    fn fitness(&self) -> f64 {
+        self.fitness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::OneMax;
+
+    fn trial(runs: &[f64]) -> Trial<bool> {
+        let settings = Settings {
+            population_size: 2,
+            mutation_rate: 0.0,
+            tournament_size: 2,
+            elitism: 0,
+            crossover: "genome",
+            operator: None,
+        };
+        Trial::new(settings, runs.to_vec())
+    }
+
+    #[test]
+    fn contenders_overlap_the_best_interval() {
+        let tuning = Tuning {
+            trials: vec![
+                trial(&[10.0, 10.1, 9.9, 10.0]),
+                // narrow and below the best interval
+                trial(&[9.5, 9.5, 9.5, 9.5]),
+                // wide enough to reach the best interval
+                trial(&[5.0, 13.0, 5.0, 13.0]),
+            ],
+        };
+        let contenders: Vec<f64> = tuning.contenders().iter().map(|t| t.mean).collect();
+        assert_eq!(contenders, [10.0, 9.0]);
+    }
+
+    #[test]
+    fn no_best_without_candidates() {
+        let tuner = || Tuner::<OneMax>::new((), 10).with_runs(2).with_budget(100);
+        for tuning in [tuner().tune(4, 0, 0), tuner().tune(0, 3, 0)] {
+            assert!(tuning.best().is_none());
+            assert!(tuning.contenders().is_empty());
+        }
+    }
+}
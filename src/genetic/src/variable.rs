@@ -0,0 +1,372 @@
+//! # Variable-Length Genomes
+//!
+//! A [`Population`](crate::Population) stores all genotypes in one arena of
+//! fixed-size slots, so every genome has to be as long as the longest one
+//! could ever need to be. A [`VariablePopulation`] keeps a separate genome
+//! per individual instead, whose length evolves along with its genes:
+//!
+//! - Crossover is [`crossover::cut_and_splice`], which cuts both parents at
+//!   independent points, so the children may be longer or shorter than
+//!   their parents.
+//! - After [`Genome::mutate`], [`mutation::insertion`] and
+//!   [`mutation::deletion`] add or remove a single gene.
+//!
+//! The lengths are kept within the bounds set with
+//! [`VariablePopulation::with_length_bounds`]. `Genome::crossover` is not
+//! used, and `Evaluator::evaluate` receives genotypes of varying length.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    Evaluator, Gene, Genome, GenotypeInitializer, Individual, crossover, fitness, mutation,
+    selection::{Selection, Tournament},
+    statistics::{Observer, Observers, Statistics},
+    termination::{self, RunReport, Termination},
+};
+
+/// A population of phenotypes whose genotypes differ in length.
+#[derive(Debug)]
+pub struct VariablePopulation<E: Evaluator, R: Rng = ChaCha8Rng> {
+    genotypes: Vec<Vec<Gene<E>>>,
+    next_genotypes: Vec<Vec<Gene<E>>>,
+    phenotypes: Vec<E>,
+    min_length: usize,
+    max_length: usize,
+    insertion_rate: f64,
+    deletion_rate: f64,
+    rng: R,
+    selection: Box<dyn Selection>,
+    fitness: Vec<f64>,
+    parents: Vec<usize>,
+    elitism: usize,
+    evaluations: usize,
+    generation: usize,
+    history: Vec<Statistics>,
+    observers: Observers,
+}
+
+impl<E: Evaluator, R: Rng + SeedableRng> VariablePopulation<E, R> {
+    /// Creates a population seeded from the thread-local entropy source.
+    pub fn new(phenotype_size: usize, initial_length: usize) -> Self {
+        Self::with_rng(
+            phenotype_size,
+            initial_length,
+            R::from_rng(&mut rand::rng()),
+        )
+    }
+
+    /// Creates a population whose whole run is determined by `seed`.
+    pub fn with_seed(phenotype_size: usize, initial_length: usize, seed: u64) -> Self {
+        Self::with_rng(phenotype_size, initial_length, R::seed_from_u64(seed))
+    }
+}
+
+impl<E: Evaluator, R: Rng> VariablePopulation<E, R> {
+    /// Creates a population driven by the given random number generator.
+    /// Every genotype starts out with `initial_length` genes.
+    pub fn with_rng(phenotype_size: usize, initial_length: usize, mut rng: R) -> Self {
+        let genotypes = (0..phenotype_size)
+            .map(|_| {
+                let mut genotype = vec![Gene::<E>::default(); initial_length];
+                Gene::<E>::initial_genotypes(&mut genotype, &mut rng);
+                genotype
+            })
+            .collect();
+
+        Self {
+            genotypes,
+            next_genotypes: vec![Vec::new(); phenotype_size],
+            phenotypes: (0..phenotype_size).map(|i| E::new(Individual(i))).collect(),
+            min_length: 1.min(initial_length),
+            max_length: usize::MAX,
+            insertion_rate: 0.1,
+            deletion_rate: 0.1,
+            rng,
+            selection: Box::new(Tournament::default()),
+            fitness: vec![0.0; phenotype_size],
+            parents: Vec::new(),
+            elitism: 1.min(phenotype_size),
+            evaluations: 0,
+            generation: 0,
+            history: Vec::new(),
+            observers: Observers::default(),
+        }
+    }
+
+    /// Sets the shortest and the longest allowed genotype. Longer children
+    /// are truncated, shorter ones are padded with new random genes, and so
+    /// are the current genotypes. Defaults to at least 1 gene and no upper
+    /// bound.
+    ///
+    /// # Panics
+    /// Panics if `min > max`.
+    pub fn with_length_bounds(mut self, min: usize, max: usize) -> Self {
+        assert!(min <= max, "the minimum length must not exceed the maximum");
+        self.min_length = min;
+        self.max_length = max;
+        for genotype in self.genotypes.iter_mut() {
+            Self::bound_length(genotype, min, max, &mut self.rng);
+        }
+        self
+    }
+
+    /// Sets the probability that a child gains a gene, see
+    /// [`mutation::insertion`]. Defaults to 0.1.
+    pub fn with_insertion_rate(mut self, rate: f64) -> Self {
+        self.insertion_rate = rate;
+        self
+    }
+
+    /// Sets the probability that a child loses a gene, see
+    /// [`mutation::deletion`]. Defaults to 0.1.
+    pub fn with_deletion_rate(mut self, rate: f64) -> Self {
+        self.deletion_rate = rate;
+        self
+    }
+
+    /// Replaces the parent selection strategy. Defaults to a tournament of size 3.
+    pub fn with_selection(mut self, selection: impl Selection + 'static) -> Self {
+        self.selection = Box::new(selection);
+        self
+    }
+
+    /// Sets the number of the fittest individuals that are copied unchanged
+    /// into the next generation. Defaults to 1, capped at the population size.
+    pub fn with_elitism(mut self, count: usize) -> Self {
+        self.elitism = count.min(self.phenotypes.len());
+        self
+    }
+
+    /// Registers an observer that is notified after every evaluated generation.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.0.push(Box::new(observer));
+    }
+
+    pub fn evolve(&mut self, param: &E::FitnessParam) {
+        // 1. Calculate fitness for the current generation.
+        self.calculate_fitness(param);
+        self.record_statistics();
+
+        // 2. Select parents and create the next generation.
+        self.copy_elite();
+        self.create_offspring();
+
+        // 3. Swap. The new generation is now the current one.
+        std::mem::swap(&mut self.genotypes, &mut self.next_genotypes);
+    }
+
+    /// Evolves the population until `termination` is satisfied, see
+    /// [`Population::run`](crate::Population::run).
+    pub fn run(
+        &mut self,
+        param: &E::FitnessParam,
+        termination: impl Termination,
+    ) -> RunReport<Gene<E>> {
+        termination::drive(
+            self,
+            |population| population.evolve(param),
+            |population| &population.history,
+            // after `evolve` the evaluated generation lives in `next_genotypes`
+            |population, index| &population.next_genotypes[index],
+            termination,
+        )
+    }
+
+    fn calculate_fitness(&mut self, param: &E::FitnessParam) {
+        for (p, genotype) in self.phenotypes.iter_mut().zip(&self.genotypes) {
+            p.evaluate(genotype, param);
+        }
+        self.evaluations += self.phenotypes.len();
+        self.fitness.clear();
+        self.fitness
+            .extend(self.phenotypes.iter().map(|p| p.fitness()));
+        fitness::sanitize(&mut self.fitness);
+    }
+
+    /// Summarises the evaluated generation, appends it to the history and
+    /// notifies the observers.
+    fn record_statistics(&mut self) {
+        let mut statistics = Statistics::from_fitness(self.generation, &self.fitness);
+        if let Some(elite) = self.genotypes.get(statistics.elite_index) {
+            statistics.diversity = diversity(&self.genotypes, elite);
+        }
+        statistics.evaluations = self.evaluations;
+        self.observers.notify(&statistics);
+        self.history.push(statistics);
+        self.generation += 1;
+    }
+
+    /// Copies the fittest genotypes into the first slots of the next
+    /// generation, the fittest first.
+    fn copy_elite(&mut self) {
+        let mut order: Vec<usize> = (0..self.fitness.len()).collect();
+        order.sort_by(|&a, &b| fitness::compare(self.fitness[b], self.fitness[a]));
+
+        for (slot, &elite) in self.next_genotypes[..self.elitism].iter_mut().zip(&order) {
+            slot.clone_from(&self.genotypes[elite]);
+        }
+    }
+
+    /// Fills all slots after the elites with children, two for every pair of
+    /// selected parents. An odd number of children is rounded up to a full
+    /// pair and the surplus child is discarded.
+    fn create_offspring(&mut self) {
+        let children = self.phenotypes.len() - self.elitism;
+        self.parents.resize(children.div_ceil(2) * 2, 0);
+        self.selection
+            .select(&self.fitness, &mut self.parents, &mut self.rng);
+
+        let mut discarded = Vec::new();
+        for (slots, pair) in self.next_genotypes[self.elitism..]
+            .chunks_mut(2)
+            .zip(self.parents.chunks_exact(2))
+        {
+            let (child1, child2) = match slots {
+                [child1, child2] => (child1, child2),
+                [child1] => (child1, &mut discarded),
+                _ => unreachable!("chunks hold one or two slots"),
+            };
+            crossover::cut_and_splice(
+                &self.genotypes[pair[0]],
+                &self.genotypes[pair[1]],
+                child1,
+                child2,
+                &mut self.rng,
+            );
+            for child in [child1, child2] {
+                Self::bound_length(child, self.min_length, self.max_length, &mut self.rng);
+                E::Genome::mutate(child, &mut self.rng);
+                mutation::insertion(child, self.insertion_rate, self.max_length, &mut self.rng);
+                mutation::deletion(child, self.deletion_rate, self.min_length, &mut self.rng);
+            }
+        }
+    }
+
+    /// Truncates `genotype` to `max` genes or pads it with random genes up to
+    /// `min` genes.
+    fn bound_length(genotype: &mut Vec<Gene<E>>, min: usize, max: usize, rng: &mut R) {
+        let len = genotype.len();
+        if len > max {
+            genotype.truncate(max);
+        } else if len < min {
+            genotype.resize(min, Gene::<E>::default());
+            Gene::<E>::initial_genotypes(&mut genotype[len..], rng);
+        }
+    }
+
+    /// The phenotype with the highest fitness according to [`fitness::compare`].
+    pub fn fittest_phenotype(&self) -> Option<&E> {
+        fitness::argmax(self.phenotypes.iter().map(|p| p.fitness()))
+            .map(|index| &self.phenotypes[index])
+    }
+
+    /// The number of generations evaluated so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The statistics of every evaluated generation, oldest first.
+    ///
+    /// The diversity counts the genes missing from the shorter of two
+    /// genotypes as differing.
+    pub fn history(&self) -> &[Statistics] {
+        &self.history
+    }
+
+    /// The statistics of the most recently evaluated generation.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.history.last()
+    }
+
+    /// The mean number of genes of the current genotypes.
+    pub fn mean_length(&self) -> f64 {
+        if self.genotypes.is_empty() {
+            return 0.0;
+        }
+        let genes: usize = self.genotypes.iter().map(Vec::len).sum();
+        genes as f64 / self.genotypes.len() as f64
+    }
+
+    pub fn get_phenotypes(&self) -> &[E] {
+        &self.phenotypes
+    }
+    pub fn get_phenotypes_mut(&mut self) -> &mut [E] {
+        &mut self.phenotypes
+    }
+
+    /// The genotype of the given phenotype.
+    ///
+    /// # Panics
+    /// Panics if the phenotype's index is out of bounds for the current population size.
+    pub fn get_genotype(&self, phenotype: &E) -> &[Gene<E>] {
+        &self.genotypes[crate::slot_of(&self.phenotypes, phenotype)]
+    }
+
+    /// Iterates over each phenotype mutably, providing its corresponding genotype.
+    pub fn for_each_phenotype_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut E, &[Gene<E>]),
+    {
+        for (p, genotype) in self.phenotypes.iter_mut().zip(&self.genotypes) {
+            func(p, genotype);
+        }
+    }
+}
+
+/// Mean fraction of positions in which the genotypes differ from `elite`,
+/// where a position present in only one of the two counts as differing.
+fn diversity<G: PartialEq>(genotypes: &[Vec<G>], elite: &[G]) -> f64 {
+    let (differing, positions) =
+        genotypes
+            .iter()
+            .fold((0, 0), |(differing, positions), genotype| {
+                let common = genotype.len().min(elite.len());
+                let longest = genotype.len().max(elite.len());
+                let mismatches = genotype.iter().zip(elite).filter(|(a, b)| a != b).count();
+                (
+                    differing + mismatches + longest - common,
+                    positions + longest,
+                )
+            });
+    if positions == 0 {
+        return 0.0;
+    }
+    differing as f64 / positions as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarks::OneMax;
+
+    #[test]
+    fn lengths_stay_within_bounds() {
+        let mut population = VariablePopulation::<OneMax>::with_seed(20, 3, 1)
+            .with_length_bounds(5, 12)
+            .with_insertion_rate(0.5)
+            .with_deletion_rate(0.5);
+        let within = |population: &VariablePopulation<OneMax>| {
+            population
+                .genotypes
+                .iter()
+                .all(|genotype| (5..=12).contains(&genotype.len()))
+        };
+        assert!(within(&population));
+        for _ in 0..50 {
+            population.evolve(&());
+            assert!(within(&population));
+        }
+    }
+
+    #[test]
+    fn cut_and_splice_preserves_the_total_length() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let (mut child1, mut child2) = (Vec::new(), Vec::new());
+        for (len1, len2) in [(0, 0), (0, 4), (3, 7), (10, 1)] {
+            let (parent1, parent2) = (vec![1u8; len1], vec![2u8; len2]);
+            crossover::cut_and_splice(&parent1, &parent2, &mut child1, &mut child2, &mut rng);
+            assert_eq!(child1.len() + child2.len(), len1 + len2);
+        }
+    }
+}
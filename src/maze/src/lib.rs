@@ -1,3 +1,4 @@
+#[cfg(target_arch = "wasm32")]
 mod color;
 pub mod generator;
 pub mod solver;
@@ -402,7 +403,9 @@ pub struct State {
     uniform_bind_group_layout: wgpu::BindGroupLayout,
     colors: Colors,
     maze_buffer: wgpu::Buffer,
+    // only the egui panel sends events
     #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(not(feature = "egui"), allow(dead_code))]
     proxy: winit::event_loop::EventLoopProxy<UserEvent>,
     #[cfg(not(target_arch = "wasm32"))]
     start_time: std::time::Instant,
@@ -1024,7 +1027,7 @@ impl State {
 }
 
 pub enum UserEvent {
-    StateInitialized(State),
+    StateInitialized(Box<State>),
     GenerateMaze,
     SolveMaze,
     Generator(MazeAlgorithm),
@@ -1344,7 +1347,7 @@ impl ApplicationHandler<UserEvent> for App {
                         .expect("Unable to create canvas!!!");
                     assert!(
                         proxy
-                            .send_event(UserEvent::StateInitialized(new_state))
+                            .send_event(UserEvent::StateInitialized(Box::new(new_state)))
                             .is_ok()
                     );
                 });
@@ -1364,7 +1367,7 @@ impl ApplicationHandler<UserEvent> for App {
                     );
                     initial_state.window.request_redraw();
                 }
-                self.state = Some(initial_state);
+                self.state = Some(*initial_state);
             }
             UserEvent::GenerateMaze => {
                 if let Some(state) = &mut self.state {
@@ -36,15 +36,6 @@ impl Djikstra {
         }
     }
 
-    fn get_max_weight(&self) -> usize {
-        self.weights
-            .iter()
-            .flatten()
-            .max_by(|a, b| a.weight.cmp(&b.weight))
-            .unwrap()
-            .weight
-    }
-
     fn search_path(&mut self, board: &mut Board) -> MazeState {
         let mut next_cells: Vec<usize> = vec![];
         for index in &self.positions {
@@ -1,7 +1,11 @@
-use rand::{distr::StandardUniform, prelude::*};
+use rand::prelude::*;
 
 use crate::{Board, Direction, Solver, MazeState};
-use genetic::{GenotypeInitializer, Phenotype, Population, crossover};
+use genetic::{
+    Phenotype, Population,
+    constraint::{Constrained, ConstraintHandling},
+    crossover, mutation,
+};
 
 const POPULATION_SIZE: usize = 1000;
 const MUTATION_RATE: f64 = 0.02;
@@ -12,7 +16,7 @@ const DEAD_ENDS: f64 = 10.0;
 const BACKWALK_PENALTY: f64 = 0.5;
 const LENGTH_PENALTY: f64 = 0.01;
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub enum Move {
     #[default]
     Forward,
@@ -20,24 +24,7 @@ pub enum Move {
     Right,
 }
 
-impl Distribution<Move> for StandardUniform {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Move {
-        let index: u8 = rng.random_range(0..3);
-        match index {
-            0 => Move::Forward,
-            1 => Move::Left,
-            2 => Move::Right,
-            _ => unreachable!(),
-        }
-    }
-}
-impl GenotypeInitializer for Move {
-    fn initial_genotypes(genotype: &mut [Self], rng: &mut ThreadRng) {
-        for gene in genotype.iter_mut() {
-            *gene = rng.random();
-        }
-    }
-}
+genetic::enum_gene!(Move { Forward, Left, Right });
 
 pub struct Maze {
     board_size: usize,
@@ -92,7 +79,6 @@ impl Phenotype for PathEvolver {
             (maze.board_size as f64 * 2.0 - manhattan_distance_to_end) * DISTANCE;
 
         fitness_score += self.path.len() as f64 * 2.0;
-        fitness_score -= self.missed_steps.len() as f64 * MISSED_STEPS;
         fitness_score -= self.dead_ends as f64 * DEAD_ENDS;
         fitness_score -= self.backwalks.len() as f64 * BACKWALK_PENALTY;
         fitness_score -= self.path.len() as f64 * LENGTH_PENALTY;
@@ -101,25 +87,27 @@ impl Phenotype for PathEvolver {
             fitness_score += 1_000.0;
         }
 
-        self.calc_fitness = fitness_score.max(0.0)
+        // Not clamped at 0: the population subtracts the penalty for missed
+        // steps afterwards, and clamping before it would rank every path with
+        // a negative score the same, no matter how often it hit a wall.
+        self.calc_fitness = fitness_score
     }
 
-    fn mutate(genotype: &mut [Move], rng: &mut ThreadRng) {
-        for gene in genotype.iter_mut() {
-            if rng.random_bool(MUTATION_RATE) {
-                let random_move: Move = rand::random();
-                *gene = random_move;
-            }
-        }
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [Move], rng: &mut R) {
+        mutation::random_reset(genotype, MUTATION_RATE, rng);
     }
 
-    fn crossover(
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [Move], rate: f64, rng: &mut R) {
+        mutation::random_reset(genotype, rate, rng);
+    }
+
+    fn crossover<R: Rng + ?Sized>(
         parent1: &[Move],
         parent2: &[Move],
         child1: &mut [Move],
         child2: &mut [Move],
         size: usize,
-        rng: &mut ThreadRng,
+        rng: &mut R,
     ) {
         crossover::double_split(parent1, parent2, child1, child2, size, rng);
     }
@@ -147,15 +135,25 @@ impl Phenotype for PathEvolver {
     }
 }
 
+/// Walking into a wall is penalised by the population, not in the fitness.
+impl Constrained for PathEvolver {
+    fn violation(&self) -> f64 {
+        self.missed_steps.len() as f64
+    }
+}
+
 pub struct Genetic<T: Phenotype> {
     population: Population<T>,
     steps: usize,
     maze: Maze,
 }
 
-impl<PathEvolver: Phenotype> Genetic<PathEvolver> {
+impl Genetic<PathEvolver> {
     pub fn new(board: &Board) -> Self {
-        let population = Population::<PathEvolver>::new(POPULATION_SIZE, board.board_size.pow(2));
+        let population = Population::<PathEvolver>::new(POPULATION_SIZE, board.board_size.pow(2))
+            .with_constraints(ConstraintHandling::Penalty {
+                coefficient: MISSED_STEPS,
+            });
 
         Self {
             population,
@@ -268,7 +266,8 @@ impl Genetic<PathEvolver> {
 
 impl Solver for Genetic<PathEvolver> {
     fn step(&mut self, board: &mut Board) -> Result<MazeState, String> {
-        self.population.for_each_phenotype_mut(|p, genotype| {
+        let board = &*board;
+        let simulate = |p: &mut PathEvolver, genotype: &[Move]| {
             p.reset();
             for (step_index, current_move) in genotype.iter().enumerate() {
                 if p.reached_end {
@@ -276,18 +275,22 @@ impl Solver for Genetic<PathEvolver> {
                 }
                 Genetic::move_floor(p, step_index, board, current_move);
             }
-        });
+        };
 
-        self.population.evolve(&self.maze);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.population.par_for_each_phenotype_mut(simulate);
+            self.population.par_evolve(&self.maze);
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.population.for_each_phenotype_mut(simulate);
+            self.population.evolve(&self.maze);
+        }
         self.steps += 1;
 
         // check the fittest
-        let winner = self
-            .population
-            .get_phenotypes()
-            .iter()
-            .max_by(|a, b| a.get_fitness().partial_cmp(&b.get_fitness()).unwrap())
-            .unwrap();
+        let winner = self.population.fittest_phenotype().unwrap();
 
         if winner.reached_end {
             Ok(MazeState::Done)
@@ -297,12 +300,7 @@ impl Solver for Genetic<PathEvolver> {
     }
 
     fn get_path(&self) -> &Vec<usize> {
-        let winner = self
-            .population
-            .get_phenotypes()
-            .iter()
-            .max_by(|a, b| a.get_fitness().partial_cmp(&b.get_fitness()).unwrap())
-            .unwrap();
+        let winner = self.population.fittest_phenotype().unwrap();
 
         &winner.path
     }
@@ -1,7 +1,7 @@
 use crate::{solver::path, Board, Cell, Direction, MazeState, Solver, USE_WALL_FOLLOWER_PATH, WALL_BOTTOM, WALL_LEFT, WALL_RIGHT, WALL_TOP, WF_TURN_TOP_LEFT, WF_TURN_TOP_RIGHT, WF_TURN_BOTTOM_LEFT, WF_TURN_BOTTOM_RIGHT };
 
-const LINE_WIDTH: f32 = 1.0;
-
+// the walls passed are recorded, but not drawn yet
+#[allow(dead_code)]
 struct Wall {
     direction: Direction,
     cell: usize,
@@ -19,7 +19,6 @@ pub struct WallFollower {
     pub walk_path: Vec<usize>,
     walls: Vec<Wall>,
     direction: Direction,
-    distance: usize,
 }
 
 impl WallFollower {
@@ -31,7 +30,6 @@ impl WallFollower {
             walk_path: vec![0],
             walls: vec![Wall::new(Direction::East, 0)],
             direction: Direction::East,
-            distance: 4,
         }
     }
     fn wall_left(&self, cell: &Cell) -> bool {
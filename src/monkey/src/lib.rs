@@ -1,6 +1,12 @@
-use genetic::{Phenotype, Population, crossover};
+use std::marker::PhantomData;
 
-use rand::{distr::Uniform, prelude::*};
+use genetic::{
+    Phenotype, Population, crossover,
+    initializer::{Alphabet, Printable, Symbol},
+    mutation,
+};
+
+use rand::prelude::*;
 
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -18,59 +24,90 @@ pub struct GameState<E: Phenotype> {
     pub solved: bool,
 }
 
-impl GameState<StringEvolver> {
+/// Evolves strings over the alphabet `A`. The target must only contain
+/// symbols of the alphabet.
+///
+/// With a mutation rate of 1% most children are exact copies of a parent, so
+/// the population caches the fitness of the genotypes it has seen.
+impl<A: Alphabet> GameState<StringEvolver<A>> {
     pub fn new(target: String, population_size: usize) -> Self {
         let target_len = target.len();
         Self {
             target,
             generation: 1,
-            population: Population::<StringEvolver>::new(population_size, target_len),
+            population: Population::<StringEvolver<A>>::new(population_size, target_len)
+                .with_fitness_cache(population_size),
+            solved: false,
+        }
+    }
+
+    /// Creates a game state whose evolution is reproducible for a given `seed`.
+    pub fn with_seed(target: String, population_size: usize, seed: u64) -> Self {
+        let target_len = target.len();
+        Self {
+            target,
+            generation: 1,
+            population: Population::<StringEvolver<A>>::with_seed(
+                population_size,
+                target_len,
+                seed,
+            )
+            .with_fitness_cache(population_size),
             solved: false,
         }
     }
+
+    /// The fittest string of the generation evaluated last.
+    pub fn fittest_string(&self) -> (f64, String) {
+        let (fitness, genotype) = self.population.fittest();
+        let fittest = String::from_utf8_lossy(&Symbol::to_bytes(&genotype)).to_string();
+        (fitness, fittest)
+    }
 }
 
-pub struct StringEvolver {
+/// A string of symbols from the alphabet `A`, printable ASCII by default.
+pub struct StringEvolver<A: Alphabet = Printable> {
     index: usize,
     calc_fitness: f64,
+    _alphabet: PhantomData<A>,
 }
 
-impl Phenotype for StringEvolver {
-    type Gene = u8;
+impl<A: Alphabet> Phenotype for StringEvolver<A> {
+    type Gene = Symbol<A>;
     type FitnessParam = String;
 
     fn new(index: usize) -> Self {
         Self {
             index,
             calc_fitness: 0.0,
+            _alphabet: PhantomData,
         }
     }
 
-    fn fitness(&mut self, genotype: &[u8], target_string: &String) {
+    fn fitness(&mut self, genotype: &[Symbol<A>], target_string: &String) {
         self.calc_fitness = target_string
             .as_bytes()
             .iter()
             .zip(genotype)
-            .filter(|&(target_char, gene_char)| target_char == gene_char)
+            .filter(|&(target_char, gene_char)| *target_char == gene_char.0)
             .count() as f64;
     }
 
-    fn mutate(genotype: &mut [u8], rng: &mut ThreadRng) {
-        let char_range = Uniform::new_inclusive(32, 126).unwrap();
-        for gene in genotype.iter_mut() {
-            if rng.random_bool(MUTATION_RATE) {
-                *gene = rng.sample(char_range);
-            }
-        }
+    fn mutate<R: Rng + ?Sized>(genotype: &mut [Symbol<A>], rng: &mut R) {
+        mutation::random_reset(genotype, MUTATION_RATE, rng);
+    }
+
+    fn mutate_with_rate<R: Rng + ?Sized>(genotype: &mut [Symbol<A>], rate: f64, rng: &mut R) {
+        mutation::random_reset(genotype, rate, rng);
     }
 
-    fn crossover(
-        parent1: &[u8],
-        parent2: &[u8],
-        child1: &mut [u8],
-        child2: &mut [u8],
+    fn crossover<R: Rng + ?Sized>(
+        parent1: &[Symbol<A>],
+        parent2: &[Symbol<A>],
+        child1: &mut [Symbol<A>],
+        child2: &mut [Symbol<A>],
         size: usize,
-        rng: &mut ThreadRng,
+        rng: &mut R,
     ) {
         crossover::single_split(parent1, parent2, child1, child2, size, rng);
     }
@@ -95,7 +132,7 @@ pub async fn run_evolution_async(
     count_string: HtmlElement,
 ) {
     use std::time::Duration;
-    let mut game_state = GameState::new(target.to_string(), 500);
+    let mut game_state = GameState::<StringEvolver>::new(target.to_string(), 500);
     let mut _max_fitness = 0.0;
     let mut fittest: String = String::new();
 
@@ -107,8 +144,8 @@ pub async fn run_evolution_async(
         game_state.population.evolve(&game_state.target.to_string());
         game_state.generation += 1;
 
-        let (fitness, fittest_genotype) = game_state.population.fittest();
-        fittest = String::from_utf8_lossy(&fittest_genotype).to_string();
+        let fitness;
+        (fitness, fittest) = game_state.fittest_string();
         max_fitness.set_text_content(Some(&format!("{} / {}", fitness, target.len())));
         count_string.set_text_content(Some(&format!("{}", generation)));
 